@@ -0,0 +1,14 @@
+pub fn greet(name: &str) -> String {
+    format!("Hello, {name}!")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn greets() {
+        let unused = 1;
+        assert_eq!(greet("Ana"), "Hello, Ana!");
+    }
+}
@@ -0,0 +1,8 @@
+use std::io::{self, BufRead};
+
+fn main() {
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        println!("{}", lab01::greet(line.trim()));
+    }
+}
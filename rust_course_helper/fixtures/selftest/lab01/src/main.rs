@@ -0,0 +1,17 @@
+fn double(x: i32) -> i32 {
+    x * 2
+}
+
+fn main() {
+    println!("{}", double(21));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubles() {
+        assert_eq!(double(2), 4);
+    }
+}
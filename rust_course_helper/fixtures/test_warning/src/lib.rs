@@ -0,0 +1,14 @@
+pub fn add(a: u32, b: u32) -> u32 {
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds() {
+        let unused = 1;
+        assert_eq!(add(2, 2), 4);
+    }
+}
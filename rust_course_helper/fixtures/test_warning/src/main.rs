@@ -0,0 +1,3 @@
+fn main() {
+    println!("{}", lab01::add(2, 2));
+}
@@ -0,0 +1,48 @@
+//! Files checks produce that are worth keeping, like the formatting diff.
+//!
+//! With `--artifacts-dir`, each is written to `<dir>/<check>/<name>`, so the same artifact has
+//! the same path in every run. Otherwise nothing is written, and the report only has their
+//! sizes and hashes. Producers that need extra work for an artifact only do it when it's kept.
+
+use crate::Diags;
+use crate::sha256::{sha256, to_hex};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+
+pub struct Artifact {
+    pub check: &'static str,
+    pub name: String,
+    pub media_type: &'static str,
+    pub bytes: usize,
+    pub sha256: String,
+    /// Relative to the artifacts folder, once it's written there.
+    pub path: Option<Utf8PathBuf>,
+}
+
+/// Where artifacts are kept, if they are.
+pub fn kept(problems: &Diags) -> Option<&Utf8Path> {
+    problems.artifacts_dir.as_deref()
+}
+
+/// Adds an artifact from the current check to the report, writing it out if artifacts are kept.
+pub fn add(problems: &mut Diags, name: &str, media_type: &'static str, contents: &[u8]) {
+    let check = problems.current_check.unwrap_or("checker");
+    let mut path = None;
+    if let Some(dir) = &problems.artifacts_dir {
+        let relative = Utf8Path::new(check).join(name);
+        let full = dir.join(&relative);
+        let written = fs::create_dir_all(dir.join(check)).and_then(|_| fs::write(&full, contents));
+        match written {
+            Ok(()) => path = Some(relative),
+            Err(e) => problems.warn(format!("can't write the artifact {full}: {e}"), None, None),
+        }
+    }
+    problems.artifacts.push(Artifact {
+        check,
+        name: name.to_string(),
+        media_type,
+        bytes: contents.len(),
+        sha256: to_hex(&sha256(contents)),
+        path,
+    });
+}
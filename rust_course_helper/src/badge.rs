@@ -0,0 +1,141 @@
+//! `--badge`: a small SVG showing how many checks passed, for the student's README, and a JSON
+//! file next to it with the same numbers. Both are made locally, in the style of shields.io.
+//!
+//! The files are text, so the committed-files check doesn't mind them being committed.
+
+use crate::Diags;
+use crate::json::Json;
+use crate::state;
+use camino::Utf8Path;
+
+const LABEL: &str = "checks";
+/// Roughly the width of a character in 11px Verdana, which the badge is drawn with.
+const CHAR_WIDTH: usize = 7;
+const PADDING: usize = 10;
+
+fn color(passed: usize, total: usize) -> &'static str {
+    if total == 0 {
+        return "#9f9f9f";
+    }
+    match passed * 100 / total {
+        100 => "#4c1",
+        75.. => "#97ca00",
+        50.. => "#dfb317",
+        25.. => "#fe7d37",
+        _ => "#e05d44",
+    }
+}
+
+/// The badge for `passed` of `total` checks.
+pub fn svg(passed: usize, total: usize) -> String {
+    let value = format!("{passed}/{total} passing");
+    let label_width = LABEL.len() * CHAR_WIDTH + PADDING;
+    let value_width = value.len() * CHAR_WIDTH + PADDING;
+    let width = label_width + value_width;
+    let color = color(passed, total);
+    let label_x = label_width / 2;
+    let value_x = label_width + value_width / 2;
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{LABEL}: {value}">
+  <title>{LABEL}: {value}</title>
+  <linearGradient id="s" x2="0" y2="100%">
+    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+    <stop offset="1" stop-opacity=".1"/>
+  </linearGradient>
+  <clipPath id="r">
+    <rect width="{width}" height="20" rx="3" fill="#fff"/>
+  </clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{value_width}" height="20" fill="{color}"/>
+    <rect width="{width}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{LABEL}</text>
+    <text x="{label_x}" y="14">{LABEL}</text>
+    <text x="{value_x}" y="15" fill="#010101" fill-opacity=".3">{value}</text>
+    <text x="{value_x}" y="14">{value}</text>
+  </g>
+</svg>
+"##
+    )
+}
+
+/// Writes the badge to `path`, and its numbers to the same path with a `.json` extension.
+pub fn write(path: &Utf8Path, problems: &Diags) -> Result<(), String> {
+    let ran: Vec<_> = problems.checks.iter().filter(|x| x.ran()).collect();
+    let total = ran.len();
+    let passed = ran.iter().filter(|x| x.passed).count();
+    state::write_atomic(path, &svg(passed, total))?;
+
+    let sidecar = path.with_extension("json");
+    let json = Json::object([
+        ("passed", passed.into()),
+        ("total", total.into()),
+        ("color", color(passed, total).into()),
+    ]);
+    state::write_atomic(&sidecar, &format!("{json}\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::CheckStatus;
+    use crate::temp::TempDir;
+    use std::fs;
+    use std::time::Duration;
+
+    #[test]
+    fn badges_match_the_golden_files() {
+        let golden = [
+            (0, 0, include_str!("../fixtures/badge/0_of_0.svg")),
+            (1, 7, include_str!("../fixtures/badge/1_of_7.svg")),
+            (3, 4, include_str!("../fixtures/badge/3_of_4.svg")),
+            (10, 10, include_str!("../fixtures/badge/10_of_10.svg")),
+        ];
+        for (passed, total, expected) in golden {
+            // A checkout with CRLF line endings still has the same badge.
+            assert_eq!(svg(passed, total), expected.replace("\r\n", "\n"));
+        }
+    }
+
+    #[test]
+    fn colors_go_by_the_share_that_passed() {
+        assert_eq!(color(0, 0), "#9f9f9f");
+        assert_eq!(color(0, 4), "#e05d44");
+        assert_eq!(color(1, 4), "#fe7d37");
+        assert_eq!(color(2, 4), "#dfb317");
+        assert_eq!(color(3, 4), "#97ca00");
+        assert_eq!(color(99, 100), "#97ca00");
+        assert_eq!(color(4, 4), "#4c1");
+    }
+
+    #[test]
+    fn skipped_checks_dont_count() {
+        let dir = TempDir::new("badge_write").unwrap();
+        let mut problems = Diags::default();
+        for (name, passed, skipped) in [
+            ("fmt", true, None),
+            ("clippy", false, None),
+            ("branch", true, Some(crate::SkipReason::NotConfigured)),
+        ] {
+            problems.checks.push(CheckStatus {
+                name,
+                passed,
+                duration: Duration::ZERO,
+                partial: false,
+                skipped,
+                environment_failure: false,
+            });
+        }
+        let path = dir.path().join("badge.svg");
+        write(&path, &problems).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), svg(1, 2));
+        let json = fs::read_to_string(dir.path().join("badge.json")).unwrap();
+        assert_eq!(
+            json,
+            "{\n  \"passed\": 1,\n  \"total\": 2,\n  \"color\": \"#dfb317\"\n}\n"
+        );
+    }
+}
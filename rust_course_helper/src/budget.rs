@@ -0,0 +1,255 @@
+//! `--soft-budget`: how long a run is expected to take, and which checks took longer.
+//!
+//! Nothing is stopped when the budget runs out. At the end of the run, the budget is split
+//! between the checks that ran in proportion to how long each one is expected to take: the
+//! median of its recent runs on this repo, or a guess from its cost class when it has no
+//! history yet. Durations are kept in the state folder, so budgets adapt to each repo.
+
+use crate::checks::{CHECKS, Cost};
+use crate::state::{self, TIMINGS_FILE};
+use crate::toml::{self, Value};
+use crate::{CheckStatus, Diags};
+use camino::Utf8Path;
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+
+/// How many recent durations are kept for each check.
+const KEPT_RUNS: usize = 9;
+/// Seconds a check may go over its share before it's reported, so the noise in quick checks
+/// isn't.
+const SLACK: f64 = 1.0;
+
+/// Past durations of each check, in seconds, oldest first.
+pub type History = BTreeMap<String, Vec<f64>>;
+
+/// Expected duration of a check that has never run here.
+fn cost_estimate(cost: Cost) -> f64 {
+    match cost {
+        Cost::Cheap => 1.0,
+        Cost::Moderate => 5.0,
+        Cost::Expensive => 45.0,
+    }
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    let mut values = values.to_vec();
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    match values.len() {
+        0 => None,
+        n if n % 2 == 0 => Some((values[mid - 1] + values[mid]) / 2.0),
+        _ => Some(values[mid]),
+    }
+}
+
+fn expected(name: &str, history: &History) -> f64 {
+    if let Some(x) = history.get(name).and_then(|x| median(x)) {
+        return x;
+    }
+    let cost = CHECKS
+        .iter()
+        .find(|x| x.name == name)
+        .map_or(Cost::Cheap, |x| x.cost);
+    cost_estimate(cost)
+}
+
+/// Each check's share of `budget`, in seconds.
+pub fn shares(budget: f64, checks: &[&str], history: &History) -> Vec<f64> {
+    let expected: Vec<f64> = checks.iter().map(|x| expected(x, history)).collect();
+    let total: f64 = expected.iter().sum();
+    if total <= 0.0 {
+        let share = budget / checks.len().max(1) as f64;
+        return vec![share; checks.len()];
+    }
+    expected.iter().map(|x| budget * x / total).collect()
+}
+
+pub struct Overrun {
+    pub check: &'static str,
+    pub took: f64,
+    pub share: f64,
+}
+
+pub fn overruns(budget: f64, checks: &[CheckStatus], history: &History) -> Vec<Overrun> {
+    let checks: Vec<&CheckStatus> = checks.iter().filter(|x| x.ran()).collect();
+    let names: Vec<&str> = checks.iter().map(|x| x.name).collect();
+    checks
+        .iter()
+        .zip(shares(budget, &names, history))
+        .filter(|(x, share)| x.duration.as_secs_f64() > share + SLACK)
+        .map(|(x, share)| Overrun {
+            check: x.name,
+            took: x.duration.as_secs_f64(),
+            share,
+        })
+        .collect()
+}
+
+pub fn load_history(repo: &Utf8Path) -> History {
+    let path = state::state_path(repo, TIMINGS_FILE);
+    let Ok(table) = fs::read_to_string(path).map(|x| toml::parse(&x)) else {
+        return History::new();
+    };
+    // A broken file only loses the history.
+    let Some(checks) = table
+        .ok()
+        .and_then(|x| x.get("checks")?.as_table().cloned())
+    else {
+        return History::new();
+    };
+    checks
+        .into_iter()
+        .map(|(name, value)| {
+            let runs = value
+                .as_array()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|x| match x {
+                    Value::Float(x) => Some(*x),
+                    Value::Integer(x) => Some(*x as f64),
+                    _ => None,
+                })
+                .collect();
+            (name, runs)
+        })
+        .collect()
+}
+
+pub fn save_history(
+    repo: &Utf8Path,
+    mut history: History,
+    checks: &[CheckStatus],
+) -> Result<(), String> {
+    for check in checks.iter().filter(|x| x.ran()) {
+        let runs = history.entry(check.name.to_string()).or_default();
+        runs.push(check.duration.as_secs_f64());
+        let extra = runs.len().saturating_sub(KEPT_RUNS);
+        runs.drain(..extra);
+    }
+    let mut text =
+        String::from("# Recent check durations, in seconds, for `--soft-budget`.\n[checks]\n");
+    for (name, runs) in &history {
+        let runs: Vec<String> = runs.iter().map(|x| format!("{x:.3}")).collect();
+        text += &format!("{name} = [{}]\n", runs.join(", "));
+    }
+    let path = state::create_state_path(repo, TIMINGS_FILE)?;
+    state::write_atomic(&path, &text)
+}
+
+/// Reports the checks that went over their share, and warns if the whole run went over.
+pub fn report(problems: &mut Diags, budget: Duration, history: &History) {
+    let budget = budget.as_secs_f64();
+    let overruns = overruns(budget, &problems.checks, history);
+    if !overruns.is_empty() {
+        say!("checks over their share of the {budget:.0}s soft budget:");
+        for x in &overruns {
+            say!(
+                "  {:<24}{:>8.2}s, expected at most {:.2}s",
+                x.check,
+                x.took,
+                x.share
+            );
+        }
+        say!();
+    }
+
+    let total: f64 = problems
+        .checks
+        .iter()
+        .map(|x| x.duration.as_secs_f64())
+        .sum();
+    if total > budget {
+        let names: Vec<&str> = overruns.iter().map(|x| x.check).collect();
+        problems.warn(
+            format!("the run took {total:.1}s, over its soft budget of {budget:.0}s"),
+            None,
+            match names.is_empty() {
+                true => None,
+                false => Some(format!(
+                    "most of the extra time went to: {}",
+                    names.join(", ")
+                )),
+            },
+        );
+        problems.fields([
+            ("budget_seconds", format!("{budget:.0}")),
+            ("total_seconds", format!("{total:.3}")),
+            ("over_budget_checks", names.join(",")),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(runs: &[(&str, &[f64])]) -> History {
+        runs.iter()
+            .map(|(name, x)| (name.to_string(), x.to_vec()))
+            .collect()
+    }
+
+    fn status(name: &'static str, seconds: f64) -> CheckStatus {
+        CheckStatus {
+            name,
+            passed: true,
+            duration: Duration::from_secs_f64(seconds),
+            partial: false,
+            skipped: None,
+            environment_failure: false,
+        }
+    }
+
+    #[test]
+    fn medians() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&[4.0, 1.0, 2.0, 3.0]), Some(2.5));
+    }
+
+    #[test]
+    fn shares_follow_the_history() {
+        let history = history(&[("a", &[1.0, 100.0, 1.0]), ("b", &[3.0])]);
+        let shares = shares(40.0, &["a", "b"], &history);
+        assert_eq!(shares, [10.0, 30.0]);
+    }
+
+    #[test]
+    fn checks_without_history_get_their_cost_class_estimate() {
+        let history = history(&[("a", &[1.0])]);
+        // Unknown checks count as cheap.
+        let shares = shares(10.0, &["a", "unknown"], &history);
+        assert_eq!(shares, [5.0, 5.0]);
+    }
+
+    #[test]
+    fn zero_expectations_split_evenly() {
+        let history = history(&[("a", &[0.0]), ("b", &[0.0])]);
+        assert_eq!(shares(10.0, &["a", "b"], &history), [5.0, 5.0]);
+        assert!(shares(10.0, &[], &history).is_empty());
+    }
+
+    #[test]
+    fn overruns_allow_some_slack() {
+        let history = history(&[("a", &[1.0]), ("b", &[1.0]), ("c", &[2.0])]);
+        let checks = [
+            status("a", 2.0 + SLACK),
+            status("b", 2.5 + SLACK),
+            status("c", 1.0),
+        ];
+        let overruns = overruns(8.0, &checks, &history);
+        let names: Vec<&str> = overruns.iter().map(|x| x.check).collect();
+        assert_eq!(names, ["b"]);
+        assert_eq!(overruns[0].share, 2.0);
+    }
+
+    #[test]
+    fn skipped_checks_get_no_share() {
+        let history = history(&[("a", &[1.0]), ("b", &[1.0])]);
+        let mut skipped = status("b", 0.0);
+        skipped.skipped = Some(crate::SkipReason::Disabled);
+        let overruns = overruns(4.0, &[status("a", 4.5), skipped], &history);
+        assert!(overruns.is_empty());
+    }
+}
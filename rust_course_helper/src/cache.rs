@@ -0,0 +1,394 @@
+//! The checker's cache: what `--read-only` runs keep outside the repo, in one folder per repo
+//! with its `state` and `target`, all under one root in the system's temporary folder.
+//!
+//! Builds make it grow by gigabytes over a semester, so each run that uses a repo's folder
+//! writes the time to its `last_used` file, and folders unused for longer than the config's
+//! `cache_max_age_days` are removed a few milliseconds' worth at a time at the start of runs.
+//! `cache clean` removes them all at once. A folder is only taken apart while holding the run
+//! lock in its `state`, so never while a run uses it: what's removed is first moved to the
+//! trash folder, and deleted from there, over more than one run if it's big.
+//!
+//! Nothing outside the root is ever removed: symlinks are removed, not followed.
+
+use crate::clock;
+use crate::lock;
+use crate::sha256::{sha256, to_hex};
+use crate::state::LOCK_FILE;
+use camino::{Utf8Path, Utf8PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::{env, fs, process};
+
+const LAST_USED_FILE: &str = "last_used";
+const TRASH_DIR: &str = ".trash";
+/// How long a run spends removing the folders nobody used for too long.
+const CLEANUP_BUDGET: Duration = Duration::from_millis(5);
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Category {
+    /// Builds
+    Targets,
+    /// State files, like the timings and the flakiness history
+    State,
+}
+
+impl Category {
+    pub const ALL: [Category; 2] = [Category::Targets, Category::State];
+
+    fn dir(self) -> &'static str {
+        match self {
+            Category::Targets => "target",
+            Category::State => "state",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Category::Targets => "targets",
+            Category::State => "state",
+        }
+    }
+}
+
+pub fn root() -> Result<Utf8PathBuf, String> {
+    let dir = env::temp_dir().join("rust_course_helper_read_only");
+    Utf8PathBuf::from_path_buf(dir).map_err(|_| "the temporary folder's path isn't UTF-8".into())
+}
+
+/// The name of `repo`'s folder, the same whichever path leads to it.
+pub fn repo_id(repo: &Utf8Path) -> String {
+    let repo = repo.canonicalize_utf8().unwrap_or_else(|_| repo.to_owned());
+    to_hex(&sha256(repo.as_str().as_bytes()))[..16].to_string()
+}
+
+/// Notes that a run uses the repo folder `entry` now.
+pub fn touch(entry: &Utf8Path) -> Result<(), String> {
+    let path = entry.join(LAST_USED_FILE);
+    fs::write(&path, clock::now().to_string()).map_err(|e| format!("can't write {path}: {e}"))
+}
+
+/// For `--older-than`: a number of days, hours, or minutes, like `30d`, in seconds.
+pub fn parse_age(text: &str) -> Result<u64, String> {
+    let unit = match text.chars().last() {
+        Some('d') => 86400,
+        Some('h') => 3600,
+        Some('m') => 60,
+        _ => return Err("expected an age like `30d`, `12h`, or `90m`".into()),
+    };
+    let number: u64 = text[..text.len() - 1]
+        .parse()
+        .map_err(|_| "expected an age like `30d`, `12h`, or `90m`".to_string())?;
+    number
+        .checked_mul(unit)
+        .ok_or_else(|| format!("`{text}` is longer than anything could have been cached"))
+}
+
+/// A real folder, not a symlink to one.
+fn is_dir(path: &Utf8Path) -> bool {
+    path.symlink_metadata().is_ok_and(|x| x.is_dir())
+}
+
+/// The repos' folders.
+fn entries(root: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let Ok(dir) = root.read_dir_utf8() else {
+        return Vec::new();
+    };
+    let mut entries: Vec<Utf8PathBuf> = dir
+        .flatten()
+        .map(|x| x.into_path())
+        .filter(|x| x.file_name() != Some(TRASH_DIR) && is_dir(x))
+        .collect();
+    entries.sort();
+    entries
+}
+
+/// Seconds since a run last used `entry`.
+fn idle(entry: &Utf8Path) -> u64 {
+    let now = clock::now();
+    let stamp = fs::read_to_string(entry.join(LAST_USED_FILE))
+        .ok()
+        .and_then(|x| x.trim().parse().ok());
+    // Folders from before `last_used` have their own time.
+    let modified = || {
+        let modified = entry.metadata().and_then(|x| x.modified()).ok()?;
+        let since = modified.duration_since(UNIX_EPOCH).ok()?;
+        Some(since.as_secs())
+    };
+    match stamp.or_else(modified) {
+        Some(x) if x <= now => now - x,
+        // From the future, so written by a clock that was wrong then or is now: stale either
+        // way, or it would never age out.
+        _ => u64::MAX,
+    }
+}
+
+fn size(path: &Utf8Path) -> u64 {
+    let Ok(metadata) = path.symlink_metadata() else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    let Ok(dir) = path.read_dir_utf8() else {
+        return 0;
+    };
+    dir.flatten().map(|x| size(x.path())).sum()
+}
+
+/// Like `1.2 GB`.
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1000 => format!("{bytes} B"),
+        1000..1_000_000 => format!("{:.1} KB", bytes as f64 / 1e3),
+        1_000_000..1_000_000_000 => format!("{:.1} MB", bytes as f64 / 1e6),
+        _ => format!("{:.1} GB", bytes as f64 / 1e9),
+    }
+}
+
+/// Moves the `categories` of `entry` to the trash, and the whole folder if that's all of
+/// them. `false` if a run is using it, so nothing was moved.
+fn discard(root: &Utf8Path, entry: &Utf8Path, categories: &[Category]) -> Result<bool, String> {
+    let state = entry.join(Category::State.dir());
+    fs::create_dir_all(&state).map_err(|e| format!("can't create {state}: {e}"))?;
+    // Moved to the trash with the state, if the state goes.
+    let Some(_lock) = lock::try_acquire(&state.join(LOCK_FILE))? else {
+        return Ok(false);
+    };
+    let trash = root.join(TRASH_DIR);
+    fs::create_dir_all(&trash).map_err(|e| format!("can't create {trash}: {e}"))?;
+    let id = entry.file_name().unwrap_or_default();
+    // The lock moves with the state, so that goes last.
+    for category in [Category::Targets, Category::State] {
+        let from = entry.join(category.dir());
+        if !categories.contains(&category) || !from.exists() {
+            continue;
+        }
+        let to = trash.join(format!(
+            "{id}-{}-{}-{}",
+            category.dir(),
+            process::id(),
+            clock::now()
+        ));
+        fs::rename(&from, &to).map_err(|e| format!("can't move {from} to {to}: {e}"))?;
+    }
+    if categories.len() == Category::ALL.len() {
+        let _ = fs::remove_file(entry.join(LAST_USED_FILE));
+        // Fails if a run started using it meanwhile, which is fine.
+        let _ = fs::remove_dir(entry);
+    }
+    Ok(true)
+}
+
+/// Removes `path` and what's in it, until `deadline`. Whether it's all gone.
+fn remove(path: &Utf8Path, deadline: Option<Instant>) -> bool {
+    if deadline.is_some_and(|x| Instant::now() >= x) {
+        return false;
+    }
+    // Another run may be removing the same files.
+    let Ok(metadata) = path.symlink_metadata() else {
+        return true;
+    };
+    if !metadata.is_dir() {
+        let _ = fs::remove_file(path);
+        return true;
+    }
+    if let Ok(dir) = path.read_dir_utf8() {
+        for child in dir.flatten() {
+            if !remove(child.path(), deadline) {
+                return false;
+            }
+        }
+    }
+    let _ = fs::remove_dir(path);
+    true
+}
+
+/// Prints how much each category takes up.
+pub fn show_size() -> Result<(), String> {
+    let root = root()?;
+    let entries = entries(&root);
+    say!("{} repos in {root}", entries.len());
+    let mut total = 0;
+    for category in Category::ALL {
+        let bytes: u64 = entries.iter().map(|x| size(&x.join(category.dir()))).sum();
+        say!("{:>10}  {}", format_size(bytes), category.name());
+        total += bytes;
+    }
+    let trash = size(&root.join(TRASH_DIR));
+    if trash > 0 {
+        say!("{:>10}  being removed", format_size(trash));
+        total += trash;
+    }
+    say!("{:>10}  total", format_size(total));
+    Ok(())
+}
+
+/// Removes the `categories` of the repos' folders nobody used for `older_than` seconds.
+pub fn clean(older_than: u64, categories: &[Category]) -> Result<(), String> {
+    clean_in(&root()?, older_than, categories)
+}
+
+fn clean_in(root: &Utf8Path, older_than: u64, categories: &[Category]) -> Result<(), String> {
+    let before = size(root);
+    let mut removed = 0;
+    let mut in_use = 0;
+    for entry in entries(root) {
+        if idle(&entry) < older_than {
+            continue;
+        }
+        match discard(root, &entry, categories)? {
+            true => removed += 1,
+            false => in_use += 1,
+        }
+    }
+    remove(&root.join(TRASH_DIR), None);
+    let names: Vec<&str> = categories.iter().map(|x| x.name()).collect();
+    let repos = |n: usize| match n {
+        1 => "1 repo".to_string(),
+        n => format!("{n} repos"),
+    };
+    say!(
+        "removed the {} of {}, {}",
+        names.join(" and "),
+        repos(removed),
+        format_size(before.saturating_sub(size(root)))
+    );
+    if in_use > 0 {
+        say!(
+            "left {} alone because a checker run is using them",
+            repos(in_use)
+        );
+    }
+    Ok(())
+}
+
+/// Removes a few milliseconds' worth of the repos' folders nobody used for `max_age_days`,
+/// and of the trash. Problems are left for `cache clean` to report.
+pub fn clean_old(max_age_days: u64) {
+    let Ok(root) = root() else {
+        return;
+    };
+    if max_age_days == 0 || !is_dir(&root) {
+        return;
+    }
+    clean_old_in(&root, max_age_days);
+}
+
+fn clean_old_in(root: &Utf8Path, max_age_days: u64) {
+    // A config can ask for more days than there are seconds; nothing is that old.
+    let max_age = max_age_days.saturating_mul(86400);
+    let deadline = Instant::now() + CLEANUP_BUDGET;
+    for entry in entries(root) {
+        if Instant::now() >= deadline {
+            return;
+        }
+        if idle(&entry) >= max_age {
+            let _ = discard(root, &entry, &Category::ALL);
+        }
+    }
+    remove(&root.join(TRASH_DIR), Some(deadline));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    const DAY: u64 = 86400;
+
+    /// A repo's folder last used `days` ago, with a build and a state file.
+    fn entry(root: &Utf8Path, id: &str, days: u64) -> Utf8PathBuf {
+        let entry = root.join(id);
+        fs::create_dir_all(entry.join("target/debug")).unwrap();
+        fs::create_dir_all(entry.join("state")).unwrap();
+        fs::write(entry.join("target/debug/lab01"), "binary").unwrap();
+        fs::write(entry.join("state/timings.toml"), "").unwrap();
+        let at = clock::now() - days * DAY;
+        fs::write(entry.join(LAST_USED_FILE), at.to_string()).unwrap();
+        entry
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(parse_age("30d"), Ok(30 * DAY));
+        assert_eq!(parse_age("12h"), Ok(12 * 3600));
+        assert_eq!(parse_age("0m"), Ok(0));
+        for text in ["", "d", "30", "30s", "-1d", "1.5d"] {
+            assert!(parse_age(text).is_err(), "{text}");
+        }
+        let e = parse_age(&format!("{}d", u64::MAX / DAY + 1)).unwrap_err();
+        assert!(e.contains("longer than anything"), "{e}");
+    }
+
+    #[test]
+    fn runs_clean_by_the_configured_age() {
+        let dir = TempDir::new("cache_clean_old").unwrap();
+        let root = dir.path();
+        let old = entry(root, "old", 40);
+        let recent = entry(root, "recent", 2);
+        clean_old_in(root, 30);
+        assert!(!old.exists());
+        assert!(recent.exists());
+        // More days than fit in seconds keeps everything instead of overflowing.
+        clean_old_in(root, u64::MAX);
+        clean_old_in(root, u64::MAX / DAY + 1);
+        assert!(recent.exists());
+    }
+
+    #[test]
+    fn only_old_folders_are_cleaned() {
+        let dir = TempDir::new("cache_age").unwrap();
+        let root = dir.path();
+        let old = entry(root, "old", 40);
+        let recent = entry(root, "recent", 2);
+        clean_in(root, 30 * DAY, &Category::ALL).unwrap();
+        assert!(!old.exists());
+        assert!(recent.join("target/debug/lab01").exists());
+        assert!(recent.join("state/timings.toml").exists());
+        // What went to the trash is removed too.
+        assert!(!root.join(TRASH_DIR).exists());
+    }
+
+    #[test]
+    fn only_the_chosen_categories_are_cleaned() {
+        let dir = TempDir::new("cache_category").unwrap();
+        let root = dir.path();
+        let old = entry(root, "old", 40);
+        clean_in(root, 30 * DAY, &[Category::Targets]).unwrap();
+        assert!(!old.join("target").exists());
+        assert!(old.join("state/timings.toml").exists());
+        assert!(old.join(LAST_USED_FILE).exists());
+
+        let old = entry(root, "old", 40);
+        clean_in(root, 30 * DAY, &[Category::State]).unwrap();
+        assert!(old.join("target/debug/lab01").exists());
+        assert!(!old.join("state/timings.toml").exists());
+    }
+
+    #[test]
+    fn folders_in_use_are_left_alone() {
+        let dir = TempDir::new("cache_in_use").unwrap();
+        let root = dir.path();
+        let old = entry(root, "old", 40);
+        let _lock = lock::try_acquire(&old.join("state").join(LOCK_FILE))
+            .unwrap()
+            .unwrap();
+        clean_in(root, 0, &Category::ALL).unwrap();
+        assert!(old.join("target/debug/lab01").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed() {
+        let dir = TempDir::new("cache_symlink").unwrap();
+        let root = dir.path().join("root");
+        let outside = dir.path().join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("keep"), "").unwrap();
+        let old = entry(&root, "old", 40);
+        std::os::unix::fs::symlink(&outside, old.join("target/link")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("linked_entry")).unwrap();
+        clean_in(&root, 30 * DAY, &Category::ALL).unwrap();
+        assert!(!old.exists());
+        assert!(outside.join("keep").exists());
+    }
+}
@@ -0,0 +1,105 @@
+//! Capturing the output of child processes without holding all of it in memory.
+//!
+//! Only the first and last `limit` bytes of each stream are kept, which is plenty for
+//! diagnostics. A sink can see the whole stream as it arrives, for `--verbose`.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::thread::{self, JoinHandle};
+
+/// How much of the start and of the end of a stream is kept.
+pub const CAPTURE_LIMIT: usize = 64 * 1024;
+
+pub struct Captured {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    total: u64,
+    limit: usize,
+}
+
+impl Captured {
+    pub fn new(limit: usize) -> Captured {
+        Captured {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            total: 0,
+            limit,
+        }
+    }
+
+    fn push(&mut self, mut data: &[u8]) {
+        self.total += data.len() as u64;
+        let room = self.limit.saturating_sub(self.head.len());
+        let (head, rest) = data.split_at(room.min(data.len()));
+        self.head.extend_from_slice(head);
+        data = rest;
+
+        self.tail.extend(data);
+        let excess = self.tail.len().saturating_sub(self.limit);
+        self.tail.drain(..excess);
+    }
+
+    /// Bytes the stream had in total, including the ones that weren't kept.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Whether some of the stream wasn't kept.
+    pub fn truncated(&self) -> bool {
+        self.total > (self.head.len() + self.tail.len()) as u64
+    }
+
+    pub fn text(&self) -> String {
+        let mut bytes = self.head.clone();
+        let dropped = self.total - (self.head.len() + self.tail.len()) as u64;
+        if dropped > 0 {
+            bytes.extend_from_slice(format!("\n…truncated {dropped} bytes…\n").as_bytes());
+        }
+        bytes.extend(&self.tail);
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+pub type Sink = Box<dyn Write + Send>;
+
+/// Reads `pipe` to the end on another thread.
+pub fn read(
+    pipe: Option<impl Read + Send + 'static>,
+    limit: usize,
+    mut sink: Option<Sink>,
+) -> JoinHandle<io::Result<Captured>> {
+    thread::spawn(move || {
+        let mut captured = Captured::new(limit);
+        let Some(mut pipe) = pipe else {
+            return Ok(captured);
+        };
+        let mut buf = [0; 8192];
+        loop {
+            let n = match pipe.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            captured.push(&buf[..n]);
+            // Showing the output is best effort; losing it mustn't lose the capture.
+            if let Some(x) = &mut sink
+                && x.write_all(&buf[..n]).is_err()
+            {
+                sink = None;
+            }
+        }
+        Ok(captured)
+    })
+}
+
+/// A sink that shows a stream with the rest of the human-readable output.
+pub fn human_sink() -> Sink {
+    Box::new(crate::output::Sink::human())
+}
+
+pub fn join(reader: JoinHandle<io::Result<Captured>>) -> io::Result<Captured> {
+    reader
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("reader thread panicked")))
+}
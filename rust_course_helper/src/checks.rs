@@ -1,30 +1,273 @@
-use crate::{CheckResult, Context};
-use std::{
-    fs,
-    process::{Command, ExitStatus, Stdio},
-};
+mod all_targets;
+mod api;
+mod build_timings;
+mod dependencies;
+mod file_locks;
+mod generated;
+mod ice;
+mod includes;
+mod lfs;
+mod lockfile;
+mod manifest;
+mod markers;
+mod nightly;
+mod publish;
+mod shared_repo;
+mod smoke;
+mod source;
+mod syntax;
+mod test_failures;
+mod tests_scan;
+mod tracked_sources;
+
+pub use manifest::Target;
+pub use source::rust_sources;
+pub use syntax::ParsedFile;
+pub use tests_scan::TestFn;
+
+use crate::artifacts;
+use crate::exec::{Exec, ExecResult};
+use crate::fix::Fix;
+use crate::state::STATE_DIR;
+use crate::{CheckError, CheckResult, Context, SkipReason, git};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::{fs, io, process::ExitStatus};
 
 pub type CheckFn = fn(ctx: &mut Context) -> CheckResult;
 
-pub const CHECKS: &[CheckFn] = &[
-    check_gitignore,
-    check_commited_files,
-    check_lab_folder,
-    check_compiler_warnings,
-    check_clippy,
-    check_tests,
-    check_fmt,
+pub struct Check {
+    pub name: &'static str,
+    pub run: CheckFn,
+    /// Checks that have to run before this one.
+    pub after: &'static [&'static str],
+    pub cost: Cost,
+    /// Whether the check reads files one by one, so `--since` limits it to changed ones.
+    pub scans_files: bool,
+    pub category: Category,
+}
+
+/// What a check's problems are about, most important first, for `--max-problems`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    /// The repo or the machine, which every other check relies on.
+    Environment,
+    /// Files, folders and manifests.
+    Structure,
+    Compile,
+    Clippy,
+    Tests,
+    Style,
+}
+
+/// Roughly how long a check takes, so cheap checks can run first.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cost {
+    /// Looks at a few files.
+    Cheap,
+    /// Runs a quick command.
+    Moderate,
+    /// Builds the lab.
+    Expensive,
+}
+
+impl Cost {
+    pub fn name(self) -> &'static str {
+        match self {
+            Cost::Cheap => "cheap",
+            Cost::Moderate => "moderate",
+            Cost::Expensive => "expensive",
+        }
+    }
+}
+
+const fn check(name: &'static str, run: CheckFn) -> Check {
+    Check {
+        name,
+        run,
+        after: &[],
+        cost: Cost::Cheap,
+        scans_files: false,
+        category: Category::Structure,
+    }
+}
+
+impl Check {
+    const fn after(mut self, after: &'static [&'static str]) -> Check {
+        self.after = after;
+        self
+    }
+    const fn cost(mut self, cost: Cost) -> Check {
+        self.cost = cost;
+        self
+    }
+    const fn scans_files(mut self) -> Check {
+        self.scans_files = true;
+        self
+    }
+    const fn category(mut self, category: Category) -> Check {
+        self.category = category;
+        self
+    }
+}
+
+/// Checks that run cargo, which needs to read the manifest.
+const CARGO_DEPS: &[&str] = &["lab_folder", "workspace_inheritance"];
+
+/// Every check, in no particular order; `schedule::plan` decides the order.
+pub const CHECKS: &[Check] = &[
+    check("gitignore", check_gitignore),
+    check("git_repo", check_git_repo)
+        .cost(Cost::Moderate)
+        .category(Category::Environment),
+    check("committed_files", check_commited_files)
+        .after(&["git_repo"])
+        .cost(Cost::Moderate),
+    check("git_lfs", lfs::check_git_lfs)
+        .after(&["git_repo"])
+        .cost(Cost::Moderate)
+        .category(Category::Environment),
+    check("branch", check_branch)
+        .after(&["git_repo"])
+        .cost(Cost::Moderate),
+    check("shared_repo", shared_repo::check_shared_repo)
+        .after(&["git_repo"])
+        .cost(Cost::Moderate),
+    check("lab_folder", check_lab_folder),
+    check(
+        "workspace_inheritance",
+        manifest::check_workspace_inheritance,
+    )
+    .after(&["lab_folder"]),
+    check("local_dependencies", manifest::check_local_dependencies).after(&["lab_folder"]),
+    check("dependency_overrides", manifest::check_dependency_overrides).after(&["lab_folder"]),
+    // Counts as cheap so it's ahead of every other cargo command, since those update the lock
+    // file before it could be checked.
+    check("lock_file", lockfile::check_lock_file).after(CARGO_DEPS),
+    check("binary_names", manifest::check_binary_names)
+        .after(CARGO_DEPS)
+        .cost(Cost::Moderate),
+    check("compiler_warnings", check_compiler_warnings)
+        .after(CARGO_DEPS)
+        .cost(Cost::Expensive)
+        .category(Category::Compile),
+    check("clippy", check_clippy)
+        .after(CARGO_DEPS)
+        .cost(Cost::Expensive)
+        .category(Category::Clippy),
+    check("tests", check_tests)
+        .after(CARGO_DEPS)
+        .cost(Cost::Expensive)
+        .category(Category::Tests),
+    // Runs the binary the build produced.
+    check("stdin_eof", smoke::check_stdin_eof)
+        .after(&["compiler_warnings"])
+        .cost(Cost::Moderate)
+        .category(Category::Tests),
+    check("extra_args", smoke::check_extra_args)
+        .after(&["compiler_warnings"])
+        .cost(Cost::Moderate)
+        .category(Category::Tests),
+    check("fmt", check_fmt)
+        .after(CARGO_DEPS)
+        .cost(Cost::Moderate)
+        .category(Category::Style),
+    check("line_length", source::check_line_length)
+        .after(&["lab_folder"])
+        .scans_files()
+        .category(Category::Style),
+    check("should_panic", tests_scan::check_should_panic)
+        .after(&["lab_folder"])
+        .category(Category::Tests),
+    // Reads the dep-info files the build wrote.
+    check(
+        "untracked_sources",
+        tracked_sources::check_untracked_sources,
+    )
+    .after(&["git_repo", "compiler_warnings"])
+    .cost(Cost::Moderate),
+    check("include_paths", includes::check_include_paths)
+        .after(&["lab_folder"])
+        .scans_files(),
+    check("api", api::check_api).after(&["lab_folder"]),
+    check("lab_markers", markers::check_lab_markers).after(&["lab_folder"]),
+    check("generated_files", generated::check_generated_files)
+        .after(&["lab_folder"])
+        .cost(Cost::Moderate),
+    check("package_metadata", publish::check_package_metadata).after(&["lab_folder"]),
+    check("publish_dry_run", publish::check_publish_dry_run)
+        .after(CARGO_DEPS)
+        .cost(Cost::Expensive),
+    check("unstable_features", nightly::check_unstable_features)
+        .after(&["lab_folder"])
+        .scans_files()
+        .category(Category::Compile),
+    // Only builds anything when the active toolchain is nightly.
+    check("stable_build", nightly::check_stable_build)
+        .after(&["lab_folder", "workspace_inheritance", "unstable_features"])
+        .cost(Cost::Expensive)
+        .category(Category::Compile),
 ];
 
+/// Where cargo may have put the lab's build output.
+fn target_dirs(ctx: &Context) -> Vec<Utf8PathBuf> {
+    let mut dirs = Vec::new();
+    // What cargo is given wins over the checker's own environment. Cargo runs in the lab, so
+    // relative paths are from there.
+    let set = ctx
+        .child_env
+        .iter()
+        .rev()
+        .find(|x| x.0 == "CARGO_TARGET_DIR");
+    if let Some((_, x)) = set {
+        dirs.push(ctx.lab_path.join(x));
+    }
+    if let Ok(x) = std::env::var("CARGO_TARGET_DIR") {
+        dirs.push(Utf8PathBuf::from(x));
+    }
+    dirs.push(ctx.lab_path.join("target"));
+    dirs.push(ctx.repo_path.join("target"));
+    dirs
+}
+
+/// The repo's git health, probed the first time a check asks.
+pub fn git_health(ctx: &mut Context) -> git::Health {
+    ctx.git_health
+        .get_or_insert_with(|| git::probe(&ctx.repo_path))
+        .clone()
+}
+
+fn check_git_repo(ctx: &mut Context) -> CheckResult {
+    let repo = ctx.repo_path.clone();
+    match git_health(ctx) {
+        git::Health::Healthy => Ok(()),
+        git::Health::NotARepo => Err(ctx.problems.add(
+            "the repo isn't a git repository",
+            repo,
+            Some("submit a clone of your repo, not a copy of its files".into()),
+        )),
+        git::Health::Corrupted(e) => Err(ctx.problems.add(
+            format!("the git repository is corrupted or only partly cloned, so checks that use git were skipped:\n{e}"),
+            repo,
+            Some("clone the repo again into a new folder instead of copying, zipping or moving the old one".into()),
+        )),
+    }
+}
+
 fn check_gitignore(ctx: &mut Context) -> CheckResult {
     let gitignore_path = ctx.repo_path.join(".gitignore");
     let help = "you need to have a file like this: https://github.com/xTachyon/rust_course_helper/blob/main/.gitignore";
 
+    let fix = Fix::AppendLine {
+        path: gitignore_path.clone(),
+        line: "target/".into(),
+    };
+
     if !gitignore_path.exists() {
-        return Err(ctx.problems.add(
+        return Err(ctx.problems.add_fixable(
             ".gitignore doesn't exist",
             Some(gitignore_path),
             Some(help.into()),
+            fix,
         ));
     }
 
@@ -35,33 +278,54 @@ fn check_gitignore(ctx: &mut Context) -> CheckResult {
     };
 
     if !text.lines().any(|x| x.contains("target")) {
-        return Err(ctx.problems.add(
+        return Err(ctx.problems.add_fixable(
             "target folder doesn't exist in .gitignore",
             Some(gitignore_path),
             Some(help.into()),
+            fix,
+        ));
+    }
+
+    if ctx.writes_state && !text.lines().any(|x| x.contains(STATE_DIR)) {
+        return Err(ctx.problems.add_fixable(
+            format!("the checker writes files to `{STATE_DIR}/`, but it isn't in .gitignore"),
+            Some(gitignore_path.clone()),
+            None,
+            Fix::AppendLine {
+                path: gitignore_path,
+                line: format!("{STATE_DIR}/"),
+            },
         ));
     }
 
     Ok(())
 }
 
-fn command_check_return(ctx: &mut Context, name: &str, e: ExitStatus, text: &str) -> CheckResult {
+fn command_check_return(
+    ctx: &mut Context,
+    name: &str,
+    e: ExitStatus,
+    text: &str,
+    fix: Option<Fix>,
+) -> CheckResult {
     if !e.success() {
-        return Err(ctx.problems.add(
-            format!("{text}; command `{name}` failed: {e}"),
-            Some(ctx.repo_path.clone()),
-            None,
-        ));
+        let text = format!("{text}; command `{name}` failed: {e}");
+        let path = Some(ctx.repo_path.clone());
+        return Err(match fix {
+            Some(fix) => ctx.problems.add_fixable(text, path, None, fix),
+            None => ctx.problems.add(text, path, None),
+        });
     }
     Ok(())
 }
 
 fn check_commited_files(ctx: &mut Context) -> CheckResult {
-    let output = match Command::new("git")
+    let git = Exec::new("git")
         .arg("ls-files")
-        .current_dir(&ctx.repo_path)
-        .output()
-    {
+        .cwd(&ctx.repo_path)
+        .stdin_null()
+        .limit(usize::MAX);
+    let output = match output(ctx, &git) {
         Ok(x) => x,
         Err(e) => {
             return Err(ctx.problems.add(
@@ -71,44 +335,143 @@ fn check_commited_files(ctx: &mut Context) -> CheckResult {
             ));
         }
     };
-    command_check_return(ctx, "git", output.status, "failed")?;
+    command_check_return(ctx, "git", output.status, "failed", None)?;
 
-    let stdout = String::from_utf8(output.stdout).expect("from_utf8 failed.. somehow");
+    let stdout = output.stdout.text();
 
     const EXTENSIONS: &[&str] = &[
         ".exe", ".dll", ".pdb", ".lib", ".obj", ".so", ".dylib", ".a", ".o", ".rlib", ".rmeta",
         ".d",
     ];
 
-    let mut bad_files = Vec::new();
-    for line in stdout.lines() {
-        for ext in EXTENSIONS {
-            if line.ends_with(ext) {
-                bad_files.push(line);
-                break;
-            }
-        }
+    let state_prefix = format!("{STATE_DIR}/");
+    let (state_files, other_files): (Vec<&str>, Vec<&str>) =
+        stdout.lines().partition(|x| x.starts_with(&state_prefix));
+    // Files in LFS don't make the history bigger, so they're fine whatever they are.
+    let lfs_files = lfs::tracked_files(&ctx.repo_path);
+    let bad_files: Vec<&str> = other_files
+        .into_iter()
+        .filter(|line| EXTENSIONS.iter().any(|ext| line.ends_with(ext)))
+        .filter(|line| !lfs_files.iter().any(|x| x == line))
+        .collect();
+
+    let mut result = Ok(());
+    if !state_files.is_empty() {
+        result = Err(ctx.problems.add_fixable(
+            format!(
+                "{} files generated by the checker are committed",
+                state_files.len()
+            ),
+            state_files
+                .iter()
+                .map(|x| ctx.repo_path.join(x))
+                .collect::<Vec<_>>(),
+            Some(format!(
+                "these are generated by the checker; add `{STATE_DIR}/` to .gitignore"
+            )),
+            Fix::run(
+                ctx.repo_path.clone(),
+                "git",
+                ["rm", "-r", "--cached", "--quiet", "--", STATE_DIR],
+            ),
+        ));
+        ctx.problems
+            .fields([("state_file_count", state_files.len().to_string())]);
     }
 
     if bad_files.is_empty() {
-        return Ok(());
+        return result;
     }
 
-    let max_lines = 20;
-    let first_bad_files = &bad_files[..bad_files.len().min(max_lines)];
-    let mut text = format!(
-        "build files were found in the repo. bad files:\n{}",
-        first_bad_files.join("\n")
+    let fix = Fix::run(
+        ctx.repo_path.clone(),
+        "git",
+        ["rm", "--cached", "--quiet", "--"]
+            .into_iter()
+            .chain(bad_files.iter().copied()),
     );
-    if bad_files.len() > max_lines {
-        text += format!("\n..and {} more", bad_files.len() - max_lines).as_str();
+    let largest = bad_files
+        .iter()
+        .filter_map(|x| Some((*x, fs::metadata(ctx.repo_path.join(x)).ok()?.len())))
+        .max_by_key(|x| x.1);
+    let e = ctx.problems.add_fixable(
+        format!("{} build files were found in the repo", bad_files.len()),
+        bad_files
+            .iter()
+            .map(|x| ctx.repo_path.join(x))
+            .collect::<Vec<_>>(),
+        Some("remove target directories and all build artifacts".into()),
+        fix,
+    );
+    ctx.problems
+        .fields([("bad_file_count", bad_files.len().to_string())]);
+    if let Some((path, size)) = largest {
+        ctx.problems.fields([
+            ("largest_file", path.to_string()),
+            ("largest_file_bytes", size.to_string()),
+        ]);
     }
+    Err(e)
+}
 
-    Err(ctx.problems.add(
-        text,
-        Some(ctx.repo_path.clone()),
-        Some("remove target directories and all build artifacts".into()),
-    ))
+/// Matches `text` against `pattern`, where `*` matches any run of characters.
+pub fn matches_pattern(pattern: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut text) = text.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match text.find(part) {
+            Some(i) => text = &text[i + part.len()..],
+            None => return false,
+        }
+    }
+    text.len() >= last.len() && text.ends_with(last)
+}
+
+fn check_branch(ctx: &mut Context) -> CheckResult {
+    if ctx.lab_config.expected_branches.is_empty() {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let expected = ctx.lab_config.expected_branches.join("`, `");
+
+    // Branch names are passed through untouched; they can contain anything git allows.
+    let branch = match git::git(&ctx.repo_path, &["symbolic-ref", "--quiet", "HEAD"]) {
+        Ok(x) => x.strip_prefix("refs/heads/").unwrap_or(&x).to_string(),
+        Err(_) => {
+            let at = git::git(
+                &ctx.repo_path,
+                &["describe", "--tags", "--exact-match", "HEAD"],
+            )
+            .map(|tag| format!("tag `{tag}`"))
+            .or_else(|_| git::head_commit(&ctx.repo_path).map(|x| format!("commit {x}")))
+            .unwrap_or_else(|_| "an unknown commit".to_string());
+            return Err(ctx.problems.add(
+                format!("HEAD is not on a branch, it's detached at {at}"),
+                ctx.repo_path.clone(),
+                Some(format!("switch to one of these branches: `{expected}`")),
+            ));
+        }
+    };
+
+    if !ctx
+        .lab_config
+        .expected_branches
+        .iter()
+        .any(|x| matches_pattern(x, &branch))
+    {
+        return Err(ctx.problems.add(
+            format!("you're on branch `{branch}`, which is not an expected branch"),
+            ctx.repo_path.clone(),
+            Some(format!("expected one of: `{expected}`")),
+        ));
+    }
+
+    Ok(())
 }
 
 fn check_lab_folder(ctx: &mut Context) -> CheckResult {
@@ -117,61 +480,426 @@ fn check_lab_folder(ctx: &mut Context) -> CheckResult {
             .problems
             .add("lab folder doesn't exist", Some(ctx.lab_path.clone()), None));
     }
+    if ctx.lab_path.join("Cargo.toml").exists() {
+        return Ok(());
+    }
 
-    Ok(())
+    let lab = ctx.lab_path.file_name().unwrap_or_default().to_string();
+    let mut names: Vec<String> = match ctx.lab_path.read_dir_utf8() {
+        Ok(x) => x
+            .flatten()
+            .map(|x| x.file_name().to_string())
+            .filter(|x| !x.starts_with('.'))
+            .collect(),
+        Err(e) => {
+            return Err(ctx.problems.add(
+                format!("can't read the lab folder: {e}"),
+                ctx.lab_path.clone(),
+                None,
+            ));
+        }
+    };
+    names.sort();
+
+    let (text, help) = if names.is_empty() {
+        (
+            format!("{lab} is empty"),
+            "run `cargo init` inside it to start the lab",
+        )
+    } else if !source::rust_sources(&ctx.lab_path).is_empty() {
+        (
+            format!("{lab} contains Rust files but no Cargo.toml"),
+            "run `cargo init` inside it, and move the code into the `src` folder it creates",
+        )
+    } else {
+        (
+            format!("{lab} has no Rust project, only: {}", names.join(", ")),
+            "run `cargo init` inside it to start the lab",
+        )
+    };
+    Err(ctx
+        .problems
+        .add(text, ctx.lab_path.clone(), Some(help.into())))
+}
+
+/// Output bigger than this gets its own warning.
+const LARGE_OUTPUT: u64 = 8 * 1024 * 1024;
+
+/// Adds the config's `env` variables to a command a check runs. They win over the command's
+/// own settings, which is worth a note since the check may then behave differently.
+fn with_lab_env(ctx: &mut Context, exec: &Exec) -> Exec {
+    for (key, _) in &ctx.child_env {
+        if exec.sets(key) && !ctx.env_overrides.contains(key) {
+            say!("note: the config sets `{key}`, replacing the value the checker uses for it");
+            ctx.env_overrides.push(key.clone());
+        }
+    }
+    exec.clone().config_env(&ctx.child_env)
 }
 
-fn run_cargo(ctx: &mut Context, args: &[&str], text: &str) -> CheckResult {
+/// Runs a command for the current check, with the config's variables, and records it for the
+/// reports.
+fn output(ctx: &mut Context, exec: &Exec) -> io::Result<ExecResult> {
+    let exec = with_lab_env(ctx, exec);
     if ctx.verbose {
-        println!("running command: cargo {}", args.join(" "));
+        say!("running command: {}", exec.describe());
     }
+    let output = exec.run()?;
+    let description = exec.describe();
+    ctx.problems
+        .record_command(description.clone(), output.duration, output.usage);
+
+    let total = output.stdout.total() + output.stderr.total();
+    if total > LARGE_OUTPUT {
+        ctx.problems.warn(
+            format!(
+                "`{description}` produced {} MB of output",
+                total / (1024 * 1024)
+            ),
+            ctx.lab_path.clone(),
+            Some("only its start and end were kept; remove debug prints from the code, tests and build scripts".into()),
+        );
+    }
+    Ok(output)
+}
+
+/// Runs cargo in the lab. Failures that have a better explanation than the exit status are
+/// reported here; the rest are left to the caller.
+fn cargo(ctx: &mut Context, args: &[&str], text: &str) -> Result<ExecResult, CheckError> {
+    let mut output = run_cargo_command(ctx, args, text)?;
+    let mut stderr = output.stderr.text();
 
-    let run = |cmd: &mut Command| cmd.spawn()?.wait_with_output();
-    let output = match run(Command::new("cargo")
-        .args(args)
-        .current_dir(&ctx.lab_path)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped()))
+    // Locked files are usually released again by the time cargo's run again.
+    if !output.status.success()
+        && let Some(line) = file_locks::locked_file_line(&stderr).map(String::from)
     {
-        Ok(x) => x,
-        Err(e) => {
+        if ctx.verbose {
+            say!("a file in target/ was locked, trying again");
+        }
+        output = run_cargo_command(ctx, args, text)?;
+        stderr = output.stderr.text();
+        let help = Some(file_locks::help(ctx.repo_path.as_str()));
+        if !output.status.success() && file_locks::locked_file_line(&stderr).is_some() {
             return Err(ctx.problems.add(
-                format!("{}; because: cargo failed with `{e}`", text),
-                Some(ctx.lab_path.clone()),
-                None,
+                format!("{text}; because: another program kept a file cargo had to write locked, even when trying again: {line}"),
+                ctx.lab_path.join("target"),
+                help,
             ));
         }
-    };
+        ctx.problems.warn(
+            format!("`cargo {}` failed because another program had a file locked, and was run again: {line}", args[0]),
+            ctx.lab_path.join("target"),
+            help,
+        );
+        ctx.problems.fields([("retried", "true".into())]);
+    }
 
-    if ctx.verbose {
-        println!(
-            "stdout:\n{}stderr:\n{}",
-            String::from_utf8(output.stdout).expect("string is not utf8"),
-            String::from_utf8(output.stderr).expect("string is not utf8"),
+    if !output.status.success()
+        && let Some(ice) = ice::find(ctx, &format!("{stderr}\n{}", output.stdout.text()))
+    {
+        return Err(ice_problem(ctx, text, ice));
+    }
+
+    if !output.status.success()
+        && let Some(component) = missing_component(args[0], &stderr)
+    {
+        return Err(ctx.problems.add_fixable(
+            format!("{text}; because: `cargo {}` is not installed", args[0]),
+            ctx.lab_path.clone(),
+            None,
+            Fix::run(
+                ctx.lab_path.clone(),
+                "rustup",
+                ["component", "add", component],
+            ),
+        ));
+    }
+
+    if !output.status.success() && stderr.contains("failed to find a workspace root") {
+        return Err(workspace_root_problem(ctx, text, &stderr));
+    }
+
+    Ok(output)
+}
+
+/// A compiler crash isn't the student's fault, so it's an environment failure.
+fn ice_problem(ctx: &mut Context, text: &str, ice: ice::Ice) -> CheckError {
+    ctx.environment_failure = true;
+    let mut message = format!(
+        "{text}; because: the compiler crashed with an internal compiler error. This is a bug in rustc, not in your code"
+    );
+    if let Some(version) = &ice.version {
+        message += &format!("\ncompiler: {version}");
+    }
+    if !ice.query_stack.is_empty() {
+        message += &format!("\nquery stack:\n{}", ice.query_stack.join("\n"));
+    }
+    let mut help = "update the toolchain with `rustup update` and run `cargo clean`, then try again; if it still crashes, report it at https://github.com/rust-lang/rust/issues".to_string();
+    if let Some(report) = &ice.report {
+        help += &format!(" with {report} attached");
+    }
+    let paths = ice.report.clone().unwrap_or_else(|| ctx.lab_path.clone());
+    let e = ctx.problems.add(message, paths, Some(help));
+    ctx.problems.fields([
+        ("rustc_version", ice.version.unwrap_or_default()),
+        ("query_stack", ice.query_stack.join("\n")),
+    ]);
+    e
+}
+
+fn run_cargo_command(
+    ctx: &mut Context,
+    args: &[&str],
+    text: &str,
+) -> Result<ExecResult, CheckError> {
+    let cargo = Exec::new("cargo")
+        .args(args.iter().copied())
+        .cwd(&ctx.lab_path)
+        .tee(ctx.verbose);
+    output(ctx, &cargo).map_err(|e| {
+        ctx.problems.add(
+            format!("{}; because: cargo failed with `{e}`", text),
+            Some(ctx.lab_path.clone()),
+            None,
+        )
+    })
+}
+
+/// Cargo couldn't read the manifest because it inherits from a missing workspace.
+fn workspace_root_problem(ctx: &mut Context, text: &str, stderr: &str) -> CheckError {
+    let manifest_path = ctx.lab_path.join("Cargo.toml");
+    let reported = ctx
+        .problems
+        .problems
+        .iter()
+        .any(|x| x.check == Some("workspace_inheritance"));
+    if reported {
+        return ctx.problems.add(
+            format!("{text}; because: Cargo.toml inherits from a workspace that doesn't exist"),
+            manifest_path,
+            None,
         );
     }
 
-    command_check_return(ctx, "cargo", output.status, text)?;
+    // cargo says: error inheriting `edition` from workspace root manifest's `workspace.package.edition`
+    let keys: Vec<(String, String)> = stderr
+        .split("error inheriting `")
+        .skip(1)
+        .filter_map(|x| x.split_once('`'))
+        .map(|(key, _)| (key.to_string(), manifest::literal_for(key)))
+        .collect();
+    let text = match keys.is_empty() {
+        true => format!(
+            "{text}; because: Cargo.toml inherits settings from a workspace that doesn't exist"
+        ),
+        false => format!("{text}; because: {}", manifest::inheritance_problem(&keys)),
+    };
+    ctx.problems
+        .add(text, manifest_path, Some(manifest::WORKSPACE_HELP.into()))
+}
 
-    Ok(())
+/// The rustup component that provides `subcommand`, if cargo failed because it's missing.
+fn missing_component(subcommand: &str, stderr: &str) -> Option<&'static str> {
+    let component = match subcommand {
+        "clippy" => "clippy",
+        "fmt" => "rustfmt",
+        _ => return None,
+    };
+    let missing = stderr.contains("no such command") || stderr.contains("is not installed");
+    missing.then_some(component)
 }
 
 fn check_compiler_warnings(ctx: &mut Context) -> CheckResult {
-    run_cargo(ctx, &["build", "--all", "-q"], "code has compiler warnings")
+    let text = "code has compiler warnings";
+    let mut args = vec!["build", "--all", "-q"];
+    if ctx.build_timings {
+        args.push("--timings");
+    }
+    let output = cargo(ctx, &args, text)?;
+    if ctx.build_timings {
+        build_timings::check_build_timings(ctx, output.duration);
+    }
+    if !output.status.success()
+        && let Some(e) = dependencies::check_dependency_failures(ctx, &output.stderr.text())
+    {
+        return Err(e);
+    }
+    command_check_return(ctx, "cargo", output.status, text, None)?;
+    all_targets::check_all_targets(ctx, &output.stderr.text())
 }
 
 fn check_clippy(ctx: &mut Context) -> CheckResult {
-    run_cargo(ctx, &["clippy", "--all", "-q"], "code has clippy warnings")
+    let text = "code has clippy warnings";
+    let output = cargo(ctx, &["clippy", "--all", "-q"], text)?;
+    // Clippy replays its cached messages, so this is quick.
+    if artifacts::kept(ctx.problems).is_some() {
+        let json = Exec::new("cargo")
+            .args(["clippy", "--all", "--message-format=json", "-q"])
+            .cwd(&ctx.lab_path)
+            .stdin_null()
+            .limit(usize::MAX);
+        if let Ok(json) = self::output(ctx, &json) {
+            artifacts::add(
+                ctx.problems,
+                "clippy.json",
+                "application/x-ndjson",
+                json.stdout.text().as_bytes(),
+            );
+        }
+    }
+    command_check_return(ctx, "cargo", output.status, text, None)
 }
 
 fn check_tests(ctx: &mut Context) -> CheckResult {
-    run_cargo(ctx, &["test", "--all", "-q"], "code has failed tests")
+    let text = "code has failed tests";
+    // Grading tests needs their names, which `-q` leaves out.
+    let args: &[&str] = match ctx.lab_config.grading.test_points.is_empty() {
+        true => &["test", "--all", "-q"],
+        false => &["test", "--all"],
+    };
+    let output = cargo(ctx, args, text)?;
+    // Tests cut out of huge output earn nothing, like tests that didn't run.
+    ctx.problems.test_results = tests_scan::test_results(&output.stdout.text());
+    if !output.status.success() {
+        test_failures::check_missing_files(ctx, &output.stdout.text());
+    }
+    command_check_return(ctx, "cargo", output.status, text, None)?;
+    // Summaries may have been cut out of huge output.
+    if output.stdout.truncated() {
+        return Ok(());
+    }
+    tests_scan::check_zero_tests(ctx, &output.stdout.text())
 }
 
 fn check_fmt(ctx: &mut Context) -> CheckResult {
-    run_cargo(
-        ctx,
-        &["fmt", "--all", "--check", "-q"],
-        "code is not formatted",
-    )
+    let text = "code is not formatted";
+    let fix = Fix::run(ctx.lab_path.clone(), "cargo", ["fmt", "--all"]);
+    // Without `-q`, the diff is printed.
+    let output = cargo(ctx, &["fmt", "--all", "--check"], text)?;
+    let diff = output.stdout.text();
+    if !diff.is_empty() {
+        artifacts::add(ctx.problems, "fmt.diff", "text/x-diff", diff.as_bytes());
+    }
+    command_check_return(ctx, "cargo", output.status, text, Some(fix))
+}
+
+/// Resolves `path` against `base`, following symlinks when the target exists.
+fn resolve_path(base: &Utf8Path, path: &Utf8Path) -> Utf8PathBuf {
+    if let Ok(x) = base.join(path).canonicalize_utf8() {
+        return x;
+    }
+
+    let base = base.canonicalize_utf8().unwrap_or_else(|_| base.to_owned());
+    let mut result = Utf8PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            camino::Utf8Component::ParentDir => {
+                result.pop();
+            }
+            camino::Utf8Component::CurDir => {}
+            x => result.push(x),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diags;
+    use crate::temp::TempDir;
+
+    #[test]
+    fn patterns() {
+        assert!(matches_pattern("main", "main"));
+        assert!(!matches_pattern("main", "main2"));
+        assert!(matches_pattern("lab*", "lab"));
+        assert!(matches_pattern("lab*", "lab01"));
+        assert!(!matches_pattern("lab*", "my-lab01"));
+        assert!(matches_pattern("*-done", "lab01-done"));
+        assert!(!matches_pattern("*-done", "lab01-done-not"));
+        assert!(matches_pattern("lab*/*", "lab01/solution"));
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("a*b*c", "abc"));
+        assert!(matches_pattern("a*b*c", "a-b-b-c"));
+        assert!(!matches_pattern("a*b*c", "acb"));
+        // The end can't reuse what the middle matched.
+        assert!(!matches_pattern("a*a", "a"));
+        assert!(!matches_pattern("ab*bc", "abc"));
+        assert!(matches_pattern("übung-*", "übung-ß"));
+        assert!(matches_pattern("*ß", "übung-ß"));
+    }
+
+    /// A repo with one commit on `branch`.
+    fn repo(name: &str, branch: &str) -> TempDir {
+        let dir = TempDir::new(name).unwrap();
+        let repo = dir.path();
+        git::git(repo, &["init", "-q"]).unwrap();
+        git::git(
+            repo,
+            &["symbolic-ref", "HEAD", &format!("refs/heads/{branch}")],
+        )
+        .unwrap();
+        git::git(
+            repo,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                "lab",
+            ],
+        )
+        .unwrap();
+        dir
+    }
+
+    fn branch_problems(repo: &Utf8Path, expected: &[&str]) -> Vec<String> {
+        let mut problems = Diags::default();
+        {
+            let mut ctx = Context::for_lab(&mut problems, repo, "lab01");
+            ctx.lab_config.expected_branches = expected.iter().map(|x| x.to_string()).collect();
+            ctx.problems.current_check = Some("branch");
+            let _ = check_branch(&mut ctx);
+        }
+        problems.problems.into_iter().map(|x| x.text).collect()
+    }
+
+    #[test]
+    fn non_ascii_branches() {
+        let dir = repo("branch_non_ascii", "übung/ß-01");
+        assert!(branch_problems(dir.path(), &["übung/*"]).is_empty());
+        assert_eq!(
+            branch_problems(dir.path(), &["main", "lab*"]),
+            ["you're on branch `übung/ß-01`, which is not an expected branch"]
+        );
+    }
+
+    #[test]
+    fn detached_at_a_tag() {
+        let dir = repo("branch_tag", "main");
+        git::git(dir.path(), &["tag", "v1"]).unwrap();
+        git::git(dir.path(), &["checkout", "-q", "--detach", "v1"]).unwrap();
+        assert_eq!(
+            branch_problems(dir.path(), &["main"]),
+            ["HEAD is not on a branch, it's detached at tag `v1`"]
+        );
+    }
+
+    #[test]
+    fn detached_at_a_commit() {
+        let dir = repo("branch_detached", "main");
+        git::git(dir.path(), &["checkout", "-q", "--detach"]).unwrap();
+        let commit = git::head_commit(dir.path()).unwrap();
+        assert_eq!(
+            branch_problems(dir.path(), &["main"]),
+            [format!(
+                "HEAD is not on a branch, it's detached at commit {commit}"
+            )]
+        );
+    }
 }
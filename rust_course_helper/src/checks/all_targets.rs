@@ -0,0 +1,235 @@
+//! Warnings and errors in code `cargo build` doesn't compile: `#[cfg(test)]` modules, tests,
+//! benches and examples. Otherwise they only show up when the tests fail to build.
+//!
+//! The other targets are built on top of the lab's build, which cargo doesn't redo, and `cargo
+//! test` then uses them as they are, so nothing is compiled twice.
+
+use super::{cargo, command_check_return};
+use crate::json::Json;
+use crate::{CheckResult, Context};
+
+struct Message {
+    error: bool,
+    text: String,
+    file: String,
+    line: u64,
+    /// `lib`, `bin`, `test`, `bench` or `example`.
+    kind: String,
+}
+
+impl Message {
+    fn parse(line: &str) -> Option<Message> {
+        let json = Json::parse(line).ok()?;
+        if json.get("reason")?.as_str()? != "compiler-message" {
+            return None;
+        }
+        let message = json.get("message")?;
+        let error = match message.get("level")?.as_str()? {
+            "error" => true,
+            "warning" => false,
+            _ => return None,
+        };
+        // Summaries like "2 warnings emitted" have no spans.
+        let span = message
+            .get("spans")?
+            .as_array()?
+            .iter()
+            .find(|x| matches!(x.get("is_primary"), Some(Json::Bool(true))))?;
+        let line = match span.get("line_start")? {
+            Json::Number(x) => *x as u64,
+            _ => return None,
+        };
+        let kind = json
+            .get("target")?
+            .get("kind")?
+            .as_array()?
+            .first()?
+            .as_str()?;
+        Some(Message {
+            error,
+            text: message.get("message")?.as_str()?.to_string(),
+            file: span.get("file_name")?.as_str()?.to_string(),
+            line,
+            kind: kind.to_string(),
+        })
+    }
+
+    fn location(&self) -> String {
+        format!("{}:{}", self.file, self.line)
+    }
+
+    /// Where the code is, for people. Only problems `cargo build` didn't see are reported, so
+    /// ones in the lib or a binary are in their `#[cfg(test)]` parts.
+    fn code(&self) -> &'static str {
+        match self.kind.as_str() {
+            "bench" => "bench code",
+            "example" => "example code",
+            _ => "test code",
+        }
+    }
+}
+
+/// The messages in cargo's JSON output that the build didn't print on `build_stderr`, each once.
+fn unseen(json: &str, build_stderr: &str) -> Vec<Message> {
+    let mut seen = Vec::new();
+    let mut messages = Vec::new();
+    for message in json.lines().filter_map(Message::parse) {
+        // Warnings in shared code show up once for each target that compiles it, and cargo
+        // prints the build's own again.
+        let key = (message.file.clone(), message.line, message.text.clone());
+        if seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        if build_stderr.contains(&message.location()) && build_stderr.contains(&message.text) {
+            continue;
+        }
+        messages.push(message);
+    }
+    messages
+}
+
+/// Builds every target, after a successful `cargo build` printed `build_stderr`, and reports
+/// what the build didn't find.
+pub fn check_all_targets(ctx: &mut Context, build_stderr: &str) -> CheckResult {
+    let text = "code has compiler warnings";
+    let output = cargo(
+        ctx,
+        &[
+            "build",
+            "--all",
+            "--all-targets",
+            "--message-format=json",
+            "-q",
+        ],
+        text,
+    )?;
+
+    let mut result = Ok(());
+    for message in unseen(&output.stdout.text(), build_stderr) {
+        let location = message.location();
+        let level = if message.error { "error" } else { "warning" };
+        result = Err(ctx.problems.add(
+            format!(
+                "{level} in {}: {} ({location})",
+                message.code(),
+                message.text
+            ),
+            ctx.lab_path.join(&message.file),
+            Some("`cargo build` doesn't compile this, but `cargo test` does".into()),
+        ));
+        ctx.problems.fields([
+            ("line", message.line.to_string()),
+            ("target_kind", message.kind.clone()),
+        ]);
+    }
+    // Without messages, cargo's own output says why it failed.
+    if result.is_ok() {
+        command_check_return(ctx, "cargo", output.status, text, None)?;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diags;
+    use crate::temp::TempDir;
+    use std::fs;
+
+    /// Cargo's JSON for a compiler message, cut down to what's read.
+    fn json(kind: &str, level: &str, text: &str, file: &str, line: u64) -> String {
+        format!(
+            r#"{{"reason":"compiler-message","target":{{"kind":["{kind}"],"name":"lab01"}},"message":{{"level":"{level}","message":"{text}","spans":[{{"file_name":"other.rs","line_start":1,"is_primary":false}},{{"file_name":"{file}","line_start":{line},"is_primary":true}}]}}}}"#
+        )
+    }
+
+    #[test]
+    fn messages() {
+        let message = Message::parse(&json("test", "error", "oops", "tests/a.rs", 3)).unwrap();
+        assert!(message.error);
+        assert_eq!(message.text, "oops");
+        assert_eq!(message.location(), "tests/a.rs:3");
+        assert_eq!(message.code(), "test code");
+        let message = Message::parse(&json("bench", "warning", "x", "benches/b.rs", 1)).unwrap();
+        assert!(!message.error);
+        assert_eq!(message.code(), "bench code");
+        assert_eq!(
+            Message::parse(&json("lib", "warning", "x", "src/lib.rs", 1))
+                .unwrap()
+                .code(),
+            "test code"
+        );
+    }
+
+    #[test]
+    fn other_lines_are_not_messages() {
+        assert!(Message::parse(&json("lib", "note", "x", "src/lib.rs", 1)).is_none());
+        assert!(Message::parse(r#"{"reason":"compiler-artifact"}"#).is_none());
+        assert!(Message::parse(r#"{"reason":"build-finished","success":true}"#).is_none());
+        assert!(Message::parse("warning: unused variable").is_none());
+        // Summaries like "2 warnings emitted".
+        let summary = r#"{"reason":"compiler-message","target":{"kind":["lib"]},"message":{"level":"warning","message":"2 warnings emitted","spans":[]}}"#;
+        assert!(Message::parse(summary).is_none());
+    }
+
+    #[test]
+    fn messages_the_build_printed_are_left_out() {
+        let output = [
+            json("bin", "warning", "unused variable: `x`", "src/main.rs", 2),
+            json("lib", "warning", "unused variable: `y`", "src/lib.rs", 9),
+            // The same code, compiled for the tests.
+            json("lib", "warning", "unused variable: `y`", "src/lib.rs", 9),
+            json("test", "warning", "unused variable: `y`", "src/lib.rs", 9),
+            // Same line, different problem.
+            json("lib", "warning", "unused import", "src/main.rs", 2),
+        ]
+        .join("\n");
+        let build_stderr = "warning: unused variable: `x`\n --> src/main.rs:2:9\n";
+        let unseen: Vec<String> = unseen(&output, build_stderr)
+            .iter()
+            .map(|x| format!("{} {}", x.location(), x.text))
+            .collect();
+        assert_eq!(
+            unseen,
+            [
+                "src/lib.rs:9 unused variable: `y`",
+                "src/main.rs:2 unused import"
+            ]
+        );
+    }
+
+    #[test]
+    fn warnings_only_in_test_code_are_found() {
+        let dir = TempDir::new("all_targets_fixture").unwrap();
+        let lab = dir.path().join("lab01");
+        fs::create_dir_all(lab.join("src")).unwrap();
+        for (path, data) in [
+            (
+                "Cargo.toml",
+                include_str!("../../fixtures/test_warning/Cargo.toml.in"),
+            ),
+            (
+                "src/lib.rs",
+                include_str!("../../fixtures/test_warning/src/lib.rs"),
+            ),
+            (
+                "src/main.rs",
+                include_str!("../../fixtures/test_warning/src/main.rs"),
+            ),
+        ] {
+            fs::write(lab.join(path), data).unwrap();
+        }
+
+        let mut problems = Diags::default();
+        let mut ctx = Context::for_lab(&mut problems, dir.path(), "lab01");
+        ctx.problems.current_check = Some("compiler_warnings");
+        assert!(super::super::check_compiler_warnings(&mut ctx).is_err());
+        let found: Vec<_> = problems.problems.iter().map(|x| x.text.as_str()).collect();
+        assert_eq!(
+            found,
+            ["warning in test code: unused variable: `unused` (src/lib.rs:11)"]
+        );
+        assert_eq!(problems.problems[0].fields["target_kind"], "lib");
+    }
+}
@@ -0,0 +1,388 @@
+//! Checks that the lab implements the functions and types an instructor's API file describes.
+//!
+//! The API file is Rust: items with or without bodies, in the modules they're expected in.
+//!
+//! ```rust,ignore
+//! pub fn parse(input: &str) -> Result<Ast, ParseError>;
+//! pub struct Ast;
+//! impl Ast {
+//!     pub fn len(&self) -> usize;
+//! }
+//! mod geometry {
+//!     pub fn area(shape: &Shape) -> f64;
+//! }
+//! ```
+//!
+//! Signatures match when the names, receivers and number of parameters are the same, and the
+//! types have the same tokens. Lifetimes are ignored, and so are types that mention generic
+//! parameters, since those can be spelled many ways.
+
+use super::manifest::lab_targets;
+use super::syntax::{ParsedFile, parsed_sources};
+use crate::{CheckResult, Context, SkipReason};
+use camino::{Utf8Path, Utf8PathBuf};
+use quote::ToTokens;
+use std::fs;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Fn,
+    Struct,
+    Enum,
+    Trait,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Fn => "function",
+            Kind::Struct => "struct",
+            Kind::Enum => "enum",
+            Kind::Trait => "trait",
+        }
+    }
+}
+
+struct Item {
+    /// Modules, then the type for methods.
+    path: Vec<String>,
+    name: String,
+    kind: Kind,
+    signature: Option<syn::Signature>,
+}
+
+impl Item {
+    fn full_name(&self) -> String {
+        let mut parts = self.path.clone();
+        parts.push(self.name.clone());
+        parts.join("::")
+    }
+}
+
+/// `fn f();` isn't an item syn knows, but it parses as a foreign function.
+fn bodiless_fn(tokens: &proc_macro2::TokenStream) -> Option<syn::Signature> {
+    syn::parse2::<syn::ForeignItemFn>(tokens.clone())
+        .ok()
+        .map(|x| x.sig)
+}
+
+fn collect(items: &[syn::Item], path: &[String], out: &mut Vec<Item>) {
+    let mut push = |name: &syn::Ident, kind, signature| {
+        out.push(Item {
+            path: path.to_vec(),
+            name: name.to_string(),
+            kind,
+            signature,
+        })
+    };
+    for item in items {
+        match item {
+            syn::Item::Fn(x) => push(&x.sig.ident, Kind::Fn, Some(x.sig.clone())),
+            syn::Item::Verbatim(tokens) => {
+                if let Some(sig) = bodiless_fn(tokens) {
+                    push(&sig.ident.clone(), Kind::Fn, Some(sig));
+                }
+            }
+            syn::Item::Struct(x) => push(&x.ident, Kind::Struct, None),
+            syn::Item::Enum(x) => push(&x.ident, Kind::Enum, None),
+            syn::Item::Trait(x) => push(&x.ident, Kind::Trait, None),
+            _ => {}
+        }
+    }
+    for item in items {
+        match item {
+            syn::Item::Mod(x) => {
+                if let Some((_, items)) = &x.content {
+                    let mut path = path.to_vec();
+                    path.push(x.ident.to_string());
+                    collect(items, &path, out);
+                }
+            }
+            syn::Item::Impl(x) => {
+                let syn::Type::Path(ty) = &*x.self_ty else {
+                    continue;
+                };
+                let Some(ty) = ty.path.segments.last() else {
+                    continue;
+                };
+                let mut path = path.to_vec();
+                path.push(ty.ident.to_string());
+                for item in &x.items {
+                    let sig = match item {
+                        syn::ImplItem::Fn(x) => Some(x.sig.clone()),
+                        syn::ImplItem::Verbatim(tokens) => bodiless_fn(tokens),
+                        _ => None,
+                    };
+                    if let Some(sig) = sig {
+                        out.push(Item {
+                            path: path.clone(),
+                            name: sig.ident.to_string(),
+                            kind: Kind::Fn,
+                            signature: Some(sig),
+                        });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Adds the files a module's `mod x;` items declare to `stack`, with their module paths.
+/// `dir` is where the module's children are.
+fn declared_modules(
+    items: &[syn::Item],
+    dir: &Utf8Path,
+    module: &[String],
+    stack: &mut Vec<(Utf8PathBuf, Vec<String>)>,
+) {
+    for item in items {
+        let syn::Item::Mod(x) = item else {
+            continue;
+        };
+        let name = x.ident.to_string();
+        let mut child = module.to_vec();
+        child.push(name.clone());
+        match &x.content {
+            Some((_, items)) => declared_modules(items, &dir.join(&name), &child, stack),
+            None => {
+                let file = dir.join(format!("{name}.rs"));
+                match file.exists() {
+                    true => stack.push((file, child)),
+                    false => stack.push((dir.join(&name).join("mod.rs"), child)),
+                }
+            }
+        }
+    }
+}
+
+/// The files of the crate whose root is `root`, with the module each one is, found by
+/// following its `mod` items. Files of other targets, like a `main.rs` next to `lib.rs`, and
+/// files no module declares aren't in it.
+fn module_tree<'a>(
+    sources: &'a [ParsedFile],
+    root: &Utf8Path,
+) -> Vec<(Vec<String>, &'a ParsedFile)> {
+    let mut result = Vec::new();
+    let mut stack = vec![(root.to_owned(), Vec::new())];
+    while let Some((path, module)) = stack.pop() {
+        let Some(source) = sources.iter().find(|x| x.path == path) else {
+            continue;
+        };
+        // A crate root's and a `mod.rs`'s children are next to them, others' are in a folder
+        // named after them.
+        let dir = match module.is_empty() || path.file_name() == Some("mod.rs") {
+            true => path.parent().unwrap_or(&path).to_owned(),
+            false => path.with_extension(""),
+        };
+        declared_modules(&source.file.items, &dir, &module, &mut stack);
+        result.push((module, source));
+    }
+    result
+}
+
+fn items_of(sources: &[ParsedFile], root: &Utf8Path) -> Vec<Item> {
+    let mut found = Vec::new();
+    for (path, source) in module_tree(sources, root) {
+        collect(&source.file.items, &path, &mut found);
+    }
+    found
+}
+
+fn normalize(tokens: &impl ToTokens) -> String {
+    let mut text: String = tokens
+        .to_token_stream()
+        .to_string()
+        .chars()
+        .filter(|x| !x.is_whitespace())
+        .collect();
+    // Drop lifetimes: `&'a str` is `&str`, `Foo<'a, T>` is `Foo<T>`.
+    while let Some(start) = text.find('\'') {
+        let end = text[start + 1..]
+            .find(|x: char| !x.is_alphanumeric() && x != '_')
+            .map_or(text.len(), |x| start + 1 + x);
+        let end = if text[end..].starts_with(',') {
+            end + 1
+        } else {
+            end
+        };
+        text.replace_range(start..end, "");
+    }
+    text.replace("<>", "")
+}
+
+fn generic_names(sig: &syn::Signature) -> Vec<String> {
+    sig.generics
+        .type_params()
+        .map(|x| x.ident.to_string())
+        .chain(sig.inputs.iter().filter_map(|x| match x {
+            // `impl Trait` arguments are generic too.
+            syn::FnArg::Typed(x) if matches!(*x.ty, syn::Type::ImplTrait(_)) => {
+                Some("impl".to_string())
+            }
+            _ => None,
+        }))
+        .collect()
+}
+
+fn mentions_generic(ty: &str, generics: &[String]) -> bool {
+    generics.iter().any(|name| {
+        ty.match_indices(name.as_str()).any(|(i, _)| {
+            let before = ty[..i].chars().next_back();
+            let after = ty[i + name.len()..].chars().next();
+            !before.is_some_and(|x| x.is_alphanumeric() || x == '_')
+                && !after.is_some_and(|x| x.is_alphanumeric() || x == '_')
+        })
+    })
+}
+
+fn types_match(expected: &str, found: &str, generics: &[String]) -> bool {
+    expected == found || mentions_generic(expected, generics) || mentions_generic(found, generics)
+}
+
+fn param_types(sig: &syn::Signature) -> Vec<String> {
+    sig.inputs
+        .iter()
+        .map(|x| match x {
+            syn::FnArg::Receiver(x) => {
+                let reference = match (&x.reference, &x.mutability) {
+                    (Some(_), Some(_)) => "&mut",
+                    (Some(_), None) => "&",
+                    (None, _) => "",
+                };
+                format!("{reference}self")
+            }
+            syn::FnArg::Typed(x) => normalize(&x.ty),
+        })
+        .collect()
+}
+
+fn signatures_match(expected: &syn::Signature, found: &syn::Signature) -> bool {
+    let mut generics = generic_names(expected);
+    generics.extend(generic_names(found));
+
+    let expected_params = param_types(expected);
+    let found_params = param_types(found);
+    if expected_params.len() != found_params.len() {
+        return false;
+    }
+    let params_match = expected_params
+        .iter()
+        .zip(&found_params)
+        .all(|(a, b)| types_match(a, b, &generics));
+    let output_match = types_match(
+        &normalize(&expected.output),
+        &normalize(&found.output),
+        &generics,
+    );
+    params_match && output_match
+}
+
+fn show(sig: &syn::Signature) -> String {
+    let text = sig.to_token_stream().to_string();
+    // Token streams print with spaces everywhere; tidy up the most common cases.
+    text.replace(" (", "(")
+        .replace("( ", "(")
+        .replace(" )", ")")
+        .replace(" ,", ",")
+        .replace(" :", ":")
+        .replace("& ", "&")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+}
+
+pub fn check_api(ctx: &mut Context) -> CheckResult {
+    let Some(api_path) = ctx.lab_config.api_file.clone() else {
+        return ctx.skip(SkipReason::NotConfigured);
+    };
+    let api = fs::read_to_string(&api_path)
+        .map_err(|e| e.to_string())
+        .and_then(|x| syn::parse_file(&x).map_err(|e| e.to_string()));
+    let api = match api {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(ctx
+                .problems
+                .add(format!("can't read the API file: {e}"), api_path, None));
+        }
+    };
+    let mut required = Vec::new();
+    collect(&api.items, &[], &mut required);
+
+    // A library is what other code uses, so with both it's what has the API; the binary is
+    // only looked at to explain items put there instead.
+    let targets = lab_targets(ctx).unwrap_or_default();
+    let lib = targets.iter().find(|x| x.is_lib());
+    let bin = targets.iter().find(|x| x.is_bin());
+    let (root, target, other) = match (lib, bin) {
+        (Some(lib), bin) => (lib.src_path.clone(), lib.describe(&ctx.lab_path), bin),
+        (None, Some(bin)) => (bin.src_path.clone(), bin.describe(&ctx.lab_path), None),
+        // Without cargo's view, the same files cargo would pick.
+        (None, None) => {
+            let lib = ctx.lab_path.join("src/lib.rs");
+            let root = match lib.exists() {
+                true => lib,
+                false => ctx.lab_path.join("src/main.rs"),
+            };
+            let target = root
+                .strip_prefix(&ctx.lab_path)
+                .unwrap_or(&root)
+                .to_string();
+            (root, target, None)
+        }
+    };
+    let sources = parsed_sources(ctx);
+    let found = items_of(&sources, &root);
+    let in_other = other.map(|x| (x.describe(&ctx.lab_path), items_of(&sources, &x.src_path)));
+
+    let mut result = Ok(());
+    for item in &required {
+        let candidates: Vec<&Item> = found
+            .iter()
+            .filter(|x| x.name == item.name && x.kind == item.kind)
+            .collect();
+        let at_path: Vec<&&Item> = candidates.iter().filter(|x| x.path == item.path).collect();
+        let help = format!(
+            "grading expects the {} exactly as described, in {target}",
+            item.kind.name()
+        );
+
+        let text = match (at_path.as_slice(), &item.signature) {
+            ([], _) => match candidates.first() {
+                Some(x) => format!(
+                    "`{}` should be at `{}`, found it at `{}`",
+                    item.name,
+                    item.full_name(),
+                    x.full_name()
+                ),
+                None => match &in_other {
+                    Some((other, items)) if items.iter().any(|x| x.name == item.name) => format!(
+                        "`{}` is in {other}, but should be in {target}",
+                        item.full_name()
+                    ),
+                    _ => format!("missing {} `{}`", item.kind.name(), item.full_name()),
+                },
+            },
+            (found, Some(expected)) => {
+                let same = found.iter().any(|x| {
+                    x.signature
+                        .as_ref()
+                        .is_some_and(|x| signatures_match(expected, x))
+                });
+                if same {
+                    continue;
+                }
+                let found_sig = found[0].signature.as_ref().map(show).unwrap_or_default();
+                format!(
+                    "`{}` should be `{}`, found `{found_sig}`",
+                    item.full_name(),
+                    show(expected)
+                )
+            }
+            (_, None) => continue,
+        };
+        result = Err(ctx.problems.add(text, root.clone(), Some(help)));
+    }
+    result
+}
@@ -0,0 +1,93 @@
+//! `--build-timings`: which dependencies make a slow build slow.
+//!
+//! `cargo build --timings` writes an HTML report with each compiled unit's duration in a
+//! `UNIT_DATA` array. Units of the same crate are added up, build scripts included, and the
+//! lab's own crates are left out, since those are what the lab is about.
+
+use super::dependencies::{package, workspace_members};
+use super::target_dirs;
+use crate::json::Json;
+use crate::{Context, artifacts};
+use camino::Utf8PathBuf;
+use std::fs;
+use std::time::Duration;
+
+const REPORT: &str = "cargo-timing.html";
+const MAX_LISTED_CRATES: usize = 5;
+
+/// The report the last `cargo build --timings` wrote.
+fn report(ctx: &Context) -> Option<(Utf8PathBuf, String)> {
+    let path = target_dirs(ctx)
+        .into_iter()
+        .map(|x| x.join("cargo-timings").join(REPORT))
+        .filter(|x| x.is_file())
+        .max_by_key(|x| x.metadata().and_then(|x| x.modified()).ok())?;
+    let text = fs::read_to_string(&path).ok()?;
+    Some((path, text))
+}
+
+/// Each crate's name and version, and how long its units took to compile, slowest first.
+fn crate_durations(report: &str) -> Option<Vec<(String, String, f64)>> {
+    let start = report.find("const UNIT_DATA = ")? + "const UNIT_DATA = ".len();
+    let len = report[start..].find("\n];")? + 2;
+    let units = Json::parse(&report[start..start + len]).ok()?;
+    let mut crates: Vec<(String, String, f64)> = Vec::new();
+    for unit in units.as_array()? {
+        let (Some(name), Some(version), Some(Json::Number(duration))) = (
+            unit.get("name").and_then(Json::as_str),
+            unit.get("version").and_then(Json::as_str),
+            unit.get("duration"),
+        ) else {
+            continue;
+        };
+        match crates.iter_mut().find(|x| x.0 == name && x.1 == version) {
+            Some(x) => x.2 += duration,
+            None => crates.push((name.into(), version.into(), *duration)),
+        }
+    }
+    crates.sort_by(|a, b| b.2.total_cmp(&a.2));
+    Some(crates)
+}
+
+/// Looks at the report of a `cargo build --timings` that took `duration`, attaching it as an
+/// artifact, and warns about the slowest dependencies if the build was slow.
+pub fn check_build_timings(ctx: &mut Context, duration: Duration) {
+    let Some((path, text)) = report(ctx) else {
+        if ctx.verbose {
+            say!("cargo didn't write a timings report");
+        }
+        return;
+    };
+    artifacts::add(ctx.problems, REPORT, "text/html", text.as_bytes());
+    if duration.as_secs() < ctx.lab_config.slow_build_seconds {
+        return;
+    }
+    let Some(crates) = crate_durations(&text) else {
+        return;
+    };
+    let own: Vec<String> = workspace_members(ctx)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|x| package(x).map(|x| x.0))
+        .collect();
+    let slowest: Vec<String> = crates
+        .iter()
+        .filter(|x| !own.contains(&x.0))
+        .take(MAX_LISTED_CRATES)
+        .map(|(name, version, seconds)| format!("  {name} v{version}: {seconds:.1}s"))
+        .collect();
+    if slowest.is_empty() {
+        return;
+    }
+    ctx.problems.warn(
+        format!(
+            "the build took {}s; the dependencies that took longest to compile are:\n{}",
+            duration.as_secs(),
+            slowest.join("\n")
+        ),
+        path,
+        Some("depend only on the crates the lab needs, and turn off the features it doesn't use, like `features = [\"full\"]`; `default-features = false` turns off the defaults".into()),
+    );
+    ctx.problems
+        .fields([("build_seconds", duration.as_secs().to_string())]);
+}
@@ -0,0 +1,386 @@
+//! Third-party dependencies that fail to build. The lab's own code can be fine then, so they
+//! get their own problems, naming the crate, with hints picked from the error.
+
+use super::output;
+use crate::exec::Exec;
+use crate::json::Json;
+use crate::{CheckError, Context};
+
+const MAX_ERRORS_PER_CRATE: usize = 3;
+const MAX_STDERR_LINES: usize = 10;
+
+struct Failure {
+    name: String,
+    version: String,
+    /// `compile`, `build script` or `rust version`.
+    kind: &'static str,
+    /// The errors, as cargo or rustc worded them.
+    errors: Vec<String>,
+}
+
+/// The name and version in a package ID, if it comes from a registry or git.
+fn third_party(id: &str) -> Option<(String, String)> {
+    match id.contains("path+file:") {
+        true => None,
+        false => package(id),
+    }
+}
+
+/// The name and version in a package ID, in the old `name version (source)` format or the
+/// new `source#name@version` one.
+pub fn package(id: &str) -> Option<(String, String)> {
+    if let Some((source, rest)) = id.rsplit_once('#') {
+        return Some(match rest.split_once('@') {
+            Some((name, version)) => (name.into(), version.into()),
+            // The name is left out when it's the last part of the source's URL.
+            None => {
+                let name = source.rsplit('/').next().unwrap_or(source);
+                (name.split('?').next().unwrap_or(name).into(), rest.into())
+            }
+        });
+    }
+    let mut parts = id.split_whitespace();
+    Some((parts.next()?.into(), parts.next()?.into()))
+}
+
+/// The IDs of the packages in the lab's workspace.
+pub fn workspace_members(ctx: &mut Context) -> Option<Vec<String>> {
+    let cargo = Exec::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .cwd(&ctx.lab_path)
+        .stdin_null()
+        .limit(usize::MAX)
+        .description("cargo metadata");
+    let output = output(ctx, &cargo).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let metadata = Json::parse(&output.stdout.text()).ok()?;
+    let members = metadata.get("workspace_members")?.as_array()?;
+    Some(
+        members
+            .iter()
+            .filter_map(|x| Some(x.as_str()?.to_string()))
+            .collect(),
+    )
+}
+
+fn add_failure(
+    failures: &mut Vec<Failure>,
+    name: &str,
+    version: &str,
+    kind: &'static str,
+    error: String,
+) {
+    let version = version.trim_start_matches('v');
+    match failures
+        .iter_mut()
+        .find(|x| x.name == name && x.version == version)
+    {
+        Some(x) if x.errors.len() < MAX_ERRORS_PER_CRATE && !x.errors.contains(&error) => {
+            x.errors.push(error)
+        }
+        Some(_) => {}
+        None => failures.push(Failure {
+            name: name.into(),
+            version: version.into(),
+            kind,
+            errors: vec![error],
+        }),
+    }
+}
+
+/// Errors in the compiler's messages that came from packages outside the workspace.
+fn compile_failures(failures: &mut Vec<Failure>, stdout: &str, members: &[String]) {
+    for line in stdout.lines() {
+        let Ok(json) = Json::parse(line) else {
+            continue;
+        };
+        if json.get("reason").and_then(Json::as_str) != Some("compiler-message") {
+            continue;
+        }
+        let Some(id) = json.get("package_id").and_then(Json::as_str) else {
+            continue;
+        };
+        let Some(message) = json.get("message") else {
+            continue;
+        };
+        if members.iter().any(|x| x == id)
+            || message.get("level").and_then(Json::as_str) != Some("error")
+        {
+            continue;
+        }
+        let (Some((name, version)), Some(text)) = (
+            third_party(id),
+            message.get("message").and_then(Json::as_str),
+        ) else {
+            continue;
+        };
+        // Summaries like "aborting due to 2 previous errors" say nothing new.
+        if !text.starts_with("aborting due to") {
+            add_failure(failures, &name, &version, "compile", text.to_string());
+        }
+    }
+}
+
+/// Whether the package cargo names as `name version` is the lab's own, going by the package IDs
+/// of the workspace's `members`.
+fn is_member(members: &[String], name: &str, version: &str) -> bool {
+    let version = version.trim_start_matches('v');
+    members
+        .iter()
+        .filter_map(|x| package(x))
+        .any(|(x, y)| x == name && y == version)
+}
+
+/// The name and version in cargo's `name v1.2.3 (source)`, if it's a third-party package. Only
+/// packages from the file system have a source that isn't a URL, and registry ones have none.
+fn third_party_in_text(text: &str) -> Option<(&str, &str)> {
+    let (package, source) = match text.split_once(" (") {
+        Some((package, source)) => (package, Some(source.trim_end_matches(')'))),
+        None => (text, None),
+    };
+    if source.is_some_and(|x| !x.contains("://")) {
+        return None;
+    }
+    package.split_once(' ')
+}
+
+/// Failures cargo reports itself: build scripts that failed, and crates that need a newer
+/// Rust than the installed one. The lab's own are left to the caller.
+fn cargo_failures(failures: &mut Vec<Failure>, stderr: &str, members: &[String]) {
+    let lines: Vec<&str> = stderr.lines().collect();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(rest) = line
+            .split("failed to run custom build command for `")
+            .nth(1)
+            && let Some((name, version)) =
+                third_party_in_text(rest.split('`').next().unwrap_or(rest))
+            && !is_member(members, name, version)
+        {
+            let excerpt: Vec<&str> = lines[i + 1..]
+                .iter()
+                .copied()
+                .filter(|x| !x.trim().is_empty())
+                .take(MAX_STDERR_LINES)
+                .collect();
+            add_failure(failures, name, version, "build script", excerpt.join("\n"));
+        }
+        // `  name@version requires rustc 1.80`, under a line saying the compiler is too old.
+        if let Some((package, needed)) = line.trim().split_once(" requires rustc ")
+            && let Some((name, version)) = package.split_once('@')
+            && !is_member(members, name, version)
+        {
+            add_failure(
+                failures,
+                name,
+                version,
+                "rust version",
+                format!("it needs Rust {needed} or newer"),
+            );
+        }
+    }
+}
+
+/// What to try, going by the error.
+fn hint(failure: &Failure) -> String {
+    let name = &failure.name;
+    let text = failure.errors.join("\n").to_lowercase();
+    let pin = format!("`cargo update -p {name} --precise <older version>`");
+    if failure.kind == "rust version"
+        || text.contains("is unstable")
+        || text.contains("requires rustc")
+        || text.contains("edition2024")
+    {
+        return format!(
+            "the crate needs a newer Rust than the installed one: run `rustup update`, or pin an older version of it with {pin}"
+        );
+    }
+    let build_script = failure.kind == "build script";
+    if text.contains("cannot find -l")
+        || build_script
+            && [
+                "pkg-config",
+                "pkg_config",
+                "system library",
+                "could not find",
+                "not found",
+            ]
+            .iter()
+            .any(|x| text.contains(x))
+    {
+        return format!(
+            "`{name}` needs a library installed on the system, which the error names; install it with your package manager, or use a version of the crate that doesn't need it"
+        );
+    }
+    format!(
+        "your code isn't the problem; pin an older version of `{name}` with {pin}, or use a different crate"
+    )
+}
+
+/// Reports the dependencies that made `cargo build` fail, after it printed `build_stderr`.
+/// Returns `None` when none did, so the failure is the lab's own.
+pub fn check_dependency_failures(ctx: &mut Context, build_stderr: &str) -> Option<CheckError> {
+    let members = workspace_members(ctx)?;
+    let mut failures = Vec::new();
+    cargo_failures(&mut failures, build_stderr, &members);
+    if failures.is_empty() {
+        let cargo = Exec::new("cargo")
+            .args(["build", "--all", "--message-format=json", "-q"])
+            .cwd(&ctx.lab_path)
+            .stdin_null()
+            .limit(usize::MAX);
+        let output = output(ctx, &cargo).ok()?;
+        compile_failures(&mut failures, &output.stdout.text(), &members);
+    }
+
+    let mut result = None;
+    for failure in failures {
+        let what = match failure.kind {
+            "build script" => "its build script failed",
+            "rust version" => "the installed Rust is too old for it",
+            _ => "it doesn't compile",
+        };
+        let help = hint(&failure);
+        result = Some(ctx.problems.add(
+            format!(
+                "dependency `{} v{}` failed to build because {what}, so the lab's own code couldn't be checked:\n{}",
+                failure.name,
+                failure.version,
+                failure.errors.join("\n")
+            ),
+            ctx.lab_path.join("Cargo.toml"),
+            Some(help),
+        ));
+        ctx.problems.fields([
+            ("dependency", failure.name),
+            ("dependency_version", failure.version),
+            ("failure", failure.kind.to_string()),
+        ]);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diags;
+    use crate::temp::TempDir;
+    use camino::Utf8Path;
+    use std::fs;
+
+    const MEMBERS: &[&str] = &["path+file:///tmp/rv/lab01#0.1.0"];
+
+    fn failures(stderr: &str) -> Vec<(String, String, &'static str)> {
+        let members: Vec<String> = MEMBERS.iter().map(|x| x.to_string()).collect();
+        let mut failures = Vec::new();
+        cargo_failures(&mut failures, stderr, &members);
+        failures
+            .into_iter()
+            .map(|x| (x.name, x.version, x.kind))
+            .collect()
+    }
+
+    #[test]
+    fn build_scripts_of_dependencies() {
+        let stderr = "error: failed to run custom build command for `openssl-sys v0.9.1`\n\n\
+            Caused by:\n  could not find system library 'openssl'\n";
+        let expected = ("openssl-sys".into(), "0.9.1".into(), "build script");
+        assert_eq!(failures(stderr), [expected]);
+        // Git dependencies have a URL after the version.
+        let stderr = "error: failed to run custom build command for \
+            `helper v0.2.0 (https://github.com/me/helper#0123abcd)`\n";
+        let expected = ("helper".into(), "0.2.0".into(), "build script");
+        assert_eq!(failures(stderr), [expected]);
+    }
+
+    #[test]
+    fn the_labs_own_build_script_is_left_out() {
+        let stderr =
+            "error: failed to run custom build command for `lab01 v0.1.0 (/tmp/rv/lab01)`\n";
+        assert_eq!(failures(stderr), []);
+        // Other crates in the repo are the student's too.
+        let stderr =
+            "error: failed to run custom build command for `shared v0.1.0 (/tmp/rv/shared)`\n";
+        assert_eq!(failures(stderr), []);
+    }
+
+    #[test]
+    fn rust_versions() {
+        let stderr = "error: rustc 1.95.0 is not supported by the following packages:\n  \
+            lab01@0.1.0 requires rustc 1.999\n  slow@0.3.1 requires rustc 1.999\n";
+        let expected = ("slow".into(), "0.3.1".into(), "rust version");
+        assert_eq!(failures(stderr), [expected]);
+    }
+
+    /// Builds `lab01` in a repo inside `dir`, whose manifest has `dependencies`, and has the
+    /// compiler warnings check report what made the build fail.
+    fn build(dir: &Utf8Path, dependencies: &str, build_script: Option<&str>) -> Vec<String> {
+        let lab = dir.join("repo/lab01");
+        fs::create_dir_all(lab.join("src")).unwrap();
+        let manifest = format!(
+            "[package]\nname = \"lab01\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+            [dependencies]\n{dependencies}"
+        );
+        fs::write(lab.join("Cargo.toml"), manifest).unwrap();
+        fs::write(lab.join("src/main.rs"), "fn main() {}\n").unwrap();
+        if let Some(script) = build_script {
+            fs::write(lab.join("build.rs"), script).unwrap();
+        }
+        let mut problems = Diags::default();
+        let mut ctx = Context::for_lab(&mut problems, &dir.join("repo"), "lab01");
+        ctx.problems.current_check = Some("compiler_warnings");
+        assert!(super::super::check_compiler_warnings(&mut ctx).is_err());
+        problems.problems.into_iter().map(|x| x.text).collect()
+    }
+
+    #[test]
+    fn dependencies_that_need_a_newer_rust() {
+        let dir = TempDir::new("dependencies_msrv").unwrap();
+        // A git dependency, which cargo fetches without the network.
+        let slow = dir.path().join("slow");
+        fs::create_dir_all(slow.join("src")).unwrap();
+        let manifest = include_str!("../../fixtures/msrv/Cargo.toml.in");
+        fs::write(slow.join("Cargo.toml"), manifest).unwrap();
+        let lib = include_str!("../../fixtures/msrv/src/lib.rs");
+        fs::write(slow.join("src/lib.rs"), lib).unwrap();
+        crate::git::git(&slow, &["init", "-q"]).unwrap();
+        crate::git::git(&slow, &["add", "."]).unwrap();
+        let commit = [
+            "-c",
+            "user.name=test",
+            "-c",
+            "user.email=test@example.com",
+            "commit",
+            "-q",
+            "-m",
+            "slow",
+        ];
+        crate::git::git(&slow, &commit).unwrap();
+
+        let url = format!(
+            "file:///{}",
+            slow.as_str().replace('\\', "/").trim_start_matches('/')
+        );
+        let problems = build(dir.path(), &format!("slow = {{ git = \"{url}\" }}\n"), None);
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].starts_with(
+                "dependency `slow v0.1.0` failed to build because the installed Rust is too old for it"
+            ),
+            "{problems:?}"
+        );
+    }
+
+    #[test]
+    fn the_labs_own_build_script_is_the_labs_problem() {
+        let dir = TempDir::new("dependencies_own_build_script").unwrap();
+        let script = "fn main() {\n    panic!(\"no config\");\n}\n";
+        let problems = build(dir.path(), "", Some(script));
+        assert_eq!(problems.len(), 1, "{problems:?}");
+        assert!(
+            problems[0].starts_with("code has compiler warnings; command `cargo` failed"),
+            "{problems:?}"
+        );
+    }
+}
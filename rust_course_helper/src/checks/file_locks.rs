@@ -0,0 +1,55 @@
+//! Builds that fail because something else has files in `target/` open. On Windows that's
+//! usually real-time antivirus scanning, or OneDrive syncing the repo, and trying again often
+//! works.
+
+/// What cargo and the linkers print when they can't write a file someone else has open.
+const PATTERNS: &[&str] = &[
+    "Access is denied",
+    "being used by another process",
+    "LNK1104",
+    "LNK1168",
+    "cannot open output file",
+];
+/// Windows' `ERROR_ACCESS_DENIED` and `ERROR_SHARING_VIOLATION`. Elsewhere these codes are
+/// `EIO` and `EPIPE`, which have nothing to do with locks.
+const WINDOWS_PATTERNS: &[&str] = &["(os error 5)", "(os error 32)"];
+
+/// The first line of `stderr` that shows a file was locked, if there is one.
+pub fn locked_file_line(stderr: &str) -> Option<&str> {
+    stderr
+        .lines()
+        .find(|x| {
+            let windows = cfg!(windows) && WINDOWS_PATTERNS.iter().any(|p| x.contains(p));
+            windows || PATTERNS.iter().any(|p| x.contains(p))
+        })
+        .map(str::trim)
+}
+
+/// Why files get locked and what to do about it, with the likely culprit first.
+pub fn help(repo: &str) -> String {
+    let mut help = String::new();
+    if repo.contains("OneDrive") {
+        help += "the repo is in a OneDrive folder, which locks files while syncing them; \
+            move the repo to a folder that isn't synced, like C:\\dev. ";
+    }
+    help += "antivirus scanning `target/` while cargo writes to it is the usual cause: add the \
+        repo to the exclusions of Windows Security's real-time protection, close programs that \
+        may have the built binary open, and run the checker again";
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_codes_only_mean_a_lock_on_windows() {
+        let stderr = "error: failed to write `target/debug/lab01`\n  Broken pipe (os error 32)\n";
+        assert_eq!(
+            locked_file_line(stderr),
+            cfg!(windows).then_some("Broken pipe (os error 32)")
+        );
+        let stderr = "LINK : fatal error LNK1104: cannot open file 'lab01.exe'";
+        assert_eq!(locked_file_line(stderr), Some(stderr));
+    }
+}
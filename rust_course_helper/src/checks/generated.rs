@@ -0,0 +1,182 @@
+//! Committed files that a generator script produces. Hand-edited ones make tests pass that
+//! shouldn't, so they're compared against a fresh run of the generator.
+
+use super::with_lab_env;
+use crate::exec::{Exec, Stream};
+use crate::temp::TempDir;
+use crate::{CheckError, CheckResult, Context, SkipReason, diff, git};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::BTreeSet;
+use std::fs;
+use std::time::Duration;
+
+const MAX_REPORTED_FILES: usize = 10;
+const MAX_DIFF_LINES: usize = 8;
+const MAX_STDERR_LINES: usize = 10;
+
+/// Every file under `dir`, relative to it.
+fn files(dir: &Utf8Path) -> BTreeSet<Utf8PathBuf> {
+    let mut result = BTreeSet::new();
+    let mut stack = vec![dir.to_owned()];
+    while let Some(x) = stack.pop() {
+        let Ok(entries) = x.read_dir_utf8() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.into_path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if let Ok(relative) = path.strip_prefix(dir) {
+                result.insert(relative.to_owned());
+            }
+        }
+    }
+    result
+}
+
+/// Runs the generator into `out`. The error explains what went wrong.
+fn generate(ctx: &mut Context, out: &Utf8Path) -> Result<(), String> {
+    let generator = ctx.lab_config.generator.clone();
+    let [program, args @ ..] = generator.as_slice() else {
+        return Err("the config's `generator` is empty".into());
+    };
+    let args: Vec<String> = args
+        .iter()
+        .map(|x| x.replace("{out}", out.as_str()))
+        .collect();
+    let description = format!("{program} {}", args.join(" "));
+
+    let timeout = Duration::from_secs(ctx.lab_config.generator_timeout);
+    let exec = Exec::new(program)
+        .args(&args)
+        .cwd(&ctx.lab_path)
+        .stdin_null()
+        .stdout(Stream::Discard)
+        .timeout(timeout)
+        .description(description.clone());
+    let exec = with_lab_env(ctx, &exec);
+    if ctx.verbose {
+        say!("running command: {}", exec.describe());
+    }
+    let output = exec
+        .run()
+        .map_err(|e| format!("can't run `{program}`: {e}"))?;
+    ctx.problems
+        .record_command(exec.describe(), output.duration, output.usage);
+    let stderr = output.stderr.text();
+
+    let status = (!output.timed_out).then_some(output.status);
+    match status {
+        None => Err(format!(
+            "`{description}` was still running after {} seconds",
+            timeout.as_secs()
+        )),
+        Some(x) if !x.success() => {
+            let excerpt: Vec<&str> = stderr.trim().lines().take(MAX_STDERR_LINES).collect();
+            Err(format!(
+                "`{description}` failed: {x}\n{}",
+                excerpt.join("\n")
+            ))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+/// Whether the committed file at `path`, relative to the lab, and the freshly generated `file`
+/// are the same to git, so they only differ in line endings the checkout rewrote.
+fn same_in_git(ctx: &Context, path: &Utf8Path, file: &Utf8Path) -> bool {
+    let lab = &ctx.lab_path;
+    match (
+        git::content_hash(lab, path, &lab.join(path)),
+        git::content_hash(lab, path, file),
+    ) {
+        (Ok(old), Ok(new)) => old == new,
+        _ => false,
+    }
+}
+
+pub fn check_generated_files(ctx: &mut Context) -> CheckResult {
+    let Some(dir) = ctx.lab_config.generated_dir.clone() else {
+        return ctx.skip(SkipReason::NotConfigured);
+    };
+    if ctx.lab_config.generator.is_empty() {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let committed_dir = ctx.lab_path.join(&dir);
+
+    let temp = TempDir::new("generated").map_err(|e| ctx.problems.add(e, None, None))?;
+    if let Err(e) = generate(ctx, temp.path()) {
+        return Err(ctx.problems.add(
+            format!("can't generate the files in `{dir}`: {e}"),
+            ctx.lab_path.clone(),
+            Some(
+                "run the generator yourself to see why it fails; it has to work on a fresh clone"
+                    .into(),
+            ),
+        ));
+    }
+
+    let generated = files(temp.path());
+    let committed = files(&committed_dir);
+    let help = Some(format!(
+        "don't edit the files in `{dir}` by hand; run the generator again and commit what it writes"
+    ));
+    let mut problems = Vec::new();
+    let mut normalized = 0;
+    for path in generated.union(&committed) {
+        let old = fs::read(committed_dir.join(path));
+        let new = fs::read(temp.path().join(path));
+        let text = match (old, new) {
+            (Err(_), _) => format!("`{dir}/{path}` is missing; the generator creates it"),
+            (_, Err(_)) => format!("`{dir}/{path}` isn't created by the generator"),
+            (Ok(old), Ok(new)) if old == new => continue,
+            _ if same_in_git(
+                ctx,
+                &Utf8Path::new(&dir).join(path),
+                &temp.path().join(path),
+            ) =>
+            {
+                normalized += 1;
+                continue;
+            }
+            (Ok(old), Ok(new)) => match (String::from_utf8(old), String::from_utf8(new)) {
+                (Ok(old), Ok(new)) => format!(
+                    "`{dir}/{path}` differs from what the generator creates:\n{}",
+                    diff::excerpt(&old, &new, MAX_DIFF_LINES)
+                ),
+                _ => format!("`{dir}/{path}` differs from what the generator creates"),
+            },
+        };
+        problems.push((text, committed_dir.join(path)));
+    }
+
+    if normalized > 0 && ctx.verbose {
+        let settings = git::line_ending_settings(&ctx.lab_path);
+        say!(
+            "{normalized} generated files only differ in line endings, which git normalizes{}",
+            match settings.is_empty() {
+                true => String::new(),
+                false => format!(" ({})", settings.join(", ")),
+            }
+        );
+    }
+
+    let count = problems.len();
+    for (text, path) in problems.into_iter().take(MAX_REPORTED_FILES) {
+        ctx.problems.add(text, path, help.clone());
+    }
+    if count > MAX_REPORTED_FILES {
+        ctx.problems.add(
+            format!(
+                "..and {} more generated files differ",
+                count - MAX_REPORTED_FILES
+            ),
+            committed_dir,
+            help,
+        );
+    }
+    match count {
+        0 => Ok(()),
+        _ => Err(CheckError),
+    }
+}
@@ -0,0 +1,59 @@
+//! Internal compiler errors: rustc crashing, which is a bug in rustc and not in the lab.
+
+use crate::Context;
+use camino::Utf8PathBuf;
+
+const MAX_QUERY_STACK_LINES: usize = 8;
+
+pub struct Ice {
+    /// Like `rustc 1.80.0 (051478957 2024-07-21)`.
+    pub version: Option<String>,
+    pub query_stack: Vec<String>,
+    /// The `rustc-ice-*.txt` file rustc wrote the full report to.
+    pub report: Option<Utf8PathBuf>,
+}
+
+/// What cargo's output says about the crash, if the compiler crashed.
+pub fn find(ctx: &Context, output: &str) -> Option<Ice> {
+    if !output.contains("internal compiler error")
+        && !output.contains("the compiler unexpectedly panicked")
+    {
+        return None;
+    }
+    let version = output.lines().find_map(|x| {
+        let x = x.trim().strip_prefix("note: ")?;
+        let version = x.split(" running on ").next()?;
+        version.starts_with("rustc ").then(|| version.to_string())
+    });
+    let query_stack = output
+        .lines()
+        .skip_while(|x| !x.starts_with("query stack during panic"))
+        .skip(1)
+        .take_while(|x| !x.starts_with("end of query stack"))
+        .take(MAX_QUERY_STACK_LINES)
+        .map(String::from)
+        .collect();
+    // rustc names the file in a note, and writes it to the folder it ran in.
+    let named = output.lines().find_map(|x| {
+        let start = x.find("rustc-ice-")?;
+        let path = x[..start].rsplit(['`', ' ']).next().unwrap_or_default();
+        let name = x[start..].split(['`', ' ']).next()?;
+        Some(Utf8PathBuf::from(format!("{path}{name}")))
+    });
+    let report = named.filter(|x| x.is_file()).or_else(|| newest_report(ctx));
+    Some(Ice {
+        version,
+        query_stack,
+        report,
+    })
+}
+
+fn newest_report(ctx: &Context) -> Option<Utf8PathBuf> {
+    ctx.lab_path
+        .read_dir_utf8()
+        .ok()?
+        .flatten()
+        .filter(|x| x.file_name().starts_with("rustc-ice-") && x.file_name().ends_with(".txt"))
+        .max_by_key(|x| x.metadata().and_then(|x| x.modified()).ok())
+        .map(|x| x.into_path())
+}
@@ -0,0 +1,70 @@
+//! `include!` and friends reading files from outside the lab.
+
+use super::resolve_path;
+use super::syntax::scanned_sources;
+use crate::{CheckResult, Context};
+use camino::{Utf8Path, Utf8PathBuf};
+use syn::visit::{self, Visit};
+
+const INCLUDE_MACROS: &[&str] = &["include", "include_str", "include_bytes"];
+
+struct Include {
+    name: String,
+    line: usize,
+    /// `None` when the argument isn't a string literal.
+    path: Option<String>,
+}
+
+#[derive(Default)]
+struct Collector {
+    includes: Vec<Include>,
+}
+
+impl<'ast> Visit<'ast> for Collector {
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if let Some(name) = mac.path.segments.last().map(|x| x.ident.to_string())
+            && INCLUDE_MACROS.contains(&name.as_str())
+        {
+            self.includes.push(Include {
+                name,
+                line: mac.path.segments[0].ident.span().start().line,
+                path: mac.parse_body::<syn::LitStr>().ok().map(|x| x.value()),
+            });
+        }
+        visit::visit_macro(self, mac);
+    }
+}
+
+pub fn check_include_paths(ctx: &mut Context) -> CheckResult {
+    let lab_root = resolve_path(&ctx.lab_path, Utf8Path::new("."));
+    let mut result = Ok(());
+    for source in scanned_sources(ctx).iter() {
+        let mut collector = Collector::default();
+        collector.visit_file(&source.file);
+        // Paths are relative to the file doing the including.
+        let dir = source.path.parent().unwrap_or(Utf8Path::new("."));
+
+        for include in collector.includes {
+            let at = format!("`{}!` on line {}", include.name, include.line);
+            let Some(path) = include.path else {
+                ctx.problems.warn(
+                    format!("{at} builds its path at compile time, so the checker can't tell which file it reads"),
+                    source.path.clone(),
+                    Some("use a string literal with a path inside the lab folder".into()),
+                );
+                ctx.problems.fields([("line", include.line.to_string())]);
+                continue;
+            };
+            let resolved: Utf8PathBuf = resolve_path(dir, Utf8Path::new(&path));
+            if !resolved.starts_with(&lab_root) {
+                result = Err(ctx.problems.add(
+                    format!("{at} reads `{path}`, which is outside the lab folder: {resolved}"),
+                    source.path.clone(),
+                    Some("the file won't be there when someone else builds the lab; copy it into the lab folder".into()),
+                ));
+                ctx.problems.fields([("line", include.line.to_string())]);
+            }
+        }
+    }
+    result
+}
@@ -0,0 +1,126 @@
+//! Repos that keep large files, like textures, in Git LFS. Without `git lfs`, or before its
+//! objects are downloaded, the checkout has small pointer files where those files should be,
+//! and whatever reads them, like `include_bytes!` or the tests, fails in confusing ways.
+
+use super::{git_health, output};
+use crate::exec::Exec;
+use crate::fix::Fix;
+use crate::{CheckResult, Context, SkipReason, git};
+use camino::Utf8Path;
+use std::fs;
+
+/// How every pointer file starts.
+const POINTER_START: &str = "version https://git-lfs.github.com/spec/";
+/// Pointer files are never bigger than this.
+const MAX_POINTER_SIZE: u64 = 1024;
+
+/// The files `.gitattributes` puts in LFS. Works without `git lfs`.
+pub fn tracked_files(repo: &Utf8Path) -> Vec<String> {
+    let files = git::git(repo, &["ls-files", "-z", "--", ":(attr:filter=lfs)"]);
+    files
+        .unwrap_or_default()
+        .split('\0')
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn is_pointer(path: &Utf8Path) -> bool {
+    match fs::metadata(path) {
+        Ok(x) if x.is_file() && x.len() <= MAX_POINTER_SIZE => {}
+        _ => return false,
+    }
+    fs::read(path).is_ok_and(|x| x.starts_with(POINTER_START.as_bytes()))
+}
+
+/// The committed files that are pointers instead of what they point to.
+fn pointer_files(repo: &Utf8Path) -> Result<Vec<String>, String> {
+    let files = git::git(repo, &["ls-files", "-z"])?;
+    Ok(files
+        .split('\0')
+        .filter(|x| !x.is_empty() && is_pointer(&repo.join(x)))
+        .map(String::from)
+        .collect())
+}
+
+fn lfs_installed(ctx: &mut Context) -> bool {
+    let git = Exec::new("git")
+        .args(["lfs", "version"])
+        .cwd(&ctx.repo_path)
+        .stdin_null();
+    output(ctx, &git).is_ok_and(|x| x.status.success())
+}
+
+pub fn check_git_lfs(ctx: &mut Context) -> CheckResult {
+    if !matches!(git_health(ctx), git::Health::Healthy) {
+        return ctx.skip(SkipReason::NotApplicable(
+            "the repo's git history can't be read",
+        ));
+    }
+    let tracked = tracked_files(&ctx.repo_path);
+    let pointers = match pointer_files(&ctx.repo_path) {
+        Ok(x) => x,
+        Err(e) => return Err(ctx.problems.add(e, ctx.repo_path.clone(), None)),
+    };
+    if tracked.is_empty() && pointers.is_empty() {
+        return ctx.skip(SkipReason::NotApplicable("the repo doesn't use Git LFS"));
+    }
+    let installed = lfs_installed(ctx);
+    let paths: Vec<_> = pointers.iter().map(|x| ctx.repo_path.join(x)).collect();
+    let install = "install Git LFS (`apt install git-lfs`, `brew install git-lfs`, or from https://git-lfs.com), then run `git lfs install` and `git lfs pull` in the repo";
+
+    let fields = [
+        ("lfs_file_count", tracked.len().to_string()),
+        ("pointer_file_count", pointers.len().to_string()),
+        ("lfs_installed", installed.to_string()),
+    ];
+
+    if pointers.is_empty() {
+        if !installed {
+            ctx.problems.warn(
+                format!(
+                    "the repo keeps {} files in Git LFS, but `git lfs` isn't installed",
+                    tracked.len()
+                ),
+                ctx.repo_path.join(".gitattributes"),
+                Some(format!(
+                    "the files are fine now, but new checkouts and commits of them won't be; {install}"
+                )),
+            );
+            ctx.problems.fields(fields);
+        }
+        return Ok(());
+    }
+    let e = if pointers.iter().all(|x| !tracked.contains(x)) {
+        // LFS would replace these on checkout if `.gitattributes` said so.
+        ctx.problems.add(
+            format!(
+                "{} committed files are Git LFS pointers, but .gitattributes doesn't put them in LFS",
+                pointers.len()
+            ),
+            paths,
+            Some("they were committed from a checkout that didn't have the LFS objects; commit the real files instead, and add them to .gitattributes with `git lfs track` if they should stay in LFS".into()),
+        )
+    } else if !installed {
+        ctx.problems.add(
+            format!(
+                "{} files are Git LFS pointers instead of their contents, because `git lfs` isn't installed",
+                pointers.len()
+            ),
+            paths,
+            Some(install.into()),
+        )
+    } else {
+        ctx.problems.add_fixable(
+            format!(
+                "{} files are Git LFS pointers instead of their contents, because their LFS objects weren't downloaded",
+                pointers.len()
+            ),
+            paths,
+            Some("run `git lfs pull` in the repo; `git lfs install` makes later checkouts download them".into()),
+            Fix::run(ctx.repo_path.clone(), "git", ["lfs", "pull"]),
+        )
+    };
+    ctx.problems.fields(fields);
+    Err(e)
+}
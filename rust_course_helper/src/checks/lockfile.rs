@@ -0,0 +1,123 @@
+//! `Cargo.lock` files that weren't updated after `Cargo.toml` changed, which break grading
+//! builds that use `--locked`.
+
+use super::manifest::{dependency_sections, read_lab_manifest};
+use super::output;
+use crate::exec::{Exec, Stream};
+use crate::toml::{self, Table, Value};
+use crate::{CheckResult, Context, SkipReason};
+use camino::Utf8PathBuf;
+use std::collections::BTreeSet;
+use std::fs;
+
+/// The lock file cargo uses for the lab: its own, or the workspace's higher up in the repo.
+fn find_lock_file(ctx: &Context) -> Option<Utf8PathBuf> {
+    ctx.lab_path
+        .ancestors()
+        .take_while(|x| x.starts_with(&ctx.repo_path))
+        .map(|x| x.join("Cargo.lock"))
+        .find(|x| x.is_file())
+}
+
+/// Names of the packages the manifest depends on, with renames resolved.
+fn declared(manifest: &Table) -> BTreeSet<String> {
+    let mut sections: Vec<&Table> = dependency_sections(manifest).collect();
+    if let Some(Value::Table(targets)) = manifest.get("target") {
+        for target in targets.values().filter_map(Value::as_table) {
+            sections.extend(dependency_sections(target));
+        }
+    }
+    sections
+        .into_iter()
+        .flat_map(|x| x.iter())
+        .map(|(name, value)| {
+            let package = value
+                .as_table()
+                .and_then(|x| x.get("package")?.as_str())
+                .unwrap_or(name);
+            package.to_string()
+        })
+        .collect()
+}
+
+/// Names of the packages the lock file says `package` depends on.
+fn locked(lock: &Table, package: &str) -> Option<BTreeSet<String>> {
+    let entry = lock
+        .get("package")?
+        .as_array()?
+        .iter()
+        .filter_map(Value::as_table)
+        .find(|x| x.get("name").and_then(Value::as_str) == Some(package))?;
+    let dependencies = entry
+        .get("dependencies")
+        .and_then(Value::as_array)
+        .unwrap_or_default();
+    // Entries are `name`, `name version` or `name version (source)`.
+    let names = dependencies
+        .iter()
+        .filter_map(Value::as_str)
+        .filter_map(|x| x.split(' ').next())
+        .map(String::from)
+        .collect();
+    Some(names)
+}
+
+pub fn check_lock_file(ctx: &mut Context) -> CheckResult {
+    let Some(lock_path) = find_lock_file(ctx) else {
+        return ctx.skip(SkipReason::NotApplicable("there's no Cargo.lock"));
+    };
+    let Some((manifest_path, manifest)) = read_lab_manifest(ctx)? else {
+        return Ok(());
+    };
+
+    let cargo = Exec::new("cargo")
+        .args(["metadata", "--locked", "--format-version", "1"])
+        .cwd(&ctx.lab_path)
+        .stdin_null()
+        .stdout(Stream::Discard)
+        .limit(usize::MAX)
+        .description("cargo metadata --locked");
+    let output = match output(ctx, &cargo) {
+        Ok(x) => x,
+        // The build checks report cargo itself not working.
+        Err(_) => return Ok(()),
+    };
+    let stderr = output.stderr.text();
+    // Other failures, like a broken manifest, are for the build checks to explain.
+    if output.status.success() || !stderr.contains("--locked") {
+        return Ok(());
+    }
+
+    let package = manifest
+        .get("package")
+        .and_then(|x| x.as_table()?.get("name")?.as_str())
+        .unwrap_or_default();
+    let lock = fs::read_to_string(&lock_path)
+        .ok()
+        .and_then(|x| toml::parse(&x).ok());
+    let changes = lock.and_then(|x| locked(&x, package)).map(|locked| {
+        let declared = declared(&manifest);
+        let mut changes: Vec<String> = declared
+            .difference(&locked)
+            .map(|x| format!("`{x}` was added"))
+            .collect();
+        changes.extend(
+            locked
+                .difference(&declared)
+                .map(|x| format!("`{x}` was removed")),
+        );
+        changes
+    });
+    let text = match changes {
+        Some(x) if !x.is_empty() => format!(
+            "Cargo.lock wasn't updated after changing the dependencies in Cargo.toml: {}",
+            x.join(", ")
+        ),
+        _ => "Cargo.lock wasn't updated after changing Cargo.toml; a dependency's version or features probably changed".to_string(),
+    };
+    Err(ctx.problems.add(
+        text,
+        vec![manifest_path, lock_path],
+        Some("run `cargo build` and commit the updated Cargo.lock; grading builds with `--locked` and fails otherwise".into()),
+    ))
+}
@@ -0,0 +1,670 @@
+use super::{output, resolve_path};
+use crate::config::CrateType;
+use crate::exec::Exec;
+use crate::json::Json;
+use crate::toml::{self, Table, Value};
+use crate::{CheckError, CheckResult, Context, SkipReason};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+use std::net::IpAddr;
+use std::rc::Rc;
+
+const DEPENDENCY_TABLES: &[&str] = &["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Reads and parses the lab's `Cargo.toml`. Returns `None` if there isn't one, which is
+/// reported by other checks.
+pub fn read_lab_manifest(ctx: &mut Context) -> Result<Option<(Utf8PathBuf, Table)>, CheckError> {
+    let path = ctx.lab_path.join("Cargo.toml");
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    match toml::parse(&text) {
+        Ok(x) => Ok(Some((path, x))),
+        Err(e) => Err(ctx
+            .problems
+            .add(format!("can't parse Cargo.toml: {e}"), path, None)),
+    }
+}
+
+/// Names of the binaries the lab builds, with the one `cargo run` picks first when it's
+/// unambiguous.
+pub fn binary_names(manifest: &Table, lab_path: &Utf8Path) -> Vec<String> {
+    let mut names = Vec::new();
+    if let Some(Value::Array(bins)) = manifest.get("bin") {
+        names.extend(
+            bins.iter()
+                .filter_map(|x| x.as_table()?.get("name")?.as_str())
+                .map(String::from),
+        );
+    }
+    let package_name = manifest
+        .get("package")
+        .and_then(|x| x.as_table()?.get("name")?.as_str());
+    if let Some(name) = package_name
+        && lab_path.join("src/main.rs").exists()
+        && !names.iter().any(|x| x == name)
+    {
+        names.insert(0, name.to_string());
+    }
+    names
+}
+
+/// Every dependency declared in the manifest, including target-specific and workspace ones.
+fn dependencies(manifest: &Table) -> Vec<(&str, &Table)> {
+    let mut sections: Vec<&Table> = dependency_sections(manifest).collect();
+    if let Some(Value::Table(targets)) = manifest.get("target") {
+        for target in targets.values().filter_map(Value::as_table) {
+            sections.extend(dependency_sections(target));
+        }
+    }
+    if let Some(Value::Table(workspace)) = manifest.get("workspace")
+        && let Some(Value::Table(x)) = workspace.get("dependencies")
+    {
+        sections.push(x);
+    }
+
+    sections
+        .into_iter()
+        .flat_map(|x| x.iter())
+        .filter_map(|(name, value)| Some((name.as_str(), value.as_table()?)))
+        .collect()
+}
+
+pub fn dependency_sections(table: &Table) -> impl Iterator<Item = &Table> {
+    DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|name| table.get(*name)?.as_table())
+}
+
+pub fn is_inherited(value: &Value) -> bool {
+    matches!(
+        value.as_table().and_then(|x| x.get("workspace")),
+        Some(Value::Boolean(true))
+    )
+}
+
+/// What to write instead of inheriting a `[package]` key.
+pub fn literal_for(key: &str) -> String {
+    let literal = match key {
+        "edition" => "edition = \"2024\"",
+        "version" => "version = \"0.1.0\"",
+        "rust-version" => "rust-version = \"1.85\"",
+        "authors" => "authors = [\"Your Name <you@example.com>\"]",
+        "license" => "license = \"MIT\"",
+        "publish" => "publish = false",
+        _ => return format!("write the value of `{key}` out instead"),
+    };
+    format!("write `{literal}` instead")
+}
+
+/// Keys that inherit from `[workspace]`, with what to write instead.
+fn inherited_keys(manifest: &Table) -> Vec<(String, String)> {
+    let mut keys = Vec::new();
+    if let Some(Value::Table(package)) = manifest.get("package") {
+        for (key, _) in package.iter().filter(|(_, x)| is_inherited(x)) {
+            keys.push((format!("package.{key}"), literal_for(key)));
+        }
+    }
+
+    let mut sections = vec![("", manifest)];
+    if let Some(Value::Table(targets)) = manifest.get("target") {
+        for (target, table) in targets {
+            if let Some(table) = table.as_table() {
+                sections.push((target.as_str(), table));
+            }
+        }
+    }
+    for (target, table) in sections {
+        for section in DEPENDENCY_TABLES {
+            let Some(Value::Table(deps)) = table.get(*section) else {
+                continue;
+            };
+            for (name, _) in deps.iter().filter(|(_, x)| is_inherited(x)) {
+                let key = match target {
+                    "" => format!("{section}.{name}"),
+                    _ => format!("target.{target}.{section}.{name}"),
+                };
+                keys.push((
+                    key,
+                    format!(
+                        "write `{name} = \"<version>\"` instead, with the version from crates.io"
+                    ),
+                ));
+            }
+        }
+    }
+
+    if let Some(lints) = manifest.get("lints")
+        && matches!(
+            lints.as_table().and_then(|x| x.get("workspace")),
+            Some(Value::Boolean(true))
+        )
+    {
+        keys.push((
+            "lints".into(),
+            "remove it, or list the lints under `[lints.rust]` instead".into(),
+        ));
+    }
+    keys
+}
+
+/// Whether cargo will find a workspace root for the crate in `dir`.
+fn in_workspace(manifest: &Table, dir: &Utf8Path) -> bool {
+    if manifest.contains_key("workspace") {
+        return true;
+    }
+    let dir = dir.canonicalize_utf8().unwrap_or_else(|_| dir.to_owned());
+    dir.ancestors().skip(1).any(|x| {
+        fs::read_to_string(x.join("Cargo.toml"))
+            .ok()
+            .and_then(|text| toml::parse(&text).ok())
+            .is_some_and(|x| x.contains_key("workspace"))
+    })
+}
+
+pub const WORKSPACE_HELP: &str = "this crate isn't part of a workspace, so there's nothing to inherit from; \
+    `.workspace = true` lines only work in workspace members";
+
+pub fn inheritance_problem(keys: &[(String, String)]) -> String {
+    let mut text =
+        String::from("Cargo.toml inherits settings from a workspace that doesn't exist:");
+    for (key, fix) in keys {
+        text += &format!("\n  `{key}.workspace = true`: {fix}");
+    }
+    text
+}
+
+pub fn check_workspace_inheritance(ctx: &mut Context) -> CheckResult {
+    let Some((manifest_path, manifest)) = read_lab_manifest(ctx)? else {
+        return Ok(());
+    };
+    let keys = inherited_keys(&manifest);
+    if keys.is_empty() || in_workspace(&manifest, &ctx.lab_path) {
+        return Ok(());
+    }
+    Err(ctx.problems.add(
+        inheritance_problem(&keys),
+        manifest_path,
+        Some(WORKSPACE_HELP.into()),
+    ))
+}
+
+fn git_host(url: &str) -> Option<&str> {
+    if let Some((scheme, rest)) = url.split_once("://") {
+        if scheme == "file" {
+            return None;
+        }
+        let authority = rest.split('/').next().unwrap_or_default();
+        let host = authority.rsplit('@').next().unwrap_or_default();
+        if let Some(ipv6) = host.strip_prefix('[') {
+            return ipv6.split(']').next();
+        }
+        return host.split(':').next();
+    }
+    // scp-like syntax: `git@host:path`
+    let (before, _) = url.split_once(':')?;
+    if before.contains('/') {
+        return None;
+    }
+    before.rsplit('@').next()
+}
+
+fn is_private_host(host: &str) -> bool {
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return match ip {
+            IpAddr::V4(x) => x.is_private() || x.is_loopback() || x.is_link_local(),
+            IpAddr::V6(x) => {
+                x.is_loopback() || (x.segments()[0] & 0xfe00) == 0xfc00 || x.is_unicast_link_local()
+            }
+        };
+    }
+    let host = host.to_ascii_lowercase();
+    !host.contains('.')
+        || [".localhost", ".local", ".lan", ".internal", ".home"]
+            .iter()
+            .any(|x| host.ends_with(x))
+}
+
+pub fn check_local_dependencies(ctx: &mut Context) -> CheckResult {
+    let Some((manifest_path, manifest)) = read_lab_manifest(ctx)? else {
+        return Ok(());
+    };
+    let manifest_dir = manifest_path.parent().unwrap_or(Utf8Path::new("."));
+    let repo_root = ctx
+        .repo_path
+        .canonicalize_utf8()
+        .unwrap_or_else(|_| ctx.repo_path.clone());
+
+    let mut result = Ok(());
+    for (name, dependency) in dependencies(&manifest) {
+        if ctx
+            .lab_config
+            .allowed_local_dependencies
+            .iter()
+            .any(|x| x == name)
+        {
+            continue;
+        }
+
+        if let Some(path) = dependency.get("path").and_then(Value::as_str) {
+            let resolved = resolve_path(manifest_dir, Utf8Path::new(path));
+            if !resolved.starts_with(&repo_root) {
+                result = Err(ctx.problems.add(
+                    format!(
+                        "dependency `{name}` points at `{path}`, which is outside the repository"
+                    ),
+                    vec![manifest_path.clone(), resolved],
+                    Some(
+                        "nobody else can build this; move the crate inside the repository or depend on a published version"
+                            .into(),
+                    ),
+                ));
+            }
+        }
+
+        if let Some(url) = dependency.get("git").and_then(Value::as_str) {
+            let private = match git_host(url) {
+                Some(host) => is_private_host(host),
+                None => true,
+            };
+            if private {
+                result = Err(ctx.problems.add(
+                    format!("dependency `{name}` uses git repository `{url}`, which is only reachable from your machine or network"),
+                    manifest_path.clone(),
+                    Some("use a public git URL or depend on a published version".into()),
+                ));
+            }
+        }
+    }
+
+    result
+}
+
+/// Profile settings that change how the lab behaves, with what they do.
+const PROFILE_SETTINGS: &[(&str, &str, &str)] = &[
+    (
+        "panic",
+        "\"abort\"",
+        "panics abort the process, so tests can't catch them and `#[should_panic]` tests fail",
+    ),
+    (
+        "overflow-checks",
+        "false",
+        "integer overflow wraps around silently instead of panicking",
+    ),
+    (
+        "debug-assertions",
+        "false",
+        "`debug_assert!` and overflow checks are turned off",
+    ),
+];
+
+fn render(value: &Value) -> String {
+    match value {
+        Value::String(x) => format!("{x:?}"),
+        Value::Integer(x) => x.to_string(),
+        Value::Boolean(x) => x.to_string(),
+        x => x.type_name().to_string(),
+    }
+}
+
+fn check_overrides(ctx: &mut Context, manifest_path: &Utf8Path, manifest: &Table) -> CheckResult {
+    let mut result = Ok(());
+    let allowed = ctx.lab_config.allow_dependency_overrides;
+    let help = "this changes which code gets built for a dependency, so the lab wouldn't be \
+        graded against the real crate; remove the section";
+
+    let mut overrides = Vec::new();
+    if let Some(Value::Table(patch)) = manifest.get("patch") {
+        for (source, crates) in patch {
+            let names: Vec<&str> = crates
+                .as_table()
+                .map(|x| x.keys().map(String::as_str).collect())
+                .unwrap_or_default();
+            overrides.push((format!("[patch.{source}]"), names));
+        }
+    }
+    if let Some(Value::Table(replace)) = manifest.get("replace") {
+        overrides.push((
+            "[replace]".to_string(),
+            replace.keys().map(String::as_str).collect(),
+        ));
+    }
+    for (header, names) in overrides {
+        let text = format!(
+            "`{header}` overrides {}",
+            names
+                .iter()
+                .map(|x| format!("`{x}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if allowed {
+            ctx.problems
+                .warn(text, manifest_path.to_owned(), Some(help.into()));
+        } else {
+            result = Err(ctx
+                .problems
+                .add(text, manifest_path.to_owned(), Some(help.into())));
+        }
+    }
+
+    if let Some(Value::Table(profiles)) = manifest.get("profile") {
+        for (profile, settings) in profiles {
+            let Some(settings) = settings.as_table() else {
+                continue;
+            };
+            for (key, bad, effect) in PROFILE_SETTINGS {
+                if let Some(value) = settings.get(*key)
+                    && render(value) == *bad
+                {
+                    ctx.problems.warn(
+                        format!("`[profile.{profile}]` sets `{key} = {bad}`: {effect}"),
+                        manifest_path.to_owned(),
+                        Some("remove the setting unless the lab asks for it".into()),
+                    );
+                }
+            }
+        }
+    }
+    result
+}
+
+pub fn check_dependency_overrides(ctx: &mut Context) -> CheckResult {
+    let mut result = Ok(());
+    if let Some((path, manifest)) = read_lab_manifest(ctx)? {
+        result = check_overrides(ctx, &path, &manifest);
+    }
+
+    // Overrides only take effect in the workspace root, which may be the repo root.
+    let root_path = ctx.repo_path.join("Cargo.toml");
+    if let Ok(text) = fs::read_to_string(&root_path) {
+        match toml::parse(&text) {
+            Ok(root) => result = result.and(check_overrides(ctx, &root_path, &root)),
+            Err(e) => {
+                result =
+                    Err(ctx
+                        .problems
+                        .add(format!("can't parse Cargo.toml: {e}"), root_path, None));
+            }
+        }
+    }
+    result
+}
+
+/// A target of the lab's package, as cargo sees it.
+pub struct Target {
+    pub name: String,
+    /// Like `lib`, `bin` or `test`.
+    pub kinds: Vec<String>,
+    /// The crate root, like `src/main.rs`.
+    pub src_path: Utf8PathBuf,
+}
+
+impl Target {
+    pub fn is_bin(&self) -> bool {
+        self.kinds.iter().any(|x| x == "bin")
+    }
+
+    pub fn is_lib(&self) -> bool {
+        const LIBRARY_KINDS: &[&str] =
+            &["lib", "rlib", "dylib", "cdylib", "staticlib", "proc-macro"];
+        self.kinds
+            .iter()
+            .any(|x| LIBRARY_KINDS.contains(&x.as_str()))
+    }
+
+    /// Like "the library `lab01` (src/lib.rs)", for problems that say which target they're about.
+    pub fn describe(&self, lab_path: &Utf8Path) -> String {
+        let kind = match self.is_lib() {
+            true => "library",
+            false => "binary",
+        };
+        let path = self
+            .src_path
+            .strip_prefix(lab_path)
+            .unwrap_or(&self.src_path);
+        format!("the {kind} `{}` ({path})", self.name)
+    }
+}
+
+/// The targets of the lab's package, in cargo's order, which has the library first. Asked
+/// from cargo once per run.
+pub fn lab_targets(ctx: &mut Context) -> Option<Rc<Vec<Target>>> {
+    if let Some(x) = &ctx.lab_targets {
+        return x.clone();
+    }
+    let targets = read_lab_targets(ctx).map(Rc::new);
+    ctx.lab_targets = Some(targets.clone());
+    targets
+}
+
+fn read_lab_targets(ctx: &mut Context) -> Option<Vec<Target>> {
+    let cargo = Exec::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .cwd(&ctx.lab_path)
+        .stdin_null()
+        .limit(usize::MAX)
+        .description("cargo metadata");
+    let output = output(ctx, &cargo).ok()?;
+    // A broken manifest is reported by the checks that build.
+    if !output.status.success() {
+        return None;
+    }
+    let metadata = Json::parse(&output.stdout.text()).ok()?;
+
+    let manifest_path = ctx.lab_path.join("Cargo.toml");
+    let manifest_path = resolve_path(Utf8Path::new("."), &manifest_path);
+    let package = metadata.get("packages")?.as_array()?.iter().find(|x| {
+        x.get("manifest_path")
+            .and_then(Json::as_str)
+            .is_some_and(|x| resolve_path(Utf8Path::new("."), Utf8Path::new(x)) == manifest_path)
+    })?;
+    let lab = ctx
+        .lab_path
+        .canonicalize_utf8()
+        .unwrap_or_else(|_| ctx.lab_path.clone());
+    let targets = package
+        .get("targets")?
+        .as_array()?
+        .iter()
+        .filter_map(|x| {
+            let src_path = Utf8Path::new(x.get("src_path")?.as_str()?);
+            Some(Target {
+                name: x.get("name")?.as_str()?.to_string(),
+                kinds: x
+                    .get("kind")?
+                    .as_array()?
+                    .iter()
+                    .filter_map(|x| Some(x.as_str()?.to_string()))
+                    .collect(),
+                // Relative to the lab like the other paths checks use, when it's in there.
+                src_path: match src_path.strip_prefix(&lab) {
+                    Ok(x) => ctx.lab_path.join(x),
+                    Err(_) => src_path.to_owned(),
+                },
+            })
+        })
+        .collect();
+    Some(targets)
+}
+
+pub fn check_binary_names(ctx: &mut Context) -> CheckResult {
+    let mut expected = ctx.lab_config.expected_binaries.clone();
+    if expected.is_empty() {
+        // Libraries have no binary to run unless the config asks for one.
+        if let CrateType::Lib = ctx.lab_config.crate_type {
+            return ctx.skip(SkipReason::NotApplicable("the lab is a library"));
+        }
+        // Grading runs the binary named after the lab, whichever folder holds it.
+        expected.push(ctx.lab.clone());
+    }
+    if !ctx.lab_path.join("Cargo.toml").exists() {
+        return Ok(());
+    }
+    let Some(targets) = lab_targets(ctx) else {
+        return Ok(());
+    };
+    let found: Vec<String> = targets
+        .iter()
+        .filter(|x| x.is_bin())
+        .map(|x| x.name.clone())
+        .collect();
+
+    let missing: Vec<&String> = expected.iter().filter(|x| !found.contains(x)).collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let list = |x: &[&String]| {
+        x.iter()
+            .map(|x| format!("`{x}`"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let found_text = match (found.is_empty(), targets.iter().find(|x| x.is_lib())) {
+        (true, Some(lib)) => format!("the lab only has {}", lib.describe(&ctx.lab_path)),
+        (true, None) => "the lab has no binaries".to_string(),
+        (false, _) => format!(
+            "the lab's binaries are {}",
+            list(&found.iter().collect::<Vec<_>>())
+        ),
+    };
+    let snippets: Vec<String> = missing
+        .iter()
+        .map(|x| format!("[[bin]]\nname = \"{x}\"\npath = \"src/main.rs\""))
+        .collect();
+    Err(ctx.problems.add(
+        match missing.as_slice() {
+            [x] => format!("grading runs `cargo run --bin {x}`, but {found_text}"),
+            _ => format!(
+                "grading needs the binaries {}, but {found_text}",
+                list(&missing)
+            ),
+        },
+        ctx.lab_path.join("Cargo.toml"),
+        Some(format!(
+            "name the binary in Cargo.toml:\n{}",
+            snippets.join("\n\n")
+        )),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Diags;
+    use crate::temp::TempDir;
+
+    fn lab(name: &str, dependencies: &str) -> TempDir {
+        let dir = TempDir::new(name).unwrap();
+        let lab = dir.path().join("repo/lab01");
+        fs::create_dir_all(lab.join("vendored")).unwrap();
+        fs::create_dir_all(dir.path().join("elsewhere")).unwrap();
+        let manifest = format!(
+            "[package]\nname = \"lab01\"\nversion = \"0.1.0\"\n\n[dependencies]\n{dependencies}"
+        );
+        fs::write(lab.join("Cargo.toml"), manifest).unwrap();
+        dir
+    }
+
+    fn local_dependency_problems(dir: &TempDir) -> Vec<String> {
+        let mut problems = Diags::default();
+        {
+            let repo = dir.path().join("repo");
+            let mut ctx = Context::for_lab(&mut problems, &repo, "lab01");
+            ctx.problems.current_check = Some("local_dependencies");
+            let _ = check_local_dependencies(&mut ctx);
+        }
+        problems.problems.into_iter().map(|x| x.text).collect()
+    }
+
+    #[test]
+    fn path_dependencies_outside_the_repo_are_flagged() {
+        let dir = lab(
+            "manifest_escape",
+            "helper = { path = \"../../elsewhere\" }\n",
+        );
+        let problems = local_dependency_problems(&dir);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("`helper` points at `../../elsewhere`"));
+    }
+
+    #[test]
+    fn path_dependencies_inside_the_repo_are_fine() {
+        let dir = lab(
+            "manifest_inside",
+            "helper = { path = \"vendored\" }\nsibling = { path = \"../lab01/vendored/..\" }\n",
+        );
+        assert_eq!(local_dependency_problems(&dir), Vec::<String>::new());
+    }
+
+    #[test]
+    fn private_git_urls_are_flagged() {
+        let dir = lab(
+            "manifest_git",
+            "a = { git = \"https://github.com/rust-lang/log\" }\n\
+            b = { git = \"http://localhost:3000/me/b.git\" }\n\
+            c = { git = \"git@192.168.1.20:me/c.git\" }\n",
+        );
+        let problems = local_dependency_problems(&dir);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("`b` uses git repository `http://localhost:3000/me/b.git`"));
+        assert!(problems[1].contains("`c`"));
+    }
+
+    #[test]
+    fn binaries_are_named_after_the_lab_not_its_folder() {
+        let dir = TempDir::new("manifest_binary_name").unwrap();
+        let lab = dir.path().join("Project");
+        fs::create_dir_all(lab.join("src")).unwrap();
+        fs::write(
+            lab.join("Cargo.toml"),
+            "[package]\nname = \"project\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::write(lab.join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        let mut problems = Diags::default();
+        let mut ctx = Context::for_lab(&mut problems, dir.path(), "Project");
+        ctx.lab = "project".into();
+        ctx.lab_config.lab_dirs = vec!["project".into(), "Project".into()];
+        ctx.problems.current_check = Some("binary_names");
+        assert!(check_binary_names(&mut ctx).is_ok());
+        assert!(problems.problems.is_empty());
+    }
+
+    #[test]
+    fn git_hosts() {
+        assert_eq!(git_host("https://github.com/a/b"), Some("github.com"));
+        assert_eq!(git_host("ssh://git@gitlab.com:22/a/b"), Some("gitlab.com"));
+        assert_eq!(git_host("https://[::1]:8080/a"), Some("::1"));
+        assert_eq!(git_host("git@github.com:a/b.git"), Some("github.com"));
+        assert_eq!(git_host("file:///home/me/b"), None);
+        assert_eq!(git_host("../b"), None);
+        assert_eq!(git_host("./x:y/b"), None);
+    }
+
+    #[test]
+    fn private_hosts() {
+        for host in [
+            "localhost",
+            "gitserver",
+            "git.lan",
+            "build.internal",
+            "pi.local",
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.0.5",
+            "169.254.1.1",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(is_private_host(host), "{host}");
+        }
+        for host in ["github.com", "GitLab.com", "8.8.8.8", "2001:db8::1"] {
+            assert!(!is_private_host(host), "{host}");
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Catches another lab's solution submitted by mistake, like lab04's code copied into the
+//! lab05 folder, which otherwise just fails every lab05 test.
+//!
+//! The config lists code the lab's solution has to contain and code that only other labs'
+//! solutions contain. It's a token scan, much lighter than the API check: `fn run_simulation`
+//! matches however it's spaced, and comments are ignored.
+
+use super::syntax::parsed_sources;
+use crate::{CheckResult, Context, SkipReason};
+use camino::Utf8PathBuf;
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+
+/// Tokens as text, with groups flattened into their delimiters and contents.
+fn flatten(tokens: TokenStream, out: &mut Vec<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    proc_macro2::Delimiter::Parenthesis => ("(", ")"),
+                    proc_macro2::Delimiter::Brace => ("{", "}"),
+                    proc_macro2::Delimiter::Bracket => ("[", "]"),
+                    proc_macro2::Delimiter::None => ("", ""),
+                };
+                if !open.is_empty() {
+                    out.push(open.into());
+                }
+                flatten(group.stream(), out);
+                if !close.is_empty() {
+                    out.push(close.into());
+                }
+            }
+            TokenTree::Punct(x) => out.push(x.as_char().to_string()),
+            x => out.push(x.to_string()),
+        }
+    }
+}
+
+/// A marker's tokens, split like `flatten` splits code. Markers don't have to be valid code on
+/// their own, like `println!(`, so they're not lexed as Rust.
+fn marker_tokens(marker: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut chars = marker.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut token = c.to_string();
+        if c.is_alphanumeric() || c == '_' {
+            while let Some(&x) = chars.peek().filter(|x| x.is_alphanumeric() || **x == '_') {
+                token.push(x);
+                chars.next();
+            }
+        } else if c == '"' {
+            while let Some(x) = chars.next() {
+                token.push(x);
+                match x {
+                    '\\' => token.extend(chars.next()),
+                    '"' => break,
+                    _ => {}
+                }
+            }
+        }
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn contains(haystack: &[String], needle: &[String]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|x| x == needle)
+}
+
+pub fn check_lab_markers(ctx: &mut Context) -> CheckResult {
+    let required = ctx.lab_config.required_markers.clone();
+    let forbidden = ctx.lab_config.forbidden_markers.clone();
+    if required.is_empty() && forbidden.is_empty() {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let files: Vec<(Utf8PathBuf, Vec<String>)> = parsed_sources(ctx)
+        .iter()
+        .map(|x| {
+            let mut tokens = Vec::new();
+            flatten(x.file.to_token_stream(), &mut tokens);
+            (x.path.clone(), tokens)
+        })
+        .collect();
+    if files.is_empty() {
+        return ctx.skip(SkipReason::NotApplicable(
+            "the lab has no sources that parse",
+        ));
+    }
+    let lab = ctx
+        .lab_path
+        .file_name()
+        .unwrap_or(ctx.lab_path.as_str())
+        .to_string();
+
+    let mut result = Ok(());
+    for (marker, expected) in required
+        .iter()
+        .map(|x| (x, true))
+        .chain(forbidden.iter().map(|x| (x, false)))
+    {
+        let tokens = marker_tokens(marker);
+        let found: Vec<Utf8PathBuf> = files
+            .iter()
+            .filter(|(_, x)| contains(x, &tokens))
+            .map(|(path, _)| path.clone())
+            .collect();
+        if expected && found.is_empty() {
+            result = Err(ctx.problems.add(
+                format!("`{marker}` isn't anywhere in the lab, though every solution to it has it; this may be another lab's code"),
+                ctx.lab_path.clone(),
+                Some(format!("check that `{lab}` has this lab's code, and not a copy of another lab's")),
+            ));
+        } else if !expected && !found.is_empty() {
+            result = Err(ctx.problems.add(
+                format!(
+                    "`{marker}` belongs to another lab's solution, so this may be that lab's code"
+                ),
+                found,
+                Some(format!(
+                    "check that `{lab}` has this lab's code, and not a copy of another lab's"
+                )),
+            ));
+        } else {
+            continue;
+        }
+        ctx.problems.fields([("marker", marker.clone())]);
+    }
+    result
+}
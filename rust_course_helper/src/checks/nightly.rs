@@ -0,0 +1,140 @@
+//! Code that only builds on nightly. Labs are graded on stable, so this is caught before it is.
+
+use super::manifest::read_lab_manifest;
+use super::syntax::scanned_sources;
+use super::{command_check_return, git_health, output};
+use crate::exec::Exec;
+use crate::{CheckResult, Context, SkipReason, git};
+use std::fs;
+
+const STABLE_HELP: &str = "labs are graded on stable Rust; use only stable features and APIs";
+
+/// Names in `#![feature(a, b)]`.
+fn feature_names(attr: &syn::Attribute) -> Option<Vec<String>> {
+    if !attr.path().is_ident("feature") {
+        return None;
+    }
+    let list = attr.meta.require_list().ok()?;
+    let names = list
+        .parse_args_with(
+            syn::punctuated::Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated,
+        )
+        .map(|x| x.iter().map(ToString::to_string).collect())
+        .unwrap_or_else(|_| vec![list.tokens.to_string()]);
+    Some(names)
+}
+
+pub fn check_unstable_features(ctx: &mut Context) -> CheckResult {
+    let mut result = Ok(());
+
+    for source in scanned_sources(ctx).iter() {
+        for attr in &source.file.attrs {
+            let Some(names) = feature_names(attr) else {
+                continue;
+            };
+            let line = attr.pound_token.span.start().line;
+            for name in names {
+                result = Err(ctx.problems.add(
+                    format!("`#![feature({name})]` on line {line} needs a nightly compiler"),
+                    source.path.clone(),
+                    Some(STABLE_HELP.into()),
+                ));
+                ctx.problems.fields([("line", line.to_string())]);
+            }
+        }
+    }
+
+    if let Some((path, manifest)) = read_lab_manifest(ctx)?
+        && let Some(features) = manifest.get("cargo-features").and_then(|x| x.as_array())
+    {
+        for name in features.iter().filter_map(|x| x.as_str()) {
+            result = Err(ctx.problems.add(
+                format!("`cargo-features` enables `{name}`, which needs a nightly cargo"),
+                path.clone(),
+                Some(STABLE_HELP.into()),
+            ));
+        }
+    }
+
+    // Any config cargo reads while building can turn on nightly features for stable.
+    let files = match git_health(ctx) {
+        git::Health::Healthy => git::git(&ctx.repo_path, &["ls-files"]).unwrap_or_default(),
+        // The git check explains why.
+        _ => String::new(),
+    };
+    let configs = files.lines().filter(|x| {
+        x.ends_with(".cargo/config")
+            || x.ends_with(".cargo/config.toml")
+            || *x == "rust-toolchain"
+            || *x == "rust-toolchain.toml"
+    });
+    for config in configs {
+        let path = ctx.repo_path.join(config);
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+        for (i, line) in text.lines().enumerate() {
+            if line.contains("RUSTC_BOOTSTRAP") {
+                result = Err(ctx.problems.add(
+                    format!(
+                        "line {} sets `RUSTC_BOOTSTRAP`, which unlocks nightly features",
+                        i + 1
+                    ),
+                    path.clone(),
+                    Some(STABLE_HELP.into()),
+                ));
+                ctx.problems.fields([("line", (i + 1).to_string())]);
+            }
+        }
+    }
+    result
+}
+
+fn rustc_version(ctx: &Context, toolchain: Option<&str>) -> Option<String> {
+    let output = Exec::new("rustc")
+        .args(toolchain.map(|x| format!("+{x}")))
+        .arg("--version")
+        .cwd(&ctx.lab_path)
+        .stdin_null()
+        .run()
+        .ok()?;
+    output
+        .success()
+        .then(|| output.stdout.text().trim().to_string())
+}
+
+/// When the lab is being checked with nightly, builds it again with stable, if it's installed.
+pub fn check_stable_build(ctx: &mut Context) -> CheckResult {
+    let Some(active) = rustc_version(ctx, None) else {
+        return ctx.skip(SkipReason::MissingTool("rustc".into()));
+    };
+    if !active.contains("nightly") {
+        return ctx.skip(SkipReason::NotApplicable("the lab is built with stable"));
+    }
+    let Some(stable) = rustc_version(ctx, Some("stable")) else {
+        // Checking with nightly is the student's choice; installing stable too is the fix.
+        return ctx.skip(SkipReason::MissingTool(
+            "the stable toolchain (`rustup toolchain install stable`)".into(),
+        ));
+    };
+
+    let text = format!("code doesn't build with {stable}");
+    let cargo = Exec::new("cargo")
+        .args(["+stable", "build", "--all", "-q"])
+        .env_remove("RUSTC_BOOTSTRAP")
+        .cwd(&ctx.lab_path)
+        .stdin_null()
+        .tee(ctx.verbose)
+        .description("cargo +stable build");
+    let output = match output(ctx, &cargo) {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(ctx.problems.add(
+                format!("{text}; because: cargo failed with `{e}`"),
+                ctx.lab_path.clone(),
+                None,
+            ));
+        }
+    };
+    command_check_return(ctx, "cargo", output.status, &text, None)
+}
@@ -0,0 +1,157 @@
+//! Whether the project's crate looks publishable: filled in `[package]` metadata, and
+//! optionally a `cargo publish` dry run.
+
+use super::manifest::{is_inherited, read_lab_manifest};
+use super::{cargo, command_check_return};
+use crate::toml::Value;
+use crate::{CheckResult, Context, SkipReason, git};
+use camino::Utf8Path;
+
+/// Text that templates and tutorials leave behind; compared case-insensitively.
+const PLACEHOLDERS: &[&str] = &[
+    "a new rust project",
+    "description",
+    "my project",
+    "your description here",
+    "lorem ipsum",
+    "todo",
+    "tbd",
+    "fixme",
+    "example",
+    "https://github.com/username",
+    "https://github.com/user/repo",
+];
+
+const MAX_CARGO_LINES: usize = 15;
+
+fn is_placeholder(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    text.is_empty()
+        || PLACEHOLDERS.iter().any(|x| {
+            text == *x || text.starts_with(&format!("{x} ")) || text.starts_with(&format!("{x}:"))
+        })
+        || text.contains("lorem ipsum")
+}
+
+/// `host/owner/repo` for the usual forms of GitHub-style URLs, so different spellings of the
+/// same repo compare equal.
+fn normalize_url(url: &str) -> String {
+    let url = url.trim().to_lowercase();
+    let url = ["https://", "http://", "ssh://", "git://"]
+        .iter()
+        .find_map(|x| url.strip_prefix(x))
+        .unwrap_or(&url)
+        .to_string();
+    let url = url
+        .strip_prefix("git@")
+        .unwrap_or(&url)
+        .replacen(':', "/", 1);
+    let url = url.trim_end_matches('/');
+    url.strip_suffix(".git").unwrap_or(url).to_string()
+}
+
+/// What's wrong with `key`'s value, or `None` if it looks fine.
+fn field_problem(ctx: &Context, key: &str, value: Option<&Value>) -> Option<String> {
+    let value = match value {
+        // Reported by the inheritance check if there's nothing to inherit from.
+        Some(x) if is_inherited(x) => return None,
+        Some(x) => x,
+        // Cargo picks up a README on its own.
+        None if key == "readme" && ctx.lab_path.join("README.md").is_file() => return None,
+        None => return Some(format!("`package.{key}` is missing")),
+    };
+    match value {
+        Value::String(x) if is_placeholder(x) => Some(format!(
+            "`package.{key}` is `{x}`, which looks like a placeholder"
+        )),
+        Value::Array(items) if items.is_empty() => Some(format!("`package.{key}` is empty")),
+        Value::Array(items) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|x| is_placeholder(x))
+            .map(|x| format!("`package.{key}` has `{x}`, which looks like a placeholder")),
+        Value::Boolean(false) if key == "readme" => {
+            Some("`package.readme` is `false`, so the crate has no README".into())
+        }
+        _ => None,
+    }
+}
+
+fn readme_problem(ctx: &Context, readme: &str) -> Option<String> {
+    let path = ctx.lab_path.join(readme);
+    if !path.is_file() {
+        return Some(format!(
+            "`package.readme` is `{readme}`, which doesn't exist"
+        ));
+    }
+    let tracked = git::git(&ctx.lab_path, &["ls-files", "--error-unmatch", readme]).is_ok();
+    (!tracked).then(|| format!("`package.readme` is `{readme}`, which isn't committed"))
+}
+
+fn repository_problem(repo: &Utf8Path, repository: &str) -> Option<String> {
+    // Without a remote there's nothing to compare with.
+    let origin = git::git(repo, &["remote", "get-url", "origin"]).ok()?;
+    (normalize_url(&origin) != normalize_url(repository)).then(|| {
+        format!("`package.repository` is `{repository}`, but the repo was cloned from `{origin}`")
+    })
+}
+
+pub fn check_package_metadata(ctx: &mut Context) -> CheckResult {
+    if ctx.lab_config.publish_fields.is_empty() {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let Some((path, manifest)) = read_lab_manifest(ctx)? else {
+        return Ok(());
+    };
+    let package = manifest.get("package").and_then(Value::as_table);
+
+    let mut problems = Vec::new();
+    for key in &ctx.lab_config.publish_fields {
+        let value = package.and_then(|x| x.get(key));
+        if let Some(x) = field_problem(ctx, key, value) {
+            problems.push(x);
+            continue;
+        }
+        let text = value.and_then(Value::as_str);
+        let problem = match (key.as_str(), text) {
+            ("readme", Some(x)) => readme_problem(ctx, x),
+            ("repository", Some(x)) => repository_problem(&ctx.repo_path, x),
+            _ => None,
+        };
+        problems.extend(problem);
+    }
+
+    let help = "a publishable crate describes itself: see \
+        https://doc.rust-lang.org/cargo/reference/manifest.html#the-package-section";
+    let mut result = Ok(());
+    for text in problems {
+        result = Err(ctx.problems.add(text, path.clone(), Some(help.into())));
+    }
+    result
+}
+
+pub fn check_publish_dry_run(ctx: &mut Context) -> CheckResult {
+    if !ctx.lab_config.publish_dry_run {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let text = "the crate can't be packaged for publishing";
+    let output = cargo(ctx, &["publish", "--dry-run", "--allow-dirty", "-q"], text)?;
+    if output.status.success() {
+        return Ok(());
+    }
+    // Cargo's own messages say which files or fields are the trouble.
+    let stderr = output.stderr.text();
+    let lines: Vec<&str> = stderr
+        .lines()
+        .filter(|x| x.starts_with("error") || x.starts_with("warning") || x.starts_with("  "))
+        .take(MAX_CARGO_LINES)
+        .collect();
+    if lines.is_empty() {
+        return command_check_return(ctx, "cargo", output.status, text, None);
+    }
+    Err(ctx.problems.add(
+        format!("{text}:\n{}", lines.join("\n")),
+        ctx.lab_path.join("Cargo.toml"),
+        None,
+    ))
+}
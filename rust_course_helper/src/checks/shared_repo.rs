@@ -0,0 +1,117 @@
+//! Repos that two students share, against the rules: lab folders with each student's name
+//! after them, or lab folders that are each mostly committed by someone else.
+//!
+//! Both are heuristics, so they only warn, and need more than one stray folder to do so.
+
+use crate::{CheckResult, Context, SkipReason, git};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `lab03-andrei` is `("lab03", Some("andrei"))`. `None` for folders that aren't labs.
+fn lab_folder(name: &str) -> Option<(String, Option<String>)> {
+    let lower = name.to_lowercase();
+    let (base, rest) = if let Some(rest) = lower.strip_prefix("project") {
+        ("project".to_string(), rest)
+    } else {
+        let digits = lower.strip_prefix("lab")?;
+        let len = digits.chars().take_while(char::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        (lower[..3 + len].to_string(), &digits[len..])
+    };
+    if rest.is_empty() {
+        return Some((base, None));
+    }
+    // `projects` or `lab03x` aren't named after anyone.
+    let suffix = rest.strip_prefix(['-', '_', ' ', '.'])?;
+    let suffix = suffix.trim_start_matches(['-', '_', ' ', '.']);
+    Some((base, (!suffix.is_empty()).then(|| suffix.to_string())))
+}
+
+/// The author with most commits touching `dir`, if they made at least `percent` of them.
+fn dominant_author(ctx: &Context, dir: &str, min_commits: usize, percent: usize) -> Option<String> {
+    let output = git::git(&ctx.repo_path, &["shortlog", "-sne", "HEAD", "--", dir]).ok()?;
+    // Lines look like `    12\tName <email>`, most commits first.
+    let authors: Vec<(usize, String)> = output
+        .lines()
+        .filter_map(|x| {
+            let (count, author) = x.trim().split_once('\t')?;
+            let email = author.rsplit_once('<').map_or(author, |x| x.1);
+            Some((
+                count.parse().ok()?,
+                email.trim_end_matches('>').to_lowercase(),
+            ))
+        })
+        .collect();
+    let total: usize = authors.iter().map(|x| x.0).sum();
+    let (count, email) = authors.into_iter().max_by_key(|x| x.0)?;
+    (total >= min_commits && count * 100 >= total * percent).then_some(email)
+}
+
+pub fn check_shared_repo(ctx: &mut Context) -> CheckResult {
+    if !ctx.lab_config.shared_repo_check {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let mut folders: Vec<(String, String, Option<String>)> = ctx
+        .repo_path
+        .read_dir_utf8()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|x| x.file_type().is_ok_and(|x| x.is_dir()))
+        .filter_map(|x| {
+            let name = x.file_name().to_string();
+            let (lab, suffix) = lab_folder(&name)?;
+            Some((name, lab, suffix))
+        })
+        .collect();
+    folders.sort();
+
+    let mut variants: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for (name, lab, suffix) in &folders {
+        if suffix.is_some() {
+            variants.entry(lab).or_default().push(name);
+        }
+    }
+    for (lab, names) in variants {
+        if names.len() >= ctx.lab_config.shared_repo_min_variants {
+            ctx.problems.warn(
+                format!(
+                    "{lab} is in several folders with different names after it: {}; the repo may be shared by several students",
+                    names.join(", ")
+                ),
+                names.iter().map(|x| ctx.repo_path.join(x)).collect::<Vec<_>>(),
+                Some("each student has to submit their own labs from their own repo".into()),
+            );
+        }
+    }
+
+    let mut authors: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for (name, _, _) in &folders {
+        let author = dominant_author(
+            ctx,
+            name,
+            ctx.lab_config.shared_repo_min_commits,
+            ctx.lab_config.shared_repo_dominance_percent,
+        );
+        if let Some(author) = author {
+            authors.entry(author).or_default().push(name);
+        }
+    }
+    let identities: BTreeSet<&String> = authors.keys().collect();
+    if identities.len() > 1 {
+        let list: Vec<String> = authors
+            .iter()
+            .map(|(author, names)| format!("{author} ({})", names.join(", ")))
+            .collect();
+        ctx.problems.warn(
+            format!(
+                "different people committed most of the work in different lab folders: {}; the repo may be shared by several students",
+                list.join("; ")
+            ),
+            ctx.repo_path.clone(),
+            Some("each student has to submit their own labs from their own repo; if these are all you, set the same `user.email` everywhere".into()),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,228 @@
+use super::manifest::{Target, binary_names, lab_targets, read_lab_manifest};
+use super::{target_dirs, with_lab_env};
+use crate::exec::{Exec, Stream};
+use crate::{CheckError, CheckResult, Context, SkipReason};
+use camino::Utf8PathBuf;
+use std::env;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+const MAX_STDERR_LINES: usize = 10;
+
+/// Finds a binary built by `cargo build`, looking in the usual target folders.
+fn find_binary(ctx: &Context, name: &str) -> Option<Utf8PathBuf> {
+    let file_name = format!("{name}{}", env::consts::EXE_SUFFIX);
+    target_dirs(ctx)
+        .into_iter()
+        .map(|x| x.join("debug").join(&file_name))
+        .find(|x| x.is_file())
+        .and_then(|x| x.canonicalize_utf8().ok())
+}
+
+struct Run {
+    /// `None` if it was still running when the time ran out.
+    status: Option<ExitStatus>,
+    stderr: String,
+}
+
+impl Run {
+    fn panicked(&self) -> bool {
+        self.status.is_some_and(|x| x.code() == Some(101)) && self.stderr.contains("panicked")
+    }
+
+    /// Whether it went wrong in a way no program should, rather than just reporting an error.
+    fn broke(&self) -> bool {
+        self.status.is_none_or(|x| x.code().is_none()) || self.panicked()
+    }
+
+    fn describe(&self, timeout: Duration) -> String {
+        match self.status {
+            None => format!("was still running after {} seconds", timeout.as_secs()),
+            Some(_) if self.panicked() => {
+                let line = self.stderr.lines().find(|x| x.contains("panicked"));
+                // The message is on the line after `panicked at <location>:`.
+                let message = line.and_then(|x| {
+                    let mut lines = self.stderr.lines().skip_while(|l| *l != x);
+                    lines.nth(1)
+                });
+                match message {
+                    Some(x) => format!("panicked: {}", x.trim()),
+                    None => "panicked".into(),
+                }
+            }
+            Some(x) => match x.code() {
+                Some(0) => "exited successfully".into(),
+                Some(code) => format!("exited with code {code}"),
+                None => format!("crashed: {x}"),
+            },
+        }
+    }
+}
+
+/// Runs the lab's binary with nothing on stdin, killing it after `timeout`.
+fn run(
+    ctx: &mut Context,
+    binary: &Utf8PathBuf,
+    args: &[&str],
+    timeout: Duration,
+) -> Result<Run, String> {
+    if ctx.verbose {
+        say!("running {binary} {} with empty stdin", args.join(" "));
+    }
+    let exec = Exec::new(binary.as_str())
+        .args(args.iter().copied())
+        .cwd(&ctx.lab_path)
+        .env("RUST_BACKTRACE", "0")
+        .stdin_null()
+        .stdout(Stream::Discard)
+        .timeout(timeout);
+    let exec = with_lab_env(ctx, &exec);
+    let output = exec.run().map_err(|e| format!("can't be run: {e}"))?;
+    ctx.problems
+        .record_command(exec.describe(), output.duration, output.usage);
+    Ok(Run {
+        status: (!output.timed_out).then_some(output.status),
+        stderr: output.stderr.text(),
+    })
+}
+
+/// The binary the build produced, or why the check can't run.
+fn built_binary(ctx: &mut Context) -> Result<Option<(String, Utf8PathBuf)>, CheckError> {
+    let Some((_, manifest)) = read_lab_manifest(ctx)? else {
+        return Ok(None);
+    };
+    // The manifest says which binary `cargo run` picks; cargo also knows about `src/bin`, and
+    // about libraries, which can't be run.
+    let mut names = binary_names(&manifest, &ctx.lab_path);
+    if let Some(targets) = lab_targets(ctx) {
+        let bins: Vec<&Target> = targets.iter().filter(|x| x.is_bin()).collect();
+        names.retain(|x| bins.iter().any(|b| b.name == *x));
+        names.extend(
+            bins.iter()
+                .filter(|b| !names.contains(&b.name))
+                .map(|b| b.name.clone())
+                .collect::<Vec<_>>(),
+        );
+    }
+    let Some(name) = names.into_iter().next() else {
+        ctx.skip(SkipReason::NotApplicable("the lab has no binary"))?;
+        return Ok(None);
+    };
+    // If it wasn't built, the build check already complained.
+    let Some(binary) = find_binary(ctx, &name) else {
+        ctx.skip(SkipReason::NotApplicable("the binary wasn't built"))?;
+        return Ok(None);
+    };
+    Ok(Some((name, binary)))
+}
+
+pub fn check_stdin_eof(ctx: &mut Context) -> CheckResult {
+    if !ctx.lab_config.stdin_eof_check {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let Some((name, binary)) = built_binary(ctx)? else {
+        return Ok(());
+    };
+    let timeout = Duration::from_secs(ctx.lab_config.stdin_eof_timeout);
+    let run = match run(ctx, &binary, &[], timeout) {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(ctx
+                .problems
+                .add(format!("the binary `{name}` {e}"), binary, None));
+        }
+    };
+
+    let help = "stdin can run out: `read_line` returns `Ok(0)` at end of file, and `lines()` \
+        simply ends. Stop reading or report an error then, instead of looping or unwrapping";
+    match run.status {
+        None => Err(ctx.problems.add(
+            format!(
+                "the binary `{name}` was still running after {} seconds with nothing on stdin; it's probably waiting for input that will never come",
+                timeout.as_secs()
+            ),
+            binary,
+            Some(help.into()),
+        )),
+        Some(status) if status.code() == Some(101) && run.stderr.contains("panicked") => {
+            let excerpt: Vec<&str> = run.stderr.trim().lines().take(MAX_STDERR_LINES).collect();
+            Err(ctx.problems.add(
+                format!(
+                    "the binary `{name}` panicked when stdin was empty:\n{}",
+                    excerpt.join("\n")
+                ),
+                binary,
+                Some(help.into()),
+            ))
+        }
+        Some(status) if status.code().is_none() => Err(ctx.problems.add(
+            format!("the binary `{name}` crashed when stdin was empty: {status}"),
+            binary,
+            Some(help.into()),
+        )),
+        // Exiting with an error code after reporting the missing input is fine.
+        Some(_) => Ok(()),
+    }
+}
+
+/// Runs the binary with each of the lab's argument lists, then again with `extra_arg` after
+/// them, since grading scripts may pass flags the lab doesn't define yet.
+pub fn check_extra_args(ctx: &mut Context) -> CheckResult {
+    if ctx.lab_config.argument_templates.is_empty() {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let Some((name, binary)) = built_binary(ctx)? else {
+        return Ok(());
+    };
+    let timeout = Duration::from_secs(ctx.lab_config.extra_args_timeout);
+    let extra = ctx.lab_config.extra_arg.clone();
+    let mut result = Ok(());
+    for template in ctx.lab_config.argument_templates.clone() {
+        let args: Vec<&str> = template.iter().map(String::as_str).collect();
+        let mut with_extra = args.clone();
+        with_extra.push(&extra);
+        let runs = run(ctx, &binary, &args, timeout)
+            .and_then(|x| Ok((x, run(ctx, &binary, &with_extra, timeout)?)));
+        let (baseline, extended) = match runs {
+            Ok(x) => x,
+            Err(e) => {
+                return Err(ctx
+                    .problems
+                    .add(format!("the binary `{name}` {e}"), binary, None));
+            }
+        };
+        // Only what the extra argument changed is the student's problem here.
+        let failed = |x: &Run| x.status.is_none_or(|x| !x.success());
+        let broke =
+            (extended.broke() && !baseline.broke()) || (failed(&extended) && !failed(&baseline));
+        if !broke {
+            continue;
+        }
+        let command = |args: &[&str]| {
+            let mut x = name.clone();
+            for arg in args {
+                // Quoted like a shell needs them.
+                match arg.is_empty() || arg.contains(char::is_whitespace) {
+                    true => x += &format!(" {arg:?}"),
+                    false => x += &format!(" {arg}"),
+                }
+            }
+            x
+        };
+        result = Err(ctx.problems.add(
+            format!(
+                "the binary `{name}` fails when an extra argument is added:\n  `{}` {}\n  `{}` {}",
+                command(&args),
+                baseline.describe(timeout),
+                command(&with_extra),
+                extended.describe(timeout)
+            ),
+            binary.clone(),
+            Some(format!(
+                "grading may pass arguments the lab doesn't define, like `{extra}`; read the \
+                arguments the lab defines by position, and ignore any after them"
+            )),
+        ));
+    }
+    result
+}
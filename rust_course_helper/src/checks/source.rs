@@ -0,0 +1,122 @@
+use crate::config::LineLengthMode;
+use crate::{CheckResult, Context, SkipReason};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+
+/// Lines containing this are never reported as too long.
+const LONG_LINE_OK: &str = "// checker: long-line-ok";
+const MAX_LONG_LINES_PER_FILE: usize = 5;
+
+/// Every `.rs` file in the lab, skipping build output and hidden folders.
+pub fn rust_sources(lab_path: &Utf8Path) -> Vec<Utf8PathBuf> {
+    let mut result = Vec::new();
+    let mut stack = vec![lab_path.to_owned()];
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = dir.read_dir_utf8() else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if name != "target" && !name.starts_with('.') {
+                    stack.push(entry.into_path());
+                }
+            } else if name.ends_with(".rs") {
+                result.push(entry.into_path());
+            }
+        }
+    }
+    result.sort();
+    result
+}
+
+/// The `.rs` files file-scanning checks look at: all of them, or the ones `--since` selected.
+pub fn scanned_rust_sources(ctx: &Context) -> Vec<Utf8PathBuf> {
+    let mut files = rust_sources(&ctx.lab_path);
+    files.retain(|x| ctx.scope.includes(x));
+    files
+}
+
+fn is_wide(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD)
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F | 0x200B..=0x200F | 0x20D0..=0x20FF | 0xFE00..=0xFE0F | 0xFE20..=0xFE2F)
+}
+
+fn line_length(line: &str, mode: LineLengthMode) -> usize {
+    match mode {
+        LineLengthMode::Chars => line.chars().count(),
+        LineLengthMode::Display => line
+            .chars()
+            .map(|c| match c {
+                _ if is_zero_width(c) => 0,
+                _ if is_wide(c) => 2,
+                _ => 1,
+            })
+            .sum(),
+    }
+}
+
+pub fn check_line_length(ctx: &mut Context) -> CheckResult {
+    let Some(max) = ctx.lab_config.max_line_length else {
+        return ctx.skip(SkipReason::NotConfigured);
+    };
+    let mode = ctx.lab_config.line_length_mode;
+
+    for path in scanned_rust_sources(ctx) {
+        let Ok(text) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        let long_lines: Vec<_> = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.contains(LONG_LINE_OK))
+            .map(|(i, line)| (i + 1, line_length(line, mode)))
+            .filter(|(_, len)| *len > max)
+            .collect();
+
+        for (line, len) in long_lines.iter().take(MAX_LONG_LINES_PER_FILE) {
+            ctx.problems.warn(
+                format!("line {line} is {len} columns long, more than the limit of {max}"),
+                path.clone(),
+                Some(format!(
+                    "split the line, or add `{LONG_LINE_OK}` at its end if it can't be split"
+                )),
+            );
+            ctx.problems.fields([("line", line.to_string())]);
+        }
+        if long_lines.len() > MAX_LONG_LINES_PER_FILE {
+            ctx.problems.warn(
+                format!(
+                    "..and {} more lines longer than {max} columns",
+                    long_lines.len() - MAX_LONG_LINES_PER_FILE
+                ),
+                path.clone(),
+                None,
+            );
+        }
+    }
+
+    Ok(())
+}
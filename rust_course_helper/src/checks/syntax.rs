@@ -0,0 +1,52 @@
+//! The lab's sources parsed with `syn`, shared by the checks that look at code.
+
+use super::source::rust_sources;
+use crate::Context;
+use crate::scope::Scope;
+use camino::Utf8PathBuf;
+use std::fs;
+use std::rc::Rc;
+
+pub struct ParsedFile {
+    pub path: Utf8PathBuf,
+    pub file: syn::File,
+}
+
+/// Every source file that parses, read once per run. Files that don't parse are skipped; the
+/// build reports them.
+pub fn parsed_sources(ctx: &mut Context) -> Rc<Vec<ParsedFile>> {
+    if let Some(x) = &ctx.parsed_sources {
+        return x.clone();
+    }
+    let files: Vec<ParsedFile> = rust_sources(&ctx.lab_path)
+        .into_iter()
+        .filter_map(|path| {
+            let text = fs::read_to_string(&path).ok()?;
+            let file = syn::parse_file(&text).ok()?;
+            Some(ParsedFile { path, file })
+        })
+        .collect();
+    let files = Rc::new(files);
+    ctx.parsed_sources = Some(files.clone());
+    files
+}
+
+/// Parsed sources limited to the files `--since` selected, for checks that look at each file on
+/// its own.
+pub struct ScannedSources {
+    files: Rc<Vec<ParsedFile>>,
+    scope: Scope,
+}
+
+impl ScannedSources {
+    pub fn iter(&self) -> impl Iterator<Item = &ParsedFile> {
+        self.files.iter().filter(|x| self.scope.includes(&x.path))
+    }
+}
+
+pub fn scanned_sources(ctx: &mut Context) -> ScannedSources {
+    ScannedSources {
+        files: parsed_sources(ctx),
+        scope: ctx.scope.clone(),
+    }
+}
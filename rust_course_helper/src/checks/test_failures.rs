@@ -0,0 +1,171 @@
+//! Explanations for test failures, found in the output of `cargo test`.
+
+use crate::Context;
+use camino::Utf8Path;
+
+/// How the standard library reports files and folders that aren't there.
+const NOT_FOUND: &[&str] = &[
+    "No such file or directory",
+    "os error 2",
+    "os error 3",
+    "kind: NotFound",
+    "cannot find the file specified",
+    "cannot find the path specified",
+];
+
+/// Lines before the error that may name the file, like a `panicked at` line with the message.
+const CONTEXT_LINES: usize = 2;
+
+/// Whether `token` is a `file.rs:12:5` source location, which panics print.
+fn is_location(token: &str) -> bool {
+    let mut parts = token.rsplitn(3, ':');
+    let column = parts.next().unwrap_or_default();
+    let line = parts.next().unwrap_or_default();
+    parts.next().is_some()
+        && !column.is_empty()
+        && column.bytes().all(|x| x.is_ascii_digit())
+        && !line.is_empty()
+        && line.bytes().all(|x| x.is_ascii_digit())
+}
+
+fn looks_like_relative_path(token: &str) -> bool {
+    !token.is_empty()
+        && !token.contains(char::is_whitespace)
+        && !token.contains("::")
+        && !Utf8Path::new(token).is_absolute()
+        && !is_location(token)
+        && (token.contains('/')
+            || token.contains('\\')
+            || token.rsplit_once('.').is_some_and(|(name, ext)| {
+                !name.is_empty()
+                    && (1..=5).contains(&ext.len())
+                    && ext.bytes().all(|x| x.is_ascii_alphanumeric())
+            }))
+}
+
+/// Strings in `line` between double quotes or backticks.
+fn quoted(line: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    for quote in ['"', '`'] {
+        let parts: Vec<&str> = line.split(quote).collect();
+        // Odd parts are inside quotes, unless the last quote isn't closed.
+        found.extend(parts.iter().skip(1).step_by(2).take((parts.len() - 1) / 2));
+    }
+    found
+}
+
+/// Relative paths mentioned next to "file not found" errors.
+pub fn missing_files(output: &str) -> Vec<String> {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut paths: Vec<String> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        if !NOT_FOUND.iter().any(|x| line.contains(x)) {
+            continue;
+        }
+        for context in &lines[i.saturating_sub(CONTEXT_LINES)..=i] {
+            let candidates = quoted(context).into_iter().chain(
+                context
+                    .split_whitespace()
+                    .map(|x| x.trim_matches(|c: char| ",;:()[]{}".contains(c))),
+            );
+            for x in candidates.filter(|x| looks_like_relative_path(x)) {
+                if !paths.iter().any(|p| p == x) {
+                    paths.push(x.to_string());
+                }
+            }
+        }
+    }
+    paths
+}
+
+/// Explains failed tests that opened files relative to the wrong folder.
+pub fn check_missing_files(ctx: &mut Context, output: &str) {
+    for path in missing_files(output) {
+        let in_lab = ctx.lab_path.join(&path).exists();
+        let in_repo = ctx.repo_path.join(&path).exists();
+        let text = match (in_lab, in_repo) {
+            (true, _) => format!(
+                "a test couldn't find `{path}`, which exists relative to the lab folder; it only works when run from there"
+            ),
+            (false, true) => format!(
+                "a test couldn't find `{path}`, which exists relative to the repository root, but tests run in the lab folder"
+            ),
+            (false, false) => format!(
+                "a test couldn't find `{path}`, which doesn't exist relative to the lab folder or the repository root"
+            ),
+        };
+        let found = match (in_lab, in_repo) {
+            (true, _) => Some(ctx.lab_path.join(&path)),
+            (false, true) => Some(ctx.repo_path.join(&path)),
+            (false, false) => None,
+        };
+        let help = format!(
+            "don't depend on the working directory; build the path from the crate's folder with `Path::new(env!(\"CARGO_MANIFEST_DIR\")).join({path:?})`"
+        );
+        ctx.problems.warn(text, found, Some(help));
+        let relative_to = match (in_lab, in_repo) {
+            (true, _) => "lab",
+            (false, true) => "repo",
+            (false, false) => "none",
+        };
+        ctx.problems.fields([
+            ("missing_file", path.clone()),
+            ("found_relative_to", relative_to.into()),
+        ]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwrap_on_a_missing_file() {
+        let output = "\
+---- reads_input stdout ----
+
+thread 'reads_input' panicked at src/lib.rs:12:45:
+called `Result::unwrap()` on an `Err` value: Os { code: 2, kind: NotFound, message: \"No such file or directory\" }
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+";
+        // The path isn't in the message, and the location isn't a path.
+        assert!(missing_files(output).is_empty());
+    }
+
+    #[test]
+    fn expect_with_the_path() {
+        let output = "\
+thread 'reads_input' panicked at tests/io.rs:8:10:
+can't open tests/data/input.txt: No such file or directory (os error 2)
+";
+        assert_eq!(missing_files(output), ["tests/data/input.txt"]);
+    }
+
+    #[test]
+    fn quoted_path_on_an_earlier_line() {
+        let output = "\
+thread 'parses' panicked at src/main.rs:3:5:
+failed to read \"input.txt\"
+Caused by: The system cannot find the file specified. (os error 2)
+";
+        assert_eq!(missing_files(output), ["input.txt"]);
+    }
+
+    #[test]
+    fn absolute_paths_and_other_errors_are_left_alone() {
+        let output = "\
+can't open /etc/course/data.txt: No such file or directory (os error 2)
+can't open data.txt: Permission denied (os error 13)
+";
+        assert!(missing_files(output).is_empty());
+    }
+
+    #[test]
+    fn each_path_once() {
+        let output = "\
+open `data.csv`: os error 2
+open `data.csv`: os error 2
+";
+        assert_eq!(missing_files(output), ["data.csv"]);
+    }
+}
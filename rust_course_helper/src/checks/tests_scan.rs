@@ -0,0 +1,228 @@
+//! The lab's test functions.
+
+use super::syntax::parsed_sources;
+use crate::{CheckResult, Context, SkipReason};
+use camino::Utf8PathBuf;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use syn::visit::{self, Visit};
+
+pub struct TestFn {
+    pub path: Utf8PathBuf,
+    pub name: String,
+    pub line: usize,
+    pub should_panic: Option<ShouldPanic>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ShouldPanic {
+    /// Any panic passes.
+    Bare,
+    /// Has an `expected` message.
+    Expected,
+}
+
+struct Collector<'a> {
+    path: &'a Utf8PathBuf,
+    tests: Vec<TestFn>,
+}
+
+fn is_named(attr: &syn::Attribute, name: &str) -> bool {
+    attr.path().segments.last().is_some_and(|x| x.ident == name)
+}
+
+fn should_panic(attr: &syn::Attribute) -> ShouldPanic {
+    match &attr.meta {
+        syn::Meta::Path(_) => ShouldPanic::Bare,
+        syn::Meta::NameValue(_) => ShouldPanic::Expected,
+        syn::Meta::List(list) => {
+            let mut expected = false;
+            let _ = list.parse_nested_meta(|x| {
+                expected |= x.path.is_ident("expected");
+                if x.input.peek(syn::Token![=]) {
+                    x.value()?.parse::<syn::Expr>()?;
+                }
+                Ok(())
+            });
+            match expected {
+                true => ShouldPanic::Expected,
+                false => ShouldPanic::Bare,
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        if item.attrs.iter().any(|x| is_named(x, "test")) {
+            self.tests.push(TestFn {
+                path: self.path.clone(),
+                name: item.sig.ident.to_string(),
+                line: item.sig.ident.span().start().line,
+                should_panic: item
+                    .attrs
+                    .iter()
+                    .find(|x| is_named(x, "should_panic"))
+                    .map(should_panic),
+            });
+        }
+        visit::visit_item_fn(self, item);
+    }
+}
+
+/// The lab's tests.
+pub fn tests(ctx: &mut Context) -> Rc<Vec<TestFn>> {
+    if let Some(x) = &ctx.tests {
+        return x.clone();
+    }
+    let mut tests = Vec::new();
+    for source in parsed_sources(ctx).iter() {
+        let mut collector = Collector {
+            path: &source.path,
+            tests: Vec::new(),
+        };
+        collector.visit_file(&source.file);
+        tests.extend(collector.tests);
+    }
+    let tests = Rc::new(tests);
+    ctx.tests = Some(tests.clone());
+    tests
+}
+
+/// Tests that ran, from the `test result:` lines of every test binary.
+pub fn executed_tests(output: &str) -> usize {
+    let count = |summary: &str, what: &str| {
+        summary
+            .split(';')
+            .find_map(|x| x.trim().strip_suffix(what)?.trim().parse::<usize>().ok())
+            .unwrap_or(0)
+    };
+    output
+        .lines()
+        .filter_map(|x| x.trim().strip_prefix("test result: "))
+        .map(|x| {
+            let x = x.split_once(". ").map_or(x, |(_, rest)| rest);
+            count(x, "passed") + count(x, "failed")
+        })
+        .sum()
+}
+
+/// Whether each test passed, from the `test <name> ... ok` lines `cargo test` prints without
+/// `-q`. A name several test binaries have only passes if it passes in all of them; ignored tests
+/// are left out.
+pub fn test_results(output: &str) -> BTreeMap<String, bool> {
+    let mut results: BTreeMap<String, bool> = BTreeMap::new();
+    for line in output.lines() {
+        let Some((name, result)) = line
+            .strip_prefix("test ")
+            .and_then(|x| x.rsplit_once(" ... "))
+        else {
+            continue;
+        };
+        let passed = match result.trim() {
+            "ok" => true,
+            "FAILED" => false,
+            _ => continue,
+        };
+        *results.entry(name.to_string()).or_insert(passed) &= passed;
+    }
+    results
+}
+
+/// Fails when `cargo test` ran nothing even though the sources have tests.
+pub fn check_zero_tests(ctx: &mut Context, output: &str) -> CheckResult {
+    if ctx.lab_config.allow_zero_tests || executed_tests(output) > 0 {
+        return Ok(());
+    }
+    let tests = tests(ctx);
+    let Some(first) = tests.first() else {
+        return Ok(());
+    };
+    let e = ctx.problems.add(
+        match tests.len() {
+            1 => "`cargo test` ran no tests, but the sources have a test function".to_string(),
+            n => format!("`cargo test` ran no tests, but the sources have {n} test functions"),
+        },
+        first.path.clone(),
+        Some(
+            "the tests are probably never compiled: check for `#[cfg(feature = ...)]` or other \
+            `cfg` attributes around them, a `#[cfg(test)]` module that isn't declared with `mod`, \
+            or tests in a file no target includes"
+                .into(),
+        ),
+    );
+    ctx.problems
+        .fields([("test_function_count", tests.len().to_string())]);
+    Err(e)
+}
+
+pub fn check_should_panic(ctx: &mut Context) -> CheckResult {
+    if !ctx.lab_config.should_panic_check {
+        return ctx.skip(SkipReason::NotConfigured);
+    }
+    let tests = tests(ctx);
+    let help = "name part of the panic message you expect, like \
+        `#[should_panic(expected = \"index out of bounds\")]`, so the test fails when the code \
+        panics for some other reason";
+
+    for test in tests.iter() {
+        if test.should_panic == Some(ShouldPanic::Bare) {
+            ctx.problems.warn(
+                format!(
+                    "`#[should_panic]` on `{}` (line {}) has no `expected` message, so any panic makes it pass",
+                    test.name, test.line
+                ),
+                test.path.clone(),
+                Some(help.into()),
+            );
+            ctx.problems.fields([("line", test.line.to_string())]);
+        }
+    }
+
+    let panicking = tests.iter().filter(|x| x.should_panic.is_some()).count();
+    let max = ctx.lab_config.max_should_panic_percent;
+    if panicking > 0 && panicking * 100 > tests.len() * max {
+        ctx.problems.warn(
+            format!(
+                "{panicking} of {} tests ({}%) are `#[should_panic]`, more than the {max}% expected",
+                tests.len(),
+                panicking * 100 / tests.len()
+            ),
+            ctx.lab_path.clone(),
+            Some("most tests should check what the code does, not that it panics".into()),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_by_test_name() {
+        let output = "
+running 3 tests
+test tests::adds ... ok
+test tests::parses ... FAILED
+test tests::slow ... ignored
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out
+
+running 2 tests
+test tests::adds ... FAILED
+test round_trips ... ok
+test src/lib.rs - parse (line 12) ... ok
+";
+        let results = test_results(output);
+        let results: Vec<(&str, bool)> = results.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        assert_eq!(
+            results,
+            [
+                ("round_trips", true),
+                ("src/lib.rs - parse (line 12)", true),
+                ("tests::adds", false),
+                ("tests::parses", false),
+            ]
+        );
+    }
+}
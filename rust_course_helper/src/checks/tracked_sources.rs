@@ -0,0 +1,145 @@
+//! Source files the build reads that git doesn't have, like a `src/helpers.rs` that was never
+//! `git add`ed. The lab builds here, but not from a clone.
+//!
+//! cargo writes a dep-info file next to what it builds, listing every file the compiler read,
+//! `include!`d ones too. Anything in there under the lab folder and outside the target folders
+//! has to be committed; files a build script generates live in the target folder.
+
+use super::{git_health, target_dirs};
+use crate::fix::Fix;
+use crate::{CheckResult, Context, SkipReason, git};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::BTreeSet;
+use std::fs;
+
+/// The files listed in one dep-info file. Spaces in paths are escaped with a backslash.
+fn dependencies(text: &str) -> Vec<String> {
+    let mut result = Vec::new();
+    for line in text.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        // Drive letters are followed by a backslash, never by a space.
+        let Some((_, deps)) = line.split_once(": ") else {
+            continue;
+        };
+        let mut current = String::new();
+        let mut chars = deps.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&' ') => current.push(chars.next().unwrap_or(' ')),
+                ' ' => result.extend((!current.is_empty()).then(|| std::mem::take(&mut current))),
+                c => current.push(c),
+            }
+        }
+        result.extend((!current.is_empty()).then_some(current));
+    }
+    result
+}
+
+/// Every file some build of the lab read, canonicalized.
+fn needed_files(targets: &[Utf8PathBuf]) -> BTreeSet<Utf8PathBuf> {
+    let mut files = BTreeSet::new();
+    for dir in targets {
+        let debug = dir.join("debug");
+        for dir in [debug.clone(), debug.join("deps")] {
+            let Ok(entries) = dir.read_dir_utf8() else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if entry.path().extension() != Some("d") {
+                    continue;
+                }
+                let Ok(text) = fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                files.extend(
+                    dependencies(&text)
+                        .into_iter()
+                        .filter_map(|x| Utf8Path::new(&x).canonicalize_utf8().ok()),
+                );
+            }
+        }
+    }
+    files
+}
+
+pub fn check_untracked_sources(ctx: &mut Context) -> CheckResult {
+    if !matches!(git_health(ctx), git::Health::Healthy) {
+        return ctx.skip(SkipReason::NotApplicable(
+            "the repo's git history can't be read",
+        ));
+    }
+    let (Ok(repo), Ok(lab)) = (
+        ctx.repo_path.canonicalize_utf8(),
+        ctx.lab_path.canonicalize_utf8(),
+    ) else {
+        return ctx.skip(SkipReason::NotApplicable("the lab folder can't be found"));
+    };
+    let targets: Vec<Utf8PathBuf> = target_dirs(ctx)
+        .iter()
+        .filter_map(|x| x.canonicalize_utf8().ok())
+        .collect();
+    let needed: Vec<Utf8PathBuf> = needed_files(&targets)
+        .into_iter()
+        .filter(|x| x.starts_with(&lab) && !targets.iter().any(|t| x.starts_with(t)))
+        .collect();
+    if needed.is_empty() {
+        return ctx.skip(SkipReason::NotApplicable(
+            "the build didn't leave dep-info files",
+        ));
+    }
+
+    let tracked = match git::git(&ctx.repo_path, &["ls-files", "-z"]) {
+        Ok(x) => x,
+        Err(e) => return Err(ctx.problems.add(e, ctx.repo_path.clone(), None)),
+    };
+    let tracked: BTreeSet<&str> = tracked.split('\0').collect();
+    let untracked: Vec<String> = needed
+        .iter()
+        .filter_map(|x| x.strip_prefix(&repo).ok())
+        .map(|x| x.as_str().replace('\\', "/"))
+        .filter(|x| !tracked.contains(x.as_str()))
+        .collect();
+    if untracked.is_empty() {
+        return Ok(());
+    }
+
+    // Ignored files need `-f` to be added; `check-ignore` exits with 1 when none is.
+    let mut args = vec!["check-ignore", "--"];
+    args.extend(untracked.iter().map(String::as_str));
+    let ignored = git::git(&ctx.repo_path, &args).unwrap_or_default();
+    let ignored: Vec<&str> = ignored.lines().collect();
+
+    let mut add = vec!["add"];
+    if !ignored.is_empty() {
+        add.push("-f");
+    }
+    add.push("--");
+    add.extend(untracked.iter().map(String::as_str));
+    let help = match ignored.is_empty() {
+        true => format!("commit them: `git {}`", add.join(" ")),
+        false => format!(
+            "commit them: `git {}`; `-f` is needed because .gitignore matches {}, so check that it doesn't ignore more source files",
+            add.join(" "),
+            ignored.join(", ")
+        ),
+    };
+    ctx.problems.warn_fixable(
+        format!(
+            "{} files the build reads aren't committed, so the lab won't build from a clone",
+            untracked.len()
+        ),
+        untracked
+            .iter()
+            .map(|x| ctx.repo_path.join(x))
+            .collect::<Vec<_>>(),
+        Some(help),
+        Fix::run(ctx.repo_path.clone(), "git", add),
+    );
+    ctx.problems.fields([
+        ("untracked_file_count", untracked.len().to_string()),
+        ("ignored_file_count", ignored.len().to_string()),
+    ]);
+    Ok(())
+}
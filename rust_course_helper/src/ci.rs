@@ -0,0 +1,207 @@
+//! `--ci github`: output for GitHub Actions.
+//!
+//! Each check's output is a collapsible group in the log, problems become annotations on the
+//! files they're about, and the step gets a markdown summary and a `verdict` output, `success`
+//! or `failure`, for later steps. Everything else is the same as a normal run.
+
+use crate::report::{self, RepoPaths};
+use crate::{Diags, Severity};
+use std::env;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Ci {
+    Github,
+}
+
+static GROUPS: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_groups() {
+    GROUPS.store(true, Ordering::Relaxed);
+}
+
+pub fn start_group(name: &str) {
+    if GROUPS.load(Ordering::Relaxed) {
+        say!("::group::{name}");
+    }
+}
+
+pub fn end_group() {
+    if GROUPS.load(Ordering::Relaxed) {
+        say!("::endgroup::");
+    }
+}
+
+/// Workflow command messages can't have line breaks.
+fn escape_data(x: &str) -> String {
+    x.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn escape_property(x: &str) -> String {
+    escape_data(x).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Paths relative to the repo, which is what annotations expect when it's the checkout.
+fn relative_path<'a>(problems: &Diags, path: &'a str) -> &'a str {
+    problems
+        .repo
+        .as_ref()
+        .and_then(|x| path.strip_prefix(x.as_str()))
+        .map_or(path, |x| x.trim_start_matches('/'))
+}
+
+/// One `::error` or `::warning` command for each problem.
+pub fn annotations(problems: &Diags) -> String {
+    let mut out = String::new();
+    for x in &problems.problems {
+        let command = match x.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let mut properties = vec![format!(
+            "title={}",
+            escape_property(x.check.unwrap_or("checker"))
+        )];
+        if let Some(path) = x.paths.first() {
+            properties.push(format!(
+                "file={}",
+                escape_property(relative_path(problems, path.as_str()))
+            ));
+        }
+        if let Some(line) = x.fields.get("line") {
+            properties.push(format!("line={}", escape_property(line)));
+        }
+        let mut text = x.text.clone();
+        if let Some(help) = &x.help {
+            text += &format!("\nhelp: {help}");
+        }
+        writeln!(
+            out,
+            "::{command} {}::{}",
+            properties.join(","),
+            escape_data(&text)
+        )
+        .expect("writing to a string");
+    }
+    out
+}
+
+/// The step summary: the verdict, a table of the checks, and the problems. With `stable`, it's
+/// sorted and has no times or absolute paths, like the JSON report.
+pub fn summary(problems: &Diags, success: bool, stable: bool) -> String {
+    let repo_paths = problems
+        .repo
+        .as_deref()
+        .filter(|_| stable)
+        .map(RepoPaths::new);
+    let lab = problems.lab.as_ref().map_or("lab", |x| x.0.as_str());
+    let verdict = if success { "success" } else { "failure" };
+    let mut out = format!("## {lab}: {verdict}\n\n");
+    out += match stable {
+        true => "| check | result |\n|---|---|\n",
+        false => "| check | result | time |\n|---|---|---|\n",
+    };
+    let mut checks: Vec<_> = problems.checks.iter().collect();
+    if stable {
+        checks.sort_by_key(|x| x.name);
+    }
+    for x in checks {
+        let result = match (&x.skipped, x.passed) {
+            (Some(reason), _) => format!("skipped: {reason}"),
+            (None, true) => "passed".into(),
+            (None, false) if x.environment_failure => "**failed** (environment)".into(),
+            (None, false) => "**failed**".into(),
+        };
+        match stable {
+            true => writeln!(out, "| {} | {result} |", x.name),
+            false => writeln!(
+                out,
+                "| {} | {result} | {:.1}s |",
+                x.name,
+                x.duration.as_secs_f64()
+            ),
+        }
+        .expect("writing to a string");
+    }
+    if !problems.problems.is_empty() {
+        out += "\n### Problems\n\n";
+        let diags = match stable {
+            true => report::stable_order(problems),
+            false => problems.problems.iter().collect(),
+        };
+        for x in diags {
+            let severity = match x.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            let text = x.text.lines().next().unwrap_or_default();
+            let text = match &repo_paths {
+                Some(repo) => repo.text(text),
+                None => text.to_string(),
+            };
+            write!(
+                out,
+                "- **{severity}** [{}] {text}",
+                x.check.unwrap_or("checker")
+            )
+            .expect("writing to a string");
+            if let Some(path) = x.paths.first() {
+                let path = match &repo_paths {
+                    Some(repo) => repo.path(path.as_str()),
+                    None => relative_path(problems, path.as_str()).to_string(),
+                };
+                write!(out, " (`{path}`)").expect("writing to a string");
+            }
+            out.push('\n');
+        }
+    }
+    let kept: Vec<_> = problems
+        .artifacts
+        .iter()
+        .filter(|x| x.path.is_some())
+        .collect();
+    if let (Some(dir), false) = (&problems.artifacts_dir, kept.is_empty()) {
+        out += "\n### Artifacts\n\n";
+        for x in kept {
+            let path = x.path.as_ref().expect("kept artifacts have a path");
+            // Where the folder is changes between runs, so stable summaries leave it out.
+            let path = match stable {
+                true => path.clone(),
+                false => dir.join(path),
+            };
+            writeln!(
+                out,
+                "- [{}]({path}) ({}, {} bytes)",
+                x.name, x.check, x.bytes
+            )
+            .expect("writing to a string");
+        }
+    }
+    out
+}
+
+/// Appends to the file an Actions variable like `GITHUB_OUTPUT` names, if it's set.
+fn append_to_env_file(var: &str, text: &str) -> Result<(), String> {
+    let Some(path) = env::var_os(var) else {
+        return Ok(());
+    };
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut x| x.write_all(text.as_bytes()))
+        .map_err(|e| format!("can't write to `{var}` ({}): {e}", path.to_string_lossy()))
+}
+
+/// Prints the annotations, and writes the summary and the verdict for the step.
+pub fn finish(problems: &Diags, success: bool, stable: bool) -> Result<(), String> {
+    say_inline!("{}", annotations(problems));
+    append_to_env_file("GITHUB_STEP_SUMMARY", &summary(problems, success, stable))?;
+    let verdict = if success { "success" } else { "failure" };
+    append_to_env_file("GITHUB_OUTPUT", &format!("verdict={verdict}\n"))
+}
@@ -0,0 +1,92 @@
+//! The current time, for everything that compares it with a recorded time: flakiness
+//! histories, the usage log, receipts, and the run lock.
+//!
+//! Lab machines' clocks are sometimes years off. Deadlines are judged by the HEAD commit's
+//! time, not this one, but `skew` still says when the two disagree by too much to trust
+//! either. Nothing stands in for the clock, since receipts are signed with its time.
+
+use crate::datetime;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How far a commit can be ahead of the clock. Clocks a few minutes off are common, and so are
+/// time zones set wrong, which git hides but the clock doesn't.
+const TOLERANCE: u64 = 86400;
+/// 2025-01-01, before this checker existed. Clocks that say it's earlier were reset, usually by
+/// a flat CMOS battery.
+const EARLIEST: u64 = 1_735_689_600;
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+/// What's wrong with the clock, if it's implausibly early or `commit_time` is well after it.
+pub fn skew(commit_time: Option<u64>) -> Option<String> {
+    skew_at(commit_time, now())
+}
+
+fn skew_at(commit_time: Option<u64>, now: u64) -> Option<String> {
+    if now < EARLIEST {
+        return Some(format!(
+            "this machine's clock says it's {}, which can't be right",
+            datetime::utc(now)
+        ));
+    }
+    let commit = commit_time.filter(|x| *x > now + TOLERANCE)?;
+    Some(format!(
+        "the last commit is from {}, {} days after this machine's clock says it is ({}), so one of the two clocks is wrong",
+        datetime::utc(commit),
+        (commit - now) / 86400,
+        datetime::utc(now)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-10-20 12:00 UTC.
+    const NOW: u64 = 1_792_497_600;
+
+    #[test]
+    fn clocks_that_agree() {
+        assert_eq!(skew_at(None, NOW), None);
+        assert_eq!(skew_at(Some(NOW - 30 * 86400), NOW), None);
+        // Commits a little ahead are clocks a few minutes off, or time zones set wrong.
+        assert_eq!(skew_at(Some(NOW + 3600), NOW), None);
+        assert_eq!(skew_at(Some(NOW + TOLERANCE), NOW), None);
+    }
+
+    #[test]
+    fn commits_from_the_future() {
+        let skew = skew_at(Some(NOW + 3 * 86400), NOW).unwrap();
+        assert_eq!(
+            skew,
+            "the last commit is from 2026-10-23 12:00 UTC, 3 days after this machine's clock says it is (2026-10-20 12:00 UTC), so one of the two clocks is wrong"
+        );
+        assert!(skew_at(Some(NOW + TOLERANCE + 1), NOW).is_some());
+    }
+
+    #[test]
+    fn clocks_that_were_reset() {
+        // 2000-01-01, where a flat CMOS battery leaves some machines.
+        let skew = skew_at(None, 946_684_800).unwrap();
+        assert!(
+            skew.contains("2000-01-01 00:00 UTC, which can't be right"),
+            "{skew}"
+        );
+        assert!(skew_at(Some(NOW), EARLIEST - 1).is_some());
+        assert_eq!(skew_at(None, EARLIEST), None);
+    }
+
+    #[test]
+    fn the_clock_is_real() {
+        let system = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(now().abs_diff(system) <= 1);
+    }
+}
@@ -0,0 +1,862 @@
+//! Course configuration, loaded from the file given with `--config`.
+//!
+//! Settings in `[defaults]` apply to every lab; a `[labs.<name>]` table overrides them for
+//! one lab, and a `[tracks.<name>]` table selected with `--track` overrides both. The grading
+//! settings of a `[rubrics.<name>]` table override them all when `rubric` names it. Unknown keys
+//! are ignored so older checkers keep working with newer configs.
+
+use crate::checks::CHECKS;
+use crate::datetime;
+use crate::toml::{self, Table, Value};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+
+pub struct LabConfig {
+    /// Dependencies, by name, that may point at paths outside the repo or at private git hosts.
+    pub allowed_local_dependencies: Vec<String>,
+    /// Longest allowed line in source files. Not checked when missing.
+    pub max_line_length: Option<usize>,
+    pub line_length_mode: LineLengthMode,
+    /// Secret used to sign receipts.
+    pub receipt_secret: Option<String>,
+    /// Branch names the student should work on; `*` matches anything. Not checked when empty.
+    pub expected_branches: Vec<String>,
+    /// Run the lab's binary with nothing on stdin, to catch programs that can't handle EOF.
+    pub stdin_eof_check: bool,
+    /// How long the binary may run in the stdin check, in seconds.
+    pub stdin_eof_timeout: u64,
+    /// Argument lists the lab's binary is documented to take, each an array of strings like
+    /// `["--name", "Ana Maria"]`, for the extra argument check. Not checked when empty.
+    pub argument_templates: Vec<Vec<String>>,
+    /// What the extra argument check adds after each of `argument_templates`.
+    pub extra_arg: String,
+    /// How long each run of the extra argument check may take, in seconds.
+    pub extra_args_timeout: u64,
+    /// Checks that aren't run.
+    pub skip_checks: Vec<String>,
+    /// Checks whose problems are only warnings, so they never fail the run.
+    pub warning_checks: Vec<String>,
+    /// Which rubric variant grades this lab; its `[rubrics]` table, if the config has one, sets
+    /// the grading policy.
+    pub rubric: Option<String>,
+    /// Whether `[patch]` and `[replace]` sections are only warnings, e.g. for the project.
+    pub allow_dependency_overrides: bool,
+    /// Checks to run first, in this order.
+    pub check_order: Vec<String>,
+    /// Binaries grading runs with `cargo run --bin`; the lab name when empty, unless the lab is
+    /// a library.
+    pub expected_binaries: Vec<String>,
+    /// Warn about `#[should_panic]` tests that would pass on any panic.
+    pub should_panic_check: bool,
+    /// Share of tests that may be `#[should_panic]` before that's a warning.
+    pub max_should_panic_percent: usize,
+    /// Whether `cargo test` may run no tests even though the sources have some.
+    pub allow_zero_tests: bool,
+    /// What `init` creates.
+    pub crate_type: CrateType,
+    pub edition: String,
+    /// Authors `init` writes to `Cargo.toml`; the git user when empty.
+    pub authors: Vec<String>,
+    /// Folder with starter files that `init` copies into the lab, relative to the config file.
+    pub starter_dir: Option<String>,
+    pub metadata: LabMetadata,
+    /// Rust file with the signatures the lab has to implement. Given relative to the config file,
+    /// and resolved when loading.
+    pub api_file: Option<Utf8PathBuf>,
+    /// Code the lab's solution has, like `fn run_simulation`, so its absence suggests another
+    /// lab's code was submitted. Matched as tokens, so spacing and comments don't matter.
+    pub required_markers: Vec<String>,
+    /// Code only other labs' solutions have, like lab04's `fn parse_csv` for lab05.
+    pub forbidden_markers: Vec<String>,
+    /// Seconds a run is expected to take, for `--soft-budget`.
+    pub soft_budget: Option<u64>,
+    /// Seconds a build may take before `--build-timings` names the slowest dependencies.
+    pub slow_build_seconds: u64,
+    /// Days a repo's folder in the cache can go unused before runs remove it; never when 0.
+    pub cache_max_age_days: u64,
+    /// Folder names the lab may be in, tried in order; `*` matches any run of characters. Just
+    /// the lab name when empty.
+    pub lab_dirs: Vec<String>,
+    /// Command, run in the lab without a shell, that generates the files in `generated_dir`.
+    /// `{out}` in its arguments is replaced with the folder to write to.
+    pub generator: Vec<String>,
+    /// Folder in the lab, committed by the student, that has to match what `generator` writes.
+    pub generated_dir: Option<String>,
+    /// How long the generator may run, in seconds.
+    pub generator_timeout: u64,
+    /// `[package]` fields a publishable crate has to fill in, like `description`. Not checked
+    /// when empty.
+    pub publish_fields: Vec<String>,
+    /// Run `cargo publish --dry-run` to catch packaging problems.
+    pub publish_dry_run: bool,
+    /// Warn about repos that look shared by several students.
+    pub shared_repo_check: bool,
+    /// Folders of the same lab with different names after it, like `lab03-ana` and
+    /// `lab03-ion`, before that looks like sharing.
+    pub shared_repo_min_variants: usize,
+    /// Commits a lab folder needs before its main author counts.
+    pub shared_repo_min_commits: usize,
+    /// Share of a folder's commits, in percent, its main author needs.
+    pub shared_repo_dominance_percent: usize,
+    pub grading: GradingPolicy,
+    pub docs: DocsLinks,
+    /// Variables set for the commands checks run, from the `env` table. `{lab_path}` and
+    /// `{repo_path}` in values are replaced; see `child_env`.
+    pub env: Vec<(String, String)>,
+}
+
+/// Where the course's pages about each kind of problem are; see `docs`.
+#[derive(Clone, Default)]
+pub struct DocsLinks {
+    pub base_url: Option<String>,
+    /// Pages for some codes, from the `docs_urls` table, used instead of the base URL.
+    pub overrides: Vec<(String, String)>,
+    /// Codes that have a page under the base URL; all of them when empty.
+    pub documented: Vec<String>,
+}
+
+/// How `grade` turns the checks' results into a score.
+#[derive(Clone)]
+pub struct GradingPolicy {
+    /// Points for each check, from the `points` table, in check name order.
+    pub points: Vec<(String, u64)>,
+    /// Points for each test that passes, by the name `cargo test` gives it, like
+    /// `tests::parses_empty_input`, from the `test_points` table.
+    pub test_points: Vec<(String, u64)>,
+    /// Checks whose points are given whatever they found, e.g. for an extension.
+    pub waived_checks: Vec<String>,
+    /// Seconds since the Unix epoch.
+    pub deadline: Option<u64>,
+    /// Share of the points lost for each started day after the deadline.
+    pub late_penalty_percent: u64,
+}
+
+/// Course context for reports. Everything is optional.
+#[derive(Clone, Default)]
+pub struct LabMetadata {
+    pub title: Option<String>,
+    pub week: Option<usize>,
+    pub tags: Vec<String>,
+    /// Where the lab's statement is published.
+    pub statement_url: Option<String>,
+}
+
+impl LabMetadata {
+    /// One line describing the lab, or `None` without a title or week.
+    pub fn header(&self, lab: &str) -> Option<String> {
+        let mut line = match (&self.title, self.week) {
+            (Some(title), Some(week)) => format!("{lab}: {title} (week {week})"),
+            (Some(title), None) => format!("{lab}: {title}"),
+            (None, Some(week)) => format!("{lab} (week {week})"),
+            (None, None) => return None,
+        };
+        if !self.tags.is_empty() {
+            line += &format!(" [{}]", self.tags.join(", "));
+        }
+        if let Some(url) = &self.statement_url {
+            line += &format!("\n{url}");
+        }
+        Some(line)
+    }
+}
+
+impl Default for LabConfig {
+    fn default() -> Self {
+        LabConfig {
+            allowed_local_dependencies: Vec::new(),
+            max_line_length: None,
+            line_length_mode: LineLengthMode::Chars,
+            receipt_secret: None,
+            expected_branches: Vec::new(),
+            stdin_eof_check: false,
+            stdin_eof_timeout: 5,
+            argument_templates: Vec::new(),
+            extra_arg: "--quiet".into(),
+            extra_args_timeout: 5,
+            skip_checks: Vec::new(),
+            warning_checks: Vec::new(),
+            rubric: None,
+            allow_dependency_overrides: false,
+            check_order: Vec::new(),
+            expected_binaries: Vec::new(),
+            should_panic_check: false,
+            max_should_panic_percent: 30,
+            allow_zero_tests: false,
+            crate_type: CrateType::Bin,
+            edition: "2024".into(),
+            authors: Vec::new(),
+            starter_dir: None,
+            metadata: LabMetadata::default(),
+            api_file: None,
+            required_markers: Vec::new(),
+            forbidden_markers: Vec::new(),
+            soft_budget: None,
+            slow_build_seconds: 120,
+            cache_max_age_days: 30,
+            lab_dirs: Vec::new(),
+            generator: Vec::new(),
+            generated_dir: None,
+            generator_timeout: 60,
+            publish_fields: Vec::new(),
+            publish_dry_run: false,
+            shared_repo_check: false,
+            shared_repo_min_variants: 2,
+            shared_repo_min_commits: 3,
+            shared_repo_dominance_percent: 80,
+            grading: GradingPolicy {
+                points: Vec::new(),
+                test_points: Vec::new(),
+                waived_checks: Vec::new(),
+                deadline: None,
+                late_penalty_percent: 10,
+            },
+            docs: DocsLinks::default(),
+            env: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum CrateType {
+    Bin,
+    Lib,
+}
+
+#[derive(Default, Clone, Copy)]
+pub enum LineLengthMode {
+    /// Counts Unicode scalar values.
+    #[default]
+    Chars,
+    /// Counts terminal columns, so wide characters count twice.
+    Display,
+}
+
+impl LabConfig {
+    fn from_table(table: &Table) -> Result<LabConfig, String> {
+        let fields = Fields(table);
+        let default = LabConfig::default();
+        let line_length_mode = match fields.string("line_length_mode")? {
+            None | Some("chars") => LineLengthMode::Chars,
+            Some("display") => LineLengthMode::Display,
+            Some(x) => {
+                return Err(format!(
+                    "`line_length_mode` must be `chars` or `display`, found `{x}`"
+                ));
+            }
+        };
+        let crate_type = match fields.string("crate_type")? {
+            None => default.crate_type,
+            Some("bin") => CrateType::Bin,
+            Some("lib") => CrateType::Lib,
+            Some(x) => return Err(format!("`crate_type` must be `bin` or `lib`, found `{x}`")),
+        };
+        Ok(LabConfig {
+            allowed_local_dependencies: fields.string_list("allowed_local_dependencies")?,
+            max_line_length: fields.unsigned("max_line_length")?,
+            line_length_mode,
+            receipt_secret: fields.string("receipt_secret")?.map(String::from),
+            expected_branches: fields.string_list("expected_branches")?,
+            stdin_eof_check: fields
+                .bool("stdin_eof_check")?
+                .unwrap_or(default.stdin_eof_check),
+            stdin_eof_timeout: fields
+                .unsigned("stdin_eof_timeout")?
+                .map_or(default.stdin_eof_timeout, |x| x as u64),
+            argument_templates: fields.string_lists("argument_templates")?,
+            extra_arg: fields
+                .string("extra_arg")?
+                .map_or(default.extra_arg, String::from),
+            extra_args_timeout: fields
+                .unsigned("extra_args_timeout")?
+                .map_or(default.extra_args_timeout, |x| x as u64),
+            skip_checks: fields.check_list("skip_checks")?,
+            warning_checks: fields.check_list("warning_checks")?,
+            rubric: fields.string("rubric")?.map(String::from),
+            allow_dependency_overrides: fields
+                .bool("allow_dependency_overrides")?
+                .unwrap_or(default.allow_dependency_overrides),
+            check_order: fields.check_list("check_order")?,
+            expected_binaries: fields.string_list("expected_binaries")?,
+            should_panic_check: fields
+                .bool("should_panic_check")?
+                .unwrap_or(default.should_panic_check),
+            max_should_panic_percent: fields
+                .unsigned("max_should_panic_percent")?
+                .unwrap_or(default.max_should_panic_percent),
+            allow_zero_tests: fields
+                .bool("allow_zero_tests")?
+                .unwrap_or(default.allow_zero_tests),
+            crate_type,
+            edition: fields
+                .string("edition")?
+                .map_or(default.edition, String::from),
+            authors: fields.string_list("authors")?,
+            starter_dir: fields.string("starter_dir")?.map(String::from),
+            metadata: LabMetadata {
+                title: fields.string("title")?.map(String::from),
+                week: fields.unsigned("week")?,
+                tags: fields.string_list("tags")?,
+                statement_url: fields.string("statement_url")?.map(String::from),
+            },
+            api_file: fields.string("api_file")?.map(Utf8PathBuf::from),
+            required_markers: fields.string_list("required_markers")?,
+            forbidden_markers: fields.string_list("forbidden_markers")?,
+            soft_budget: fields.unsigned("soft_budget")?.map(|x| x as u64),
+            slow_build_seconds: fields
+                .unsigned("slow_build_seconds")?
+                .map_or(default.slow_build_seconds, |x| x as u64),
+            cache_max_age_days: fields
+                .unsigned("cache_max_age_days")?
+                .map_or(default.cache_max_age_days, |x| x as u64),
+            lab_dirs: fields.string_list("lab_dirs")?,
+            generator: fields.string_list("generator")?,
+            generated_dir: fields.string("generated_dir")?.map(String::from),
+            generator_timeout: fields
+                .unsigned("generator_timeout")?
+                .map_or(default.generator_timeout, |x| x as u64),
+            publish_fields: fields.string_list("publish_fields")?,
+            publish_dry_run: fields
+                .bool("publish_dry_run")?
+                .unwrap_or(default.publish_dry_run),
+            shared_repo_check: fields
+                .bool("shared_repo_check")?
+                .unwrap_or(default.shared_repo_check),
+            shared_repo_min_variants: fields
+                .unsigned("shared_repo_min_variants")?
+                .unwrap_or(default.shared_repo_min_variants),
+            shared_repo_min_commits: fields
+                .unsigned("shared_repo_min_commits")?
+                .unwrap_or(default.shared_repo_min_commits),
+            shared_repo_dominance_percent: fields
+                .unsigned("shared_repo_dominance_percent")?
+                .unwrap_or(default.shared_repo_dominance_percent),
+            grading: GradingPolicy {
+                points: fields.points("points")?,
+                test_points: fields.unsigned_table("test_points")?,
+                waived_checks: fields.check_list("waived_checks")?,
+                deadline: fields.datetime("deadline")?,
+                late_penalty_percent: fields
+                    .unsigned("late_penalty_percent")?
+                    .map_or(default.grading.late_penalty_percent, |x| x as u64),
+            },
+            docs: DocsLinks {
+                base_url: fields.string("docs_base_url")?.map(String::from),
+                overrides: fields.urls("docs_urls")?,
+                documented: fields.check_list("documented_checks")?,
+            },
+            env: fields.string_table("env")?,
+        })
+    }
+
+    pub fn skips(&self, check: &str) -> bool {
+        self.skip_checks.iter().any(|x| x == check)
+    }
+
+    pub fn only_warns(&self, check: &str) -> bool {
+        self.warning_checks.iter().any(|x| x == check)
+    }
+
+    /// The `env` variables, with `{lab_path}` and `{repo_path}` replaced by absolute paths.
+    pub fn child_env(&self, repo: &Utf8Path, lab: &Utf8Path) -> Vec<(String, String)> {
+        let absolute = |x: &Utf8Path| x.canonicalize_utf8().unwrap_or_else(|_| x.to_owned());
+        let (repo, lab) = (absolute(repo), absolute(lab));
+        self.env
+            .iter()
+            .map(|(key, value)| {
+                let value = value
+                    .replace("{lab_path}", lab.as_str())
+                    .replace("{repo_path}", repo.as_str());
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
+    /// Prints the settings that apply, for `--show-config`.
+    pub fn show(&self, lab: &str, track: Option<&str>) {
+        say!("lab: {lab}");
+        if let Some(header) = self.metadata.header(lab) {
+            say!("{header}");
+        }
+        say!("track: {}", track.unwrap_or("none"));
+        say!("rubric: {}", self.rubric.as_deref().unwrap_or("default"));
+        say!("checks:");
+        for check in CHECKS {
+            let mode = if self.skips(check.name) {
+                "skipped"
+            } else if self.only_warns(check.name) {
+                "warning"
+            } else {
+                "error"
+            };
+            say!("  {:<24}{mode}", check.name);
+        }
+
+        let list = |x: &[String]| match x.is_empty() {
+            true => "none".to_string(),
+            false => x.join(", "),
+        };
+        say!("lab folders: {}", list(&self.lab_dirs));
+        say!("expected branches: {}", list(&self.expected_branches));
+        say!(
+            "allowed local dependencies: {}",
+            list(&self.allowed_local_dependencies)
+        );
+        match self.max_line_length {
+            Some(x) => say!(
+                "max line length: {x} ({})",
+                match self.line_length_mode {
+                    LineLengthMode::Chars => "chars",
+                    LineLengthMode::Display => "display",
+                }
+            ),
+            None => say!("max line length: none"),
+        }
+        match self.grading.points.is_empty() {
+            true => say!("grading: no points"),
+            false => {
+                let max: u64 = self.grading.points.iter().map(|x| x.1).sum();
+                match self.grading.test_points.len() {
+                    0 => say!("grading: {max} points"),
+                    tests => {
+                        let points: u64 = self.grading.test_points.iter().map(|x| x.1).sum();
+                        say!("grading: {max} points, and {points} for {tests} tests");
+                    }
+                }
+            }
+        }
+        match self.grading.deadline {
+            Some(x) => say!(
+                "deadline: {}, -{}% per late day",
+                datetime::utc(x),
+                self.grading.late_penalty_percent
+            ),
+            None => say!("deadline: none"),
+        }
+        match self.soft_budget {
+            Some(x) => say!("soft budget: {x}s"),
+            None => say!("soft budget: none"),
+        }
+        say!("slow build: over {}s", self.slow_build_seconds);
+        match self.cache_max_age_days {
+            0 => say!("cache: kept"),
+            x => say!("cache: removed after {x} days unused"),
+        }
+        match self.stdin_eof_check {
+            true => say!("stdin check: on, {}s timeout", self.stdin_eof_timeout),
+            false => say!("stdin check: off"),
+        }
+        match self.argument_templates.is_empty() {
+            true => say!("extra argument check: off"),
+            false => say!(
+                "extra argument check: `{}` after {} argument lists, {}s timeout",
+                self.extra_arg,
+                self.argument_templates.len(),
+                self.extra_args_timeout
+            ),
+        }
+        match (self.required_markers.len(), self.forbidden_markers.len()) {
+            (0, 0) => say!("lab markers: off"),
+            (required, forbidden) => {
+                say!("lab markers: {required} required, {forbidden} forbidden")
+            }
+        }
+        match &self.docs.base_url {
+            Some(x) => say!(
+                "docs: {x}, {} overridden, {}",
+                self.docs.overrides.len(),
+                match self.docs.documented.is_empty() {
+                    true => "for every check".to_string(),
+                    false => format!("for {}", list(&self.docs.documented)),
+                }
+            ),
+            None => say!("docs: {} overridden", self.docs.overrides.len()),
+        }
+        match self.env.is_empty() {
+            true => say!("environment: none"),
+            false => {
+                let vars: Vec<String> = self.env.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                say!("environment: {}", vars.join(" "));
+            }
+        }
+        say!(
+            "dependency overrides: {}",
+            if self.allow_dependency_overrides {
+                "warning"
+            } else {
+                "error"
+            }
+        );
+        say!(
+            "receipt secret: {}",
+            if self.receipt_secret.is_some() {
+                "set"
+            } else {
+                "not set"
+            }
+        );
+    }
+}
+
+/// What a `[rubrics.<name>]` table can set.
+const GRADING_KEYS: &[&str] = &[
+    "points",
+    "test_points",
+    "waived_checks",
+    "deadline",
+    "late_penalty_percent",
+];
+
+pub fn load(path: &Utf8Path, lab: &str, track: Option<&str>) -> Result<LabConfig, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read config: {e}"))?;
+    let root = toml::parse(&text).map_err(|e| format!("can't parse config: {e}"))?;
+
+    let mut table = Table::new();
+    if let Some(defaults) = root.get("defaults") {
+        toml::merge(&mut table, as_table("defaults", defaults)?);
+    }
+    if let Some(labs) = root.get("labs")
+        && let Some(lab_table) = as_table("labs", labs)?.get(lab)
+    {
+        toml::merge(&mut table, as_table(lab, lab_table)?);
+    }
+    if let Some(track) = track {
+        let tracks = match root.get("tracks") {
+            Some(x) => as_table("tracks", x)?,
+            None => &Table::new(),
+        };
+        let Some(track_table) = tracks.get(track) else {
+            let names: Vec<&str> = tracks.keys().map(String::as_str).collect();
+            return Err(match names.is_empty() {
+                true => format!("unknown track `{track}`; the config has no tracks"),
+                false => format!(
+                    "unknown track `{track}`; configured tracks: {}",
+                    names.join(", ")
+                ),
+            });
+        };
+        toml::merge(&mut table, as_table(track, track_table)?);
+    }
+    if let Some(rubric) = table
+        .get("rubric")
+        .and_then(Value::as_str)
+        .map(String::from)
+        && let Some(rubrics) = root.get("rubrics")
+    {
+        let rubrics = as_table("rubrics", rubrics)?;
+        let Some(rubric_table) = rubrics.get(&rubric) else {
+            let names: Vec<&str> = rubrics.keys().map(String::as_str).collect();
+            return Err(format!(
+                "unknown rubric `{rubric}`; configured rubrics: {}",
+                names.join(", ")
+            ));
+        };
+        let rubric_table = as_table(&rubric, rubric_table)?;
+        if let Some(key) = rubric_table
+            .keys()
+            .find(|x| !GRADING_KEYS.contains(&x.as_str()))
+        {
+            return Err(format!(
+                "`rubrics.{rubric}.{key}` isn't a grading setting; rubrics can set {}",
+                GRADING_KEYS.join(", ")
+            ));
+        }
+        toml::merge(&mut table, rubric_table);
+    }
+
+    let mut config = LabConfig::from_table(&table)?;
+    let dir = path.parent().unwrap_or(Utf8Path::new("."));
+    config.api_file = config.api_file.map(|x| dir.join(x));
+    Ok(config)
+}
+
+fn as_table<'v>(name: &str, value: &'v Value) -> Result<&'v Table, String> {
+    value
+        .as_table()
+        .ok_or_else(|| format!("`{name}` must be a table, found {}", value.type_name()))
+}
+
+struct Fields<'t>(&'t Table);
+
+impl Fields<'_> {
+    fn string(&self, key: &str) -> Result<Option<&str>, String> {
+        match self.0.get(key) {
+            None => Ok(None),
+            Some(Value::String(x)) => Ok(Some(x)),
+            Some(_) => Err(format!("`{key}` must be a string")),
+        }
+    }
+    fn bool(&self, key: &str) -> Result<Option<bool>, String> {
+        match self.0.get(key) {
+            None => Ok(None),
+            Some(Value::Boolean(x)) => Ok(Some(*x)),
+            Some(_) => Err(format!("`{key}` must be a boolean")),
+        }
+    }
+    fn unsigned(&self, key: &str) -> Result<Option<usize>, String> {
+        match self.0.get(key) {
+            None => Ok(None),
+            Some(Value::Integer(x)) if *x >= 0 => Ok(Some(*x as usize)),
+            Some(_) => Err(format!("`{key}` must be a non-negative integer")),
+        }
+    }
+    fn string_list(&self, key: &str) -> Result<Vec<String>, String> {
+        let Some(value) = self.0.get(key) else {
+            return Ok(Vec::new());
+        };
+        let error = || format!("`{key}` must be a list of strings");
+        value
+            .as_array()
+            .ok_or_else(error)?
+            .iter()
+            .map(|x| x.as_str().map(String::from).ok_or_else(error))
+            .collect()
+    }
+    /// An array of arrays of strings.
+    fn string_lists(&self, key: &str) -> Result<Vec<Vec<String>>, String> {
+        let Some(value) = self.0.get(key) else {
+            return Ok(Vec::new());
+        };
+        let error =
+            || format!("`{key}` must be a list of lists of strings, like `[[\"a\", \"b c\"]]`");
+        value
+            .as_array()
+            .ok_or_else(error)?
+            .iter()
+            .map(|x| {
+                x.as_array()
+                    .ok_or_else(error)?
+                    .iter()
+                    .map(|x| x.as_str().map(String::from).ok_or_else(error))
+                    .collect()
+            })
+            .collect()
+    }
+    /// A TOML date-time, or a string `datetime::parse` understands.
+    fn datetime(&self, key: &str) -> Result<Option<u64>, String> {
+        let text = match self.0.get(key) {
+            None => return Ok(None),
+            Some(Value::String(x) | Value::Datetime(x)) => x,
+            Some(_) => return Err(format!("`{key}` must be a date-time")),
+        };
+        match datetime::parse(text) {
+            Some(x) => Ok(Some(x)),
+            None => Err(format!(
+                "`{key}` must look like `2026-10-20 23:59` or `2026-10-20T23:59:00+03:00`, found `{text}`"
+            )),
+        }
+    }
+    /// A table of check names to non-negative integers.
+    fn points(&self, key: &str) -> Result<Vec<(String, u64)>, String> {
+        let points = self.unsigned_table(key)?;
+        let names: Vec<String> = points.iter().map(|x| x.0.clone()).collect();
+        check_names(key, &names)?;
+        Ok(points)
+    }
+    /// A table of non-negative integers.
+    fn unsigned_table(&self, key: &str) -> Result<Vec<(String, u64)>, String> {
+        let Some(value) = self.0.get(key) else {
+            return Ok(Vec::new());
+        };
+        as_table(key, value)?
+            .iter()
+            .map(|(name, value)| match value {
+                Value::Integer(x) if *x >= 0 => Ok((name.clone(), *x as u64)),
+                _ => Err(format!("`{key}.{name}` must be a non-negative integer")),
+            })
+            .collect()
+    }
+    /// A table of strings.
+    fn string_table(&self, key: &str) -> Result<Vec<(String, String)>, String> {
+        let Some(value) = self.0.get(key) else {
+            return Ok(Vec::new());
+        };
+        as_table(key, value)?
+            .iter()
+            .map(|(name, value)| match value.as_str() {
+                Some(x) => Ok((name.clone(), x.to_string())),
+                None => Err(format!("`{key}.{name}` must be a string")),
+            })
+            .collect()
+    }
+    /// A table of check names to strings.
+    fn urls(&self, key: &str) -> Result<Vec<(String, String)>, String> {
+        let urls = self.string_table(key)?;
+        let names: Vec<String> = urls.iter().map(|x| x.0.clone()).collect();
+        check_names(key, &names)?;
+        Ok(urls)
+    }
+    fn check_list(&self, key: &str) -> Result<Vec<String>, String> {
+        let names = self.string_list(key)?;
+        check_names(key, &names)?;
+        Ok(names)
+    }
+}
+
+fn check_names(key: &str, names: &[String]) -> Result<(), String> {
+    if let Some(x) = names.iter().find(|x| !CHECKS.iter().any(|c| c.name == *x)) {
+        let known: Vec<&str> = CHECKS.iter().map(|x| x.name).collect();
+        return Err(format!(
+            "`{key}` names unknown check `{x}`; known checks: {}",
+            known.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    /// Loads `text` as the config, for `lab`.
+    fn load_text(name: &str, text: &str, lab: &str) -> Result<LabConfig, String> {
+        let dir = TempDir::new(name).unwrap();
+        let path = dir.path().join("course.toml");
+        fs::write(&path, text).unwrap();
+        load(&path, lab, None)
+    }
+
+    #[test]
+    fn lab_metadata() {
+        let text = r#"
+[labs.lab03]
+title = "Traits"
+week = 4
+tags = ["traits", "generics"]
+statement_url = "https://example.com/lab03"
+"#;
+        let config = load_text("config_metadata", text, "lab03").unwrap();
+        assert_eq!(config.metadata.title.as_deref(), Some("Traits"));
+        assert_eq!(config.metadata.week, Some(4));
+        assert_eq!(config.metadata.tags, ["traits", "generics"]);
+        assert_eq!(
+            config.metadata.header("lab03").unwrap(),
+            "lab03: Traits (week 4) [traits, generics]\nhttps://example.com/lab03"
+        );
+    }
+
+    #[test]
+    fn missing_metadata_is_left_out() {
+        let config = load_text("config_no_metadata", "[defaults]\n", "lab01").unwrap();
+        assert!(config.metadata.header("lab01").is_none());
+        assert!(config.metadata.tags.is_empty());
+        assert!(config.metadata.statement_url.is_none());
+    }
+
+    #[test]
+    fn unknown_keys_are_tolerated() {
+        let text = r#"
+[defaults]
+a_setting_from_a_newer_checker = true
+
+[labs.lab01]
+week = 2
+also_unknown = { nested = 1 }
+"#;
+        let config = load_text("config_unknown_keys", text, "lab01").unwrap();
+        assert_eq!(config.metadata.week, Some(2));
+    }
+
+    #[test]
+    fn wrong_types_are_errors() {
+        let e = load_text(
+            "config_wrong_type",
+            "[labs.lab01]\nweek = \"four\"\n",
+            "lab01",
+        )
+        .err()
+        .unwrap();
+        assert!(e.contains("`week`"), "{e}");
+    }
+
+    #[test]
+    fn argument_templates_are_lists() {
+        let text = r#"
+[defaults]
+argument_templates = [[], ["input.txt"], ["--name", "Ana Maria"]]
+extra_args_timeout = 20
+"#;
+        let config = load_text("config_argument_templates", text, "lab01").unwrap();
+        let expected: [&[&str]; 3] = [&[], &["input.txt"], &["--name", "Ana Maria"]];
+        assert_eq!(config.argument_templates, expected);
+        assert_eq!(config.extra_args_timeout, 20);
+        assert_eq!(config.stdin_eof_timeout, 5);
+
+        let text = "[defaults]\nargument_templates = [\"--name Ana\"]\n";
+        let e = load_text("config_argument_strings", text, "lab01")
+            .err()
+            .unwrap();
+        assert!(
+            e.contains("`argument_templates` must be a list of lists"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn labs_override_the_defaults() {
+        let text = r#"
+[defaults]
+title = "Default"
+tags = ["a"]
+
+[labs.lab02]
+title = "Second"
+"#;
+        let config = load_text("config_override", text, "lab02").unwrap();
+        assert_eq!(config.metadata.title.as_deref(), Some("Second"));
+        assert_eq!(config.metadata.tags, ["a"]);
+        let other = load_text("config_override_other", text, "lab09").unwrap();
+        assert_eq!(other.metadata.title.as_deref(), Some("Default"));
+    }
+
+    #[test]
+    fn rubrics_set_the_grading_policy() {
+        let text = r#"
+[defaults]
+rubric = "standard"
+late_penalty_percent = 10
+
+[defaults.points]
+tests = 5
+clippy = 2
+
+[labs.lab04]
+rubric = "honors"
+
+[rubrics.honors]
+late_penalty_percent = 20
+
+[rubrics.honors.points]
+tests = 10
+
+[rubrics.honors.test_points]
+"tests::hard_case" = 4
+"#;
+        let honors = load_text("config_rubric_honors", text, "lab04").unwrap();
+        assert_eq!(honors.grading.late_penalty_percent, 20);
+        let points = [("clippy".to_string(), 2), ("tests".to_string(), 10)];
+        assert_eq!(honors.grading.points, points);
+        let test_points = [("tests::hard_case".to_string(), 4)];
+        assert_eq!(honors.grading.test_points, test_points);
+
+        let e = load_text("config_rubric_unknown", text, "lab01")
+            .err()
+            .unwrap();
+        assert!(e.contains("unknown rubric `standard`"), "{e}");
+
+        let text = "[defaults]\nrubric = \"a\"\n\n[rubrics.a]\nmax_line_length = 80\n";
+        let e = load_text("config_rubric_key", text, "lab01").err().unwrap();
+        assert!(
+            e.contains("`rubrics.a.max_line_length` isn't a grading setting"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn rubrics_without_tables_are_labels() {
+        let text = "[defaults]\nrubric = \"2024\"\n\n[defaults.points]\ntests = 5\n";
+        let config = load_text("config_rubric_label", text, "lab01").unwrap();
+        assert_eq!(config.rubric.as_deref(), Some("2024"));
+        assert_eq!(config.grading.points, [("tests".to_string(), 5)]);
+    }
+}
@@ -0,0 +1,89 @@
+//! UTC timestamps, in seconds since the Unix epoch, and the few text forms the checker uses.
+
+const DAY: u64 = 86400;
+
+/// Days since the epoch of a proleptic Gregorian date; Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// The inverse of `days_from_civil`.
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (yoe + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// `2026-10-14 09:30 UTC`.
+pub fn utc(at: u64) -> String {
+    let (year, month, day) = civil_from_days((at / DAY) as i64);
+    let seconds = at % DAY;
+    format!(
+        "{year}-{month:02}-{day:02} {:02}:{:02} UTC",
+        seconds / 3600,
+        seconds % 3600 / 60
+    )
+}
+
+fn number(text: &str, digits: usize) -> Option<i64> {
+    match text.len() == digits && text.bytes().all(|x| x.is_ascii_digit()) {
+        true => text.parse().ok(),
+        false => None,
+    }
+}
+
+/// Parses `2026-10-20`, `2026-10-20 23:59`, or an RFC 3339 time like `2026-10-20T23:59:00+03:00`.
+/// Times without an offset are UTC, and a date alone means the end of that day.
+pub fn parse(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (date, time) = match text.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (text, None),
+    };
+    let mut date = date.split('-');
+    let year = number(date.next()?, 4)?;
+    let month = number(date.next()?, 2)?;
+    let day = number(date.next()?, 2)?;
+    if date.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+
+    let Some(time) = time else {
+        return u64::try_from(days * DAY as i64 + DAY as i64 - 1).ok();
+    };
+    let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(i) => time.split_at(i),
+        None => (time, ""),
+    };
+    let offset = match offset {
+        "" | "Z" | "z" => 0,
+        x => {
+            let sign = if x.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = x[1..].split_once(':')?;
+            sign * (number(hours, 2)? * 3600 + number(minutes, 2)? * 60)
+        }
+    };
+    // Fractions of a second don't matter here.
+    let time = time.split('.').next()?;
+    let mut time = time.split(':');
+    let hours = number(time.next()?, 2)?;
+    let minutes = number(time.next()?, 2)?;
+    let seconds = time.next().map_or(Some(0), |x| number(x, 2))?;
+    if time.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    u64::try_from(days * DAY as i64 + hours * 3600 + minutes * 60 + seconds - offset).ok()
+}
@@ -0,0 +1,42 @@
+//! Short excerpts of how two texts differ, for problems that quote files.
+
+const CONTEXT_LINES: usize = 1;
+
+/// The changed region between `old` and `new`: the lines from the first difference to the last,
+/// with `-` for `old` and `+` for `new`, and a line of context around them. At most
+/// `max_lines` lines of each side are kept.
+///
+/// Everything between the first and last changed line counts as changed, which keeps this
+/// linear in the size of the files at the cost of showing some unchanged lines as changed.
+pub fn excerpt(old: &str, new: &str, max_lines: usize) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut out = Vec::new();
+    let context_start = prefix.saturating_sub(CONTEXT_LINES);
+    out.extend(old[context_start..prefix].iter().map(|x| format!(" {x}")));
+    for (sign, lines) in [('-', &old), ('+', &new)] {
+        let changed = &lines[prefix..lines.len() - suffix];
+        out.extend(changed.iter().take(max_lines).map(|x| format!("{sign}{x}")));
+        if changed.len() > max_lines {
+            out.push(format!(
+                "{sign}..and {} more lines",
+                changed.len() - max_lines
+            ));
+        }
+    }
+    let context_end = (old.len() - suffix + CONTEXT_LINES).min(old.len());
+    out.extend(
+        old[old.len() - suffix..context_end]
+            .iter()
+            .map(|x| format!(" {x}")),
+    );
+    out.join("\n")
+}
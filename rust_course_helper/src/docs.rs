@@ -0,0 +1,126 @@
+//! Links from problems to the course's pages about them.
+//!
+//! A problem's code is the name of the check that found it. Its page is the config's override
+//! for the code, or else `docs_base_url` with the code in place of `{code}`, or after it when
+//! there's no placeholder. With `documented_checks`, only the codes listed there get a page
+//! from the base URL, so the others don't link to missing pages.
+
+use crate::config::DocsLinks;
+use std::env;
+
+/// The code as a URL path segment.
+fn slug(code: &str) -> String {
+    let mut slug = String::new();
+    for byte in code.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                slug.push(byte as char)
+            }
+            _ => slug += &format!("%{byte:02X}"),
+        }
+    }
+    slug
+}
+
+impl DocsLinks {
+    pub fn url(&self, code: &str) -> Option<String> {
+        if let Some(x) = self.overrides.iter().find(|x| x.0 == code) {
+            return Some(x.1.clone());
+        }
+        let base = self.base_url.as_deref()?;
+        if !self.documented.is_empty() && !self.documented.iter().any(|x| x == code) {
+            return None;
+        }
+        match base.contains("{code}") {
+            true => Some(base.replace("{code}", &slug(code))),
+            false => Some(format!("{}/{}", base.trim_end_matches('/'), slug(code))),
+        }
+    }
+}
+
+/// Whether the human-readable output goes to a terminal that shows OSC 8 hyperlinks. Most
+/// current ones do, but there's no way to ask, so this goes by what's known to.
+pub fn hyperlinks_supported() -> bool {
+    if !crate::output::is_terminal() || !colored::control::SHOULD_COLORIZE.should_colorize() {
+        return false;
+    }
+    [
+        "WT_SESSION",
+        "VTE_VERSION",
+        "KITTY_WINDOW_ID",
+        "WEZTERM_EXECUTABLE",
+    ]
+    .iter()
+    .any(|x| env::var_os(x).is_some())
+        || env::var("TERM_PROGRAM")
+            .is_ok_and(|x| matches!(x.as_str(), "iTerm.app" | "vscode" | "WezTerm" | "ghostty"))
+}
+
+/// `text` linking to `url`, for terminals that support it.
+pub fn hyperlink(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{url}\x1b\\{text}\x1b]8;;\x1b\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(base_url: Option<&str>) -> DocsLinks {
+        DocsLinks {
+            base_url: base_url.map(String::from),
+            ..DocsLinks::default()
+        }
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        let links = links(Some("https://course.example/checks/{code}.html#{code}"));
+        assert_eq!(
+            links.url("line_length").unwrap(),
+            "https://course.example/checks/line_length.html#line_length"
+        );
+    }
+
+    #[test]
+    fn codes_go_after_a_base_without_a_placeholder() {
+        for base in [
+            "https://course.example/checks",
+            "https://course.example/checks/",
+        ] {
+            let url = links(Some(base)).url("clippy");
+            assert_eq!(url.unwrap(), "https://course.example/checks/clippy");
+        }
+    }
+
+    #[test]
+    fn codes_are_escaped() {
+        let links = links(Some("https://course.example/{code}"));
+        assert_eq!(
+            links.url("a b/c?é").unwrap(),
+            "https://course.example/a%20b%2Fc%3F%C3%A9"
+        );
+        assert_eq!(
+            links.url("a-b.c~d").unwrap(),
+            "https://course.example/a-b.c~d"
+        );
+    }
+
+    #[test]
+    fn overrides_win_and_documented_checks_limit_the_base() {
+        let mut links = links(Some("https://course.example/{code}"));
+        links.overrides = vec![("fmt".into(), "https://rustfmt.example/{code}".into())];
+        links.documented = vec!["clippy".into()];
+        // Overrides are used as they are.
+        assert_eq!(links.url("fmt").unwrap(), "https://rustfmt.example/{code}");
+        assert_eq!(
+            links.url("clippy").unwrap(),
+            "https://course.example/clippy"
+        );
+        assert_eq!(links.url("tests"), None);
+    }
+
+    #[test]
+    fn no_base_no_links() {
+        assert_eq!(links(None).url("clippy"), None);
+    }
+}
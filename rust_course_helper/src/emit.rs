@@ -0,0 +1,159 @@
+//! `--emit`: several reports on stdout, one after the other, and `split-emit` to take them
+//! apart again.
+//!
+//! Each report is a section:
+//!
+//! ```text
+//! -----BEGIN CHECKER JSON 1234-----
+//! <exactly 1234 bytes>
+//! -----END CHECKER JSON-----
+//! ```
+//!
+//! The length is what delimits the body, so a report that happens to contain marker-like lines
+//! can't end its section early. The end marker is only there to catch truncated streams.
+
+use camino::Utf8Path;
+use std::fs;
+use std::io::{self, Write};
+
+const BEGIN: &str = "-----BEGIN CHECKER ";
+const END: &str = "-----END CHECKER ";
+const DASHES: &str = "-----";
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EmitFormat {
+    Json,
+}
+
+impl EmitFormat {
+    fn section_name(self) -> &'static str {
+        match self {
+            EmitFormat::Json => "JSON",
+        }
+    }
+}
+
+pub fn write_section(out: &mut impl Write, format: EmitFormat, body: &str) -> io::Result<()> {
+    let name = format.section_name();
+    write!(
+        out,
+        "{BEGIN}{name} {}{DASHES}\n{body}\n{END}{name}{DASHES}\n",
+        body.len()
+    )
+}
+
+pub struct Section<'a> {
+    pub name: &'a str,
+    pub body: &'a [u8],
+}
+
+fn read_line<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let end = input.iter().position(|&x| x == b'\n')?;
+    let line = &input[..end];
+    *input = &input[end + 1..];
+    Some(line)
+}
+
+pub fn split(mut input: &[u8]) -> Result<Vec<Section<'_>>, String> {
+    let mut sections = Vec::new();
+    while !input.is_empty() {
+        let header =
+            read_line(&mut input).ok_or("stream ends in the middle of a section header")?;
+        let header = std::str::from_utf8(header)
+            .ok()
+            .and_then(|x| x.strip_prefix(BEGIN)?.strip_suffix(DASHES))
+            .ok_or("expected a `-----BEGIN CHECKER` line")?;
+        let (name, len) = header
+            .split_once(' ')
+            .ok_or("section header has no length")?;
+        let len: usize = len
+            .parse()
+            .map_err(|_| format!("section `{name}` has a bad length `{len}`"))?;
+        if input.len() < len {
+            return Err(format!("section `{name}` is truncated"));
+        }
+        let (body, rest) = input.split_at(len);
+        input = rest;
+
+        let end = format!("\n{END}{name}{DASHES}\n");
+        input = input
+            .strip_prefix(end.as_bytes())
+            .ok_or_else(|| format!("section `{name}` doesn't end where its length says"))?;
+        sections.push(Section { name, body });
+    }
+    Ok(sections)
+}
+
+/// `split-emit`: prints one section's body, or writes every section to `out_dir`.
+pub fn split_emit(
+    input: Option<&Utf8Path>,
+    section: Option<&str>,
+    out_dir: Option<&Utf8Path>,
+) -> Result<(), String> {
+    let data = match input {
+        Some(path) => fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?,
+        None => {
+            let mut buf = Vec::new();
+            io::Read::read_to_end(&mut io::stdin(), &mut buf)
+                .map_err(|e| format!("can't read stdin: {e}"))?;
+            buf
+        }
+    };
+    let sections = split(&data)?;
+
+    if let Some(wanted) = section {
+        let found = sections
+            .iter()
+            .find(|x| x.name.eq_ignore_ascii_case(wanted))
+            .ok_or_else(|| format!("no `{wanted}` section in the stream"))?;
+        let _ = crate::output::Sink::stdout().write_all(found.body);
+        return Ok(());
+    }
+
+    if let Some(dir) = out_dir {
+        fs::create_dir_all(dir).map_err(|e| format!("can't create {dir}: {e}"))?;
+        for x in &sections {
+            let path = dir.join(format!("checker.{}", x.name.to_ascii_lowercase()));
+            fs::write(&path, x.body).map_err(|e| format!("can't write {path}: {e}"))?;
+            say!("wrote {path}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_round_trip() {
+        // A body with marker-like lines and no final newline.
+        let bodies = ["{\"a\": 1}", "-----END CHECKER JSON-----\nx", ""];
+        let mut stream = Vec::new();
+        for body in bodies {
+            write_section(&mut stream, EmitFormat::Json, body).unwrap();
+        }
+        let sections = split(&stream).unwrap();
+        assert_eq!(sections.len(), bodies.len());
+        for (section, body) in sections.iter().zip(bodies) {
+            assert_eq!(section.name, "JSON");
+            assert_eq!(section.body, body.as_bytes());
+        }
+    }
+
+    #[test]
+    fn truncated_streams_are_errors() {
+        let mut stream = Vec::new();
+        write_section(&mut stream, EmitFormat::Json, "{\"a\": 1}").unwrap();
+        for len in 1..stream.len() {
+            assert!(split(&stream[..len]).is_err(), "cut at {len}");
+        }
+    }
+
+    #[test]
+    fn wrong_lengths_are_errors() {
+        let stream = b"-----BEGIN CHECKER JSON 3-----\n{}\n-----END CHECKER JSON-----\n";
+        assert!(split(stream).is_err());
+        assert!(split(b"not a section\n").is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Running other programs.
+//!
+//! Every command the checker runs goes through `Exec`, so capturing output, timeouts and
+//! resource usage work the same way for all of them. Recording commands for reports is up to
+//! the caller, since only the ones checks run are reported.
+
+use crate::capture::{self, CAPTURE_LIMIT, Captured};
+use crate::usage::{self, Usage};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::io;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// What happens to one of the child's output streams.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    /// Kept, up to the limit.
+    Capture,
+    Discard,
+    /// Goes wherever the checker's own stream goes.
+    Inherit,
+}
+
+impl Stream {
+    fn stdio(self) -> Stdio {
+        match self {
+            Stream::Capture => Stdio::piped(),
+            Stream::Discard => Stdio::null(),
+            Stream::Inherit => Stdio::inherit(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Exec {
+    program: String,
+    args: Vec<String>,
+    cwd: Option<Utf8PathBuf>,
+    /// Variables to set, or to remove when `None`.
+    env: Vec<(String, Option<String>)>,
+    /// Variables from the course config, set after `env`.
+    config_env: Vec<(String, String)>,
+    stdin_null: bool,
+    stdout: Stream,
+    stderr: Stream,
+    limit: usize,
+    tee: bool,
+    timeout: Option<Duration>,
+    description: Option<String>,
+}
+
+pub struct ExecResult {
+    pub status: ExitStatus,
+    /// Killed because it ran longer than its timeout.
+    pub timed_out: bool,
+    pub stdout: Captured,
+    pub stderr: Captured,
+    pub duration: Duration,
+    pub usage: Option<Usage>,
+}
+
+impl ExecResult {
+    pub fn success(&self) -> bool {
+        !self.timed_out && self.status.success()
+    }
+}
+
+impl Exec {
+    /// Inherits stdin and captures stdout and stderr, like `Command::output` but bounded.
+    pub fn new(program: impl Into<String>) -> Exec {
+        Exec {
+            program: program.into(),
+            args: Vec::new(),
+            cwd: None,
+            env: Vec::new(),
+            config_env: Vec::new(),
+            stdin_null: false,
+            stdout: Stream::Capture,
+            stderr: Stream::Capture,
+            limit: CAPTURE_LIMIT,
+            tee: false,
+            timeout: None,
+            description: None,
+        }
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Exec {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Exec
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn cwd(mut self, dir: &Utf8Path) -> Exec {
+        self.cwd = Some(dir.to_path_buf());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Exec {
+        self.env.push((key.into(), Some(value.into())));
+        self
+    }
+
+    pub fn env_remove(mut self, key: &str) -> Exec {
+        self.env.push((key.into(), None));
+        self
+    }
+
+    /// Sets variables from the course config. They're set last, so they win over the
+    /// command's own settings, and `describe` shows them.
+    pub fn config_env(mut self, vars: &[(String, String)]) -> Exec {
+        self.config_env.extend_from_slice(vars);
+        self
+    }
+
+    /// Whether the command sets or removes `key` itself.
+    pub fn sets(&self, key: &str) -> bool {
+        self.env.iter().any(|x| x.0 == key)
+    }
+
+    pub fn stdin_null(mut self) -> Exec {
+        self.stdin_null = true;
+        self
+    }
+
+    pub fn stdout(mut self, stream: Stream) -> Exec {
+        self.stdout = stream;
+        self
+    }
+
+    pub fn stderr(mut self, stream: Stream) -> Exec {
+        self.stderr = stream;
+        self
+    }
+
+    /// How much of the start and of the end of each captured stream is kept.
+    pub fn limit(mut self, limit: usize) -> Exec {
+        self.limit = limit;
+        self
+    }
+
+    /// Also shows the captured streams with the human-readable output as they arrive.
+    pub fn tee(mut self, tee: bool) -> Exec {
+        self.tee = tee;
+        self
+    }
+
+    /// Kills the command if it's still running after `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Exec {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// How the command is shown in reports, instead of its full command line.
+    pub fn description(mut self, description: impl Into<String>) -> Exec {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// The description, or the command line.
+    pub fn describe(&self) -> String {
+        let mut text = String::new();
+        for (key, value) in &self.config_env {
+            text += &format!("{key}={value} ");
+        }
+        if let Some(x) = &self.description {
+            return text + x;
+        }
+        text += &self.program;
+        for x in &self.args {
+            text.push(' ');
+            text += x;
+        }
+        text
+    }
+
+    /// Starts the command without waiting for it.
+    pub fn spawn(&self) -> io::Result<Child> {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args)
+            .stdout(self.stdout.stdio())
+            .stderr(self.stderr.stdio());
+        if let Some(dir) = &self.cwd {
+            cmd.current_dir(dir);
+        }
+        for (key, value) in &self.env {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        cmd.envs(self.config_env.iter().map(|(k, v)| (k, v)));
+        if self.stdin_null {
+            cmd.stdin(Stdio::null());
+        }
+        cmd.spawn()
+    }
+
+    /// Runs the command to the end, or until its timeout.
+    pub fn run(&self) -> io::Result<ExecResult> {
+        let start = Instant::now();
+        let mut child = self.spawn()?;
+        let sink = || self.tee.then(capture::human_sink);
+        let stdout = capture::read(child.stdout.take(), self.limit, sink());
+        let stderr = capture::read(child.stderr.take(), self.limit, sink());
+
+        let (status, usage, timed_out) = match self.timeout {
+            None => {
+                let (status, usage) = usage::wait(&mut child)?;
+                (status, usage, false)
+            }
+            Some(timeout) => match usage::wait_timeout(&mut child, timeout) {
+                Ok(Some((status, usage))) => (status, usage, false),
+                waited => {
+                    let _ = child.kill();
+                    let (status, usage) = usage::wait(&mut child)?;
+                    waited?;
+                    (status, usage, true)
+                }
+            },
+        };
+        Ok(ExecResult {
+            status,
+            timed_out,
+            stdout: capture::join(stdout)?,
+            stderr: capture::join(stderr)?,
+            duration: start.elapsed(),
+            usage,
+        })
+    }
+}
@@ -0,0 +1,136 @@
+//! `--expect`: checks that a run found exactly the problems it should, for testing the checker
+//! against repos that are broken on purpose.
+//!
+//! The expectations file lists the problems by check:
+//!
+//! ```toml
+//! [[expect]]
+//! check = "gitignore"
+//!
+//! [[expect]]
+//! check = "local_dependencies"
+//! path = "lab03/Cargo.toml"  # optional: one of the problem's paths ends with this
+//! severity = "error"         # optional
+//! count = 2                  # optional: exactly this many; at least one when missing
+//! ```
+//!
+//! Problems that no expectation matches are differences too.
+
+use crate::toml::{self, Value};
+use crate::{Diag, Diags, Severity};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+
+pub struct Expectation {
+    check: String,
+    severity: Option<Severity>,
+    path: Option<Utf8PathBuf>,
+    count: Option<usize>,
+}
+
+/// Problems found outside any check are reported under this name.
+const NO_CHECK: &str = "checker";
+
+pub fn load(path: &Utf8Path) -> Result<Vec<Expectation>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read expectations: {e}"))?;
+    let root = toml::parse(&text).map_err(|e| format!("can't parse expectations: {e}"))?;
+    let Some(entries) = root.get("expect") else {
+        return Ok(Vec::new());
+    };
+    let entries = entries
+        .as_array()
+        .ok_or("`expect` must be an array of tables, like `[[expect]]`")?;
+
+    let mut expectations = Vec::new();
+    for entry in entries {
+        let entry = entry.as_table().ok_or("`expect` entries must be tables")?;
+        let string = |key: &str| match entry.get(key) {
+            None => Ok(None),
+            Some(Value::String(x)) => Ok(Some(x.as_str())),
+            Some(_) => Err(format!("`{key}` must be a string")),
+        };
+        let check = string("check")?.ok_or("every `[[expect]]` needs a `check`")?;
+        let severity = match string("severity")? {
+            None => None,
+            Some("error") => Some(Severity::Error),
+            Some("warning") => Some(Severity::Warning),
+            Some(x) => {
+                return Err(format!(
+                    "`severity` must be `error` or `warning`, found `{x}`"
+                ));
+            }
+        };
+        let count = match entry.get("count") {
+            None => None,
+            Some(Value::Integer(x)) if *x >= 0 => Some(*x as usize),
+            Some(_) => return Err("`count` must be a non-negative integer".into()),
+        };
+        expectations.push(Expectation {
+            check: check.into(),
+            severity,
+            path: string("path")?.map(Utf8PathBuf::from),
+            count,
+        });
+    }
+    Ok(expectations)
+}
+
+impl Expectation {
+    fn matches(&self, problem: &Diag) -> bool {
+        problem.check.unwrap_or(NO_CHECK) == self.check
+            && self.severity.is_none_or(|x| x == problem.severity)
+            && self
+                .path
+                .as_ref()
+                .is_none_or(|path| problem.paths.iter().any(|x| x.ends_with(path)))
+    }
+
+    fn describe(&self) -> String {
+        let mut text = format!("`{}`", self.check);
+        if let Some(severity) = self.severity {
+            text += match severity {
+                Severity::Error => " error",
+                Severity::Warning => " warning",
+            };
+        }
+        if let Some(path) = &self.path {
+            text += &format!(" at {path}");
+        }
+        text
+    }
+}
+
+/// Everything that differs between the run and the expectations.
+pub fn differences(problems: &Diags, expectations: &[Expectation]) -> Vec<String> {
+    let mut result = Vec::new();
+    let mut matched = vec![false; problems.problems.len()];
+    for expectation in expectations {
+        let mut found = 0;
+        for (i, problem) in problems.problems.iter().enumerate() {
+            if expectation.matches(problem) {
+                matched[i] = true;
+                found += 1;
+            }
+        }
+        match expectation.count {
+            Some(count) if count != found => result.push(format!(
+                "expected {count} {} problems, found {found}",
+                expectation.describe()
+            )),
+            None if found == 0 => result.push(format!(
+                "expected a {} problem, found none",
+                expectation.describe()
+            )),
+            _ => {}
+        }
+    }
+
+    for (problem, _) in problems.problems.iter().zip(matched).filter(|x| !x.1) {
+        result.push(format!(
+            "unexpected `{}` problem: {}",
+            problem.check.unwrap_or(NO_CHECK),
+            problem.text.lines().next().unwrap_or_default()
+        ));
+    }
+    result
+}
@@ -0,0 +1,178 @@
+//! Mechanical fixes for problems, and `--apply-fixes`.
+//!
+//! Fixes are only ever built by our own check code. Paths from the student's repo may end up
+//! as arguments, but commands run without a shell, so they can't inject anything.
+
+use crate::checks::{CHECKS, Check};
+use crate::exec::{Exec, Stream};
+use crate::{CheckError, CheckResult, Context, run_check};
+use camino::Utf8PathBuf;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Fix {
+    /// Runs a command in `cwd`.
+    Run {
+        cwd: Utf8PathBuf,
+        program: &'static str,
+        args: Vec<String>,
+    },
+    /// Appends a line to a file, creating it if needed.
+    AppendLine { path: Utf8PathBuf, line: String },
+}
+
+impl Fix {
+    pub fn run<I, S>(cwd: Utf8PathBuf, program: &'static str, args: I) -> Fix
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Fix::Run {
+            cwd,
+            program,
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn apply(&self) -> Result<(), String> {
+        match self {
+            Fix::Run { cwd, program, args } => {
+                let output = Exec::new(*program)
+                    .args(args)
+                    .cwd(cwd)
+                    .stdout(Stream::Inherit)
+                    .stderr(Stream::Inherit)
+                    .run()
+                    .map_err(|e| format!("{program} failed: {e}"))?;
+                if !output.success() {
+                    return Err(format!("{program} failed: {}", output.status));
+                }
+                Ok(())
+            }
+            Fix::AppendLine { path, line } => {
+                let mut text = fs::read_to_string(path).unwrap_or_default();
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text += line.as_str();
+                text.push('\n');
+                fs::write(path, text).map_err(|e| format!("can't write {path}: {e}"))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fix::Run { program, args, .. } => write!(f, "run `{program} {}`", args.join(" ")),
+            Fix::AppendLine { path, line } => write!(f, "add `{line}` to {path}"),
+        }
+    }
+}
+
+fn confirm() -> bool {
+    say_inline!("apply these fixes? [y/N] ");
+    if io::stdout().flush().and(io::stderr().flush()).is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Applies the fixes collected during the run and re-runs the checks they came from.
+pub fn apply_fixes(ctx: &mut Context, yes: bool) -> CheckResult {
+    let fixable: Vec<_> = ctx
+        .problems
+        .problems
+        .iter()
+        .filter_map(|x| Some((x.check?, x.text.clone(), x.fix.clone()?, x.paths.clone())))
+        .collect();
+
+    let mut fixes: Vec<&Fix> = Vec::new();
+    for (_, _, fix, _) in &fixable {
+        if !fixes.contains(&fix) {
+            fixes.push(fix);
+        }
+    }
+    if fixes.is_empty() {
+        say!("no fixes available");
+        return run_result(ctx);
+    }
+
+    say!("fixes available:");
+    for (_, text, fix, _) in &fixable {
+        say!("  {fix} (for: {})", text.lines().next().unwrap_or_default());
+    }
+    if !yes && !confirm() {
+        return run_result(ctx);
+    }
+
+    for fix in fixes {
+        say!("applying: {fix}");
+        if let Err(e) = fix.apply() {
+            ctx.problems.add(format!("fix failed: {e}"), None, None);
+        }
+    }
+
+    // Read from the files the fixes changed.
+    ctx.parsed_sources = None;
+    ctx.tests = None;
+    ctx.lab_targets = None;
+
+    let rerun: BTreeSet<&str> = fixable.iter().map(|(check, ..)| *check).collect();
+    ctx.problems
+        .problems
+        .retain(|x| !x.check.is_some_and(|c| rerun.contains(&c)));
+    ctx.problems.artifacts.retain(|x| !rerun.contains(&x.check));
+    // In the plan's order, each once.
+    let mut left = rerun.clone();
+    let checks: Vec<&Check> = ctx
+        .problems
+        .plan
+        .iter()
+        .filter(|x| left.remove(**x))
+        .filter_map(|x| CHECKS.iter().find(|c| c.name == *x))
+        .collect();
+    for check in checks {
+        let start = Instant::now();
+        let passed = run_check(ctx, check).is_ok();
+        if let Some(status) = ctx
+            .problems
+            .checks
+            .iter_mut()
+            .find(|x| x.name == check.name)
+        {
+            status.passed = passed;
+            status.duration += start.elapsed();
+        }
+    }
+    ctx.problems.current_check = None;
+
+    // Messages often have counts in them, which a fix that only got part of the way changes.
+    for (check, text, fix, paths) in &fixable {
+        let resolved =
+            !ctx.problems.problems.iter().any(|x| {
+                x.check == Some(check) && (x.paths == *paths || x.fix.as_ref() == Some(fix))
+            });
+        let label = if resolved { "resolved" } else { "not resolved" };
+        say!("{label}: {}", text.lines().next().unwrap_or_default());
+    }
+
+    run_result(ctx)
+}
+
+fn run_result(ctx: &Context) -> CheckResult {
+    if ctx.problems.checks.iter().all(|x| x.passed) {
+        Ok(())
+    } else {
+        Err(CheckError)
+    }
+}
@@ -0,0 +1,196 @@
+//! `--track-flakiness`: finding checks that give different results for the same commit.
+//!
+//! Each check's outcome is kept in the state folder, per commit, but only when the lab folder
+//! has no uncommitted changes, since otherwise the commit doesn't say what was checked. A check
+//! that disagrees with an earlier run on the same commit is unstable, which usually means
+//! something changed on the machine rather than in the student's code.
+
+use crate::state::{self, OUTCOMES_FILE};
+use crate::toml::{self, Value};
+use crate::{CheckStatus, Context, clock, git};
+use camino::Utf8Path;
+use std::collections::BTreeMap;
+use std::fs;
+
+/// Bumped whenever the file's layout changes. Files with another version are ignored and
+/// replaced, since they only hold history.
+const FORMAT_VERSION: i64 = 1;
+/// How many commits are remembered, most recently checked first.
+const KEPT_COMMITS: usize = 20;
+/// How many outcomes are remembered for each check of a commit.
+const KEPT_OUTCOMES: usize = 10;
+
+#[derive(Clone, Copy)]
+pub struct Outcome {
+    pub passed: bool,
+    /// Seconds since the Unix epoch.
+    pub at: u64,
+}
+
+impl Outcome {
+    fn parse(text: &str) -> Option<Outcome> {
+        let (status, at) = text.split_once(' ')?;
+        let passed = match status {
+            "passed" => true,
+            "failed" => false,
+            _ => return None,
+        };
+        Some(Outcome {
+            passed,
+            at: at.parse().ok()?,
+        })
+    }
+
+    fn render(&self) -> String {
+        format!("{} {}", status(self.passed), self.at)
+    }
+}
+
+pub struct Unstable {
+    pub check: &'static str,
+    pub commit: String,
+    /// The latest earlier run that disagrees with this one.
+    pub earlier: Outcome,
+    pub now: Outcome,
+}
+
+/// For each commit, the outcomes of each check, oldest first.
+type History = BTreeMap<String, BTreeMap<String, Vec<Outcome>>>;
+
+pub fn status(passed: bool) -> &'static str {
+    match passed {
+        true => "passed",
+        false => "failed",
+    }
+}
+
+/// `None` when the file is from another format version.
+fn load(repo: &Utf8Path) -> Option<History> {
+    let path = state::state_path(repo, OUTCOMES_FILE);
+    let Ok(text) = fs::read_to_string(path) else {
+        return Some(History::new());
+    };
+    // Like the timings, a broken file only loses the history.
+    let Ok(table) = toml::parse(&text) else {
+        return Some(History::new());
+    };
+    if table.get("version") != Some(&Value::Integer(FORMAT_VERSION)) {
+        return None;
+    }
+    let commits = table.get("commits").and_then(Value::as_table);
+    let history = commits
+        .into_iter()
+        .flatten()
+        .map(|(commit, checks)| {
+            let checks = checks
+                .as_table()
+                .into_iter()
+                .flatten()
+                .map(|(name, outcomes)| {
+                    let outcomes = outcomes
+                        .as_array()
+                        .unwrap_or_default()
+                        .iter()
+                        .filter_map(|x| Outcome::parse(x.as_str()?))
+                        .collect();
+                    (name.clone(), outcomes)
+                })
+                .collect();
+            (commit.clone(), checks)
+        })
+        .collect();
+    Some(history)
+}
+
+fn save(repo: &Utf8Path, history: &History) -> Result<(), String> {
+    let mut text = format!(
+        "# Recent outcomes of each check, per commit, for `--track-flakiness`.\nversion = {FORMAT_VERSION}\n"
+    );
+    for (commit, checks) in history {
+        text += &format!("\n[commits.{commit:?}]\n");
+        for (name, outcomes) in checks {
+            let outcomes: Vec<String> = outcomes
+                .iter()
+                .map(|x| format!("{:?}", x.render()))
+                .collect();
+            text += &format!("{name} = [{}]\n", outcomes.join(", "));
+        }
+    }
+    let path = state::create_state_path(repo, OUTCOMES_FILE)?;
+    state::write_atomic(&path, &text)
+}
+
+/// Adds this run's outcomes to the history, and returns the checks that disagree with an
+/// earlier run on the same commit.
+fn record(history: &mut History, commit: &str, checks: &[CheckStatus], at: u64) -> Vec<Unstable> {
+    // Outcomes from the future were recorded by a clock that was wrong then, or is now. Either
+    // way their times can't be compared with this run's, and they'd never be pruned.
+    for checks in history.values_mut() {
+        for outcomes in checks.values_mut() {
+            outcomes.retain(|x| x.at <= at);
+        }
+        checks.retain(|_, x| !x.is_empty());
+    }
+    history.retain(|_, x| !x.is_empty());
+
+    let mut unstable = Vec::new();
+    let entry = history.entry(commit.to_string()).or_default();
+    for check in checks.iter().filter(|x| x.ran()) {
+        let now = Outcome {
+            passed: check.passed,
+            at,
+        };
+        let outcomes = entry.entry(check.name.to_string()).or_default();
+        if let Some(earlier) = outcomes.iter().rev().find(|x| x.passed != now.passed) {
+            unstable.push(Unstable {
+                check: check.name,
+                commit: commit.to_string(),
+                earlier: *earlier,
+                now,
+            });
+        }
+        outcomes.push(now);
+        let extra = outcomes.len().saturating_sub(KEPT_OUTCOMES);
+        outcomes.drain(..extra);
+    }
+
+    let last_checked = |checks: &BTreeMap<String, Vec<Outcome>>| {
+        checks.values().flatten().map(|x| x.at).max().unwrap_or(0)
+    };
+    let mut commits: Vec<(u64, String)> = history
+        .iter()
+        .map(|(commit, checks)| (last_checked(checks), commit.clone()))
+        .collect();
+    commits.sort_by(|a, b| b.cmp(a));
+    for (_, commit) in commits.into_iter().skip(KEPT_COMMITS) {
+        history.remove(&commit);
+    }
+    unstable
+}
+
+pub fn track(ctx: &mut Context) {
+    let Ok(commit) = git::head_commit(&ctx.repo_path) else {
+        say!("flakiness isn't tracked because the repo has no commits\n");
+        return;
+    };
+    let changes = git::git(
+        &ctx.repo_path,
+        &["status", "--porcelain", "--", ctx.lab_path.as_str()],
+    );
+    if !changes.is_ok_and(|x| x.trim().is_empty()) {
+        say!(
+            "flakiness isn't tracked for this run because the lab folder has uncommitted changes\n"
+        );
+        return;
+    }
+
+    let history = load(&ctx.repo_path);
+    if history.is_none() && ctx.verbose {
+        say!("the recorded check outcomes are from another checker version, so they were reset");
+    }
+    let mut history = history.unwrap_or_default();
+    ctx.problems.unstable = record(&mut history, &commit, &ctx.problems.checks, clock::now());
+    if let Err(e) = save(&ctx.repo_path, &history) {
+        ctx.problems.warn(e, None, None);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::exec::Exec;
+use camino::Utf8Path;
+use std::time::Duration;
+
+/// How long `git fsck` may take before the probe gives up on it.
+const FSCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs git in `repo` and returns its trimmed stdout, or a description of what went wrong.
+pub fn git(repo: &Utf8Path, args: &[&str]) -> Result<String, String> {
+    let output = Exec::new("git")
+        .args(args.iter().copied())
+        .cwd(repo)
+        .stdin_null()
+        .limit(usize::MAX)
+        .run()
+        .map_err(|e| format!("git failed: {e}"))?;
+    if !output.success() {
+        return Err(format!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            output.stderr.text().trim()
+        ));
+    }
+    Ok(output.stdout.text().trim().to_string())
+}
+
+pub fn head_commit(repo: &Utf8Path) -> Result<String, String> {
+    git(repo, &["rev-parse", "--verify", "HEAD"])
+}
+
+/// When HEAD was committed, in seconds since the Unix epoch.
+pub fn commit_time(repo: &Utf8Path) -> Result<u64, String> {
+    let time = git(repo, &["show", "-s", "--format=%ct", "HEAD"])?;
+    time.parse()
+        .map_err(|_| format!("git printed `{time}` as the commit time"))
+}
+
+/// The ID git gives `file`'s contents when they're added at `path`, relative to `repo`. It's
+/// what git stores, after `core.autocrlf` and `.gitattributes` have normalized line endings,
+/// so it's the same whichever line endings the checkout wrote. Every content comparison that
+/// has to agree with git goes through this.
+pub fn content_hash(repo: &Utf8Path, path: &Utf8Path, file: &Utf8Path) -> Result<String, String> {
+    let path = format!("--path={path}");
+    git(repo, &["hash-object", &path, "--", file.as_str()])
+}
+
+/// The settings that make checkouts rewrite line endings, like `core.autocrlf=true`.
+pub fn line_ending_settings(repo: &Utf8Path) -> Vec<String> {
+    ["core.autocrlf", "core.eol"]
+        .iter()
+        .filter_map(|key| {
+            let value = git(repo, &["config", "--get", key]).ok()?;
+            (!value.is_empty()).then(|| format!("{key}={value}"))
+        })
+        .collect()
+}
+
+/// Whether the repo's git data can be trusted by the checks that read it.
+#[derive(Clone)]
+pub enum Health {
+    Healthy,
+    NotARepo,
+    /// What git said was broken.
+    Corrupted(String),
+}
+
+/// Probes the repo with a few quick commands, and only if one of them fails, with `git fsck`
+/// to tell a broken repo from one that simply has no commits yet.
+pub fn probe(repo: &Utf8Path) -> Health {
+    if git(repo, &["rev-parse", "--git-dir"]).is_err() {
+        return Health::NotARepo;
+    }
+    let quick = git(repo, &["rev-parse", "--verify", "HEAD"])
+        .and_then(|_| git(repo, &["ls-tree", "-r", "--name-only", "HEAD"]))
+        .and_then(|_| git(repo, &["status", "--porcelain"]));
+    let Err(quick_error) = quick else {
+        return Health::Healthy;
+    };
+    match fsck(repo) {
+        Some(Ok(())) => Health::Healthy,
+        Some(Err(e)) => Health::Corrupted(e),
+        // fsck didn't finish, so the quick probe's error is all there is to go on.
+        None => Health::Corrupted(quick_error),
+    }
+}
+
+/// `None` if fsck couldn't run or didn't finish in time.
+fn fsck(repo: &Utf8Path) -> Option<Result<(), String>> {
+    let output = Exec::new("git")
+        .args(["fsck", "--no-progress", "--connectivity-only"])
+        .cwd(repo)
+        .stdin_null()
+        .timeout(FSCK_TIMEOUT)
+        .run()
+        .ok()?;
+    if output.timed_out {
+        return None;
+    }
+    if output.status.success() {
+        return Some(Ok(()));
+    }
+    let output = format!("{}\n{}", output.stdout.text(), output.stderr.text());
+    // The first few lines name the missing objects; the rest is more of the same.
+    let lines: Vec<&str> = output
+        .lines()
+        .filter(|x| !x.starts_with("dangling") && !x.starts_with("notice"))
+        .take(5)
+        .collect();
+    Some(Err(lines.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+    use camino::Utf8PathBuf;
+    use std::fs;
+
+    const LF: &str = "pub const DATA: &[u8] = &[\n    1, 2, 3,\n];\n";
+    const CRLF: &str = "pub const DATA: &[u8] = &[\r\n    1, 2, 3,\r\n];\r\n";
+
+    /// A repo with `attributes` as its `.gitattributes`, and a file written with CRLF line
+    /// endings, like a Windows checkout leaves them, and its LF version elsewhere.
+    fn checkout(name: &str, attributes: &str) -> (TempDir, Utf8PathBuf, Utf8PathBuf) {
+        let dir = TempDir::new(name).unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join("generated")).unwrap();
+        git(&repo, &["init", "-q"]).unwrap();
+        git(&repo, &["config", "core.autocrlf", "false"]).unwrap();
+        fs::write(repo.join(".gitattributes"), attributes).unwrap();
+        let crlf = repo.join("generated/data.rs");
+        fs::write(&crlf, CRLF).unwrap();
+        let lf = dir.path().join("data.rs");
+        fs::write(&lf, LF).unwrap();
+        (dir, repo, lf)
+    }
+
+    #[test]
+    fn line_endings_git_normalizes_hash_the_same() {
+        let (_dir, repo, lf) = checkout("git_crlf_text", "* text=auto\n");
+        let path = Utf8Path::new("generated/data.rs");
+        let committed = content_hash(&repo, path, &repo.join(path)).unwrap();
+        assert_eq!(committed, content_hash(&repo, path, &lf).unwrap());
+    }
+
+    #[test]
+    fn autocrlf_normalizes_too() {
+        let (_dir, repo, lf) = checkout("git_crlf_autocrlf", "");
+        git(&repo, &["config", "core.autocrlf", "true"]).unwrap();
+        let path = Utf8Path::new("generated/data.rs");
+        let committed = content_hash(&repo, path, &repo.join(path)).unwrap();
+        assert_eq!(committed, content_hash(&repo, path, &lf).unwrap());
+        assert_eq!(line_ending_settings(&repo), ["core.autocrlf=true"]);
+    }
+
+    #[test]
+    fn line_endings_of_binary_files_count() {
+        let (_dir, repo, lf) = checkout("git_crlf_binary", "*.rs -text\n");
+        let path = Utf8Path::new("generated/data.rs");
+        let committed = content_hash(&repo, path, &repo.join(path)).unwrap();
+        assert_ne!(committed, content_hash(&repo, path, &lf).unwrap());
+    }
+}
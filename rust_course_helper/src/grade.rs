@@ -0,0 +1,451 @@
+//! `grade`: the lab's score, from the checks' results and the config's grading policy.
+//!
+//! Each check in `points` is worth that many points when it passes or is waived. Skipping a
+//! check the lab doesn't need counts as passing it, but skipping one because another check
+//! failed, or because a tool is missing, doesn't. Each test in `test_points` is worth that
+//! many when `cargo test` ran it and it passed, or when the `tests` check is waived, so partial
+//! work earns partial credit. Work committed after the deadline loses
+//! `late_penalty_percent` of its points for each day it's late, counting started days.
+//!
+//! Checks that failed because of the toolchain or the machine earn nothing either, but they
+//! mark the grade as needing review instead of it standing as it is. So does a commit time
+//! that's far ahead of the machine's clock, since one of the clocks is wrong and the late days
+//! can't be trusted.
+
+use crate::config::GradingPolicy;
+use crate::json::Json;
+use crate::{CheckStatus, datetime};
+use camino::Utf8Path;
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+#[derive(Clone, Copy)]
+pub enum Output {
+    /// With the rest of the report.
+    Report,
+    /// As a CSV row for gradebooks.
+    Csv,
+}
+
+pub struct Item {
+    /// The check's name, or the test's.
+    pub check: String,
+    /// Whether it's for a test rather than a check.
+    pub test: bool,
+    pub points: u64,
+    pub earned: u64,
+    pub status: String,
+}
+
+pub struct Grade {
+    pub items: Vec<Item>,
+    pub earned: u64,
+    pub max: u64,
+    /// When HEAD was committed, if the repo has commits.
+    pub submitted_at: Option<u64>,
+    pub late_days: u64,
+    pub penalty_percent: u64,
+    /// `earned`, minus the late penalty.
+    pub score: f64,
+    /// Whether a check failed because of the toolchain or the machine, so the score can't be
+    /// trusted.
+    pub needs_review: bool,
+    /// How the commit time and the machine's clock disagree, if they do by too much.
+    pub clock_skew: Option<String>,
+}
+
+/// Only looks at its arguments, so a grade can be worked out again from a saved run.
+pub fn evaluate(
+    policy: &GradingPolicy,
+    checks: &[CheckStatus],
+    tests: &BTreeMap<String, bool>,
+    submitted_at: Option<u64>,
+    clock_skew: Option<String>,
+) -> Grade {
+    let mut items: Vec<Item> = policy
+        .points
+        .iter()
+        .map(|(check, points)| {
+            let status = checks.iter().find(|x| x.name == check);
+            let (earned, status) = if policy.waived_checks.contains(check) {
+                (true, "waived".to_string())
+            } else {
+                match status {
+                    None => (false, "not run".into()),
+                    Some(x) => match &x.skipped {
+                        Some(reason) => (reason.benign(), format!("skipped: {reason}")),
+                        None if x.passed => (true, "passed".into()),
+                        None if x.environment_failure => {
+                            (false, "failed because of the environment".into())
+                        }
+                        None => (false, "failed".into()),
+                    },
+                }
+            };
+            Item {
+                check: check.clone(),
+                test: false,
+                points: *points,
+                earned: if earned { *points } else { 0 },
+                status,
+            }
+        })
+        .collect();
+    let tests_waived = policy.waived_checks.iter().any(|x| x == "tests");
+    items.extend(policy.test_points.iter().map(|(test, points)| {
+        let (earned, status) = match tests.get(test) {
+            _ if tests_waived => (true, "waived"),
+            Some(true) => (true, "passed"),
+            Some(false) => (false, "failed"),
+            None => (false, "not run"),
+        };
+        Item {
+            check: test.clone(),
+            test: true,
+            points: *points,
+            earned: if earned { *points } else { 0 },
+            status: status.into(),
+        }
+    }));
+
+    let late_days = match (policy.deadline, submitted_at) {
+        (Some(deadline), Some(at)) if at > deadline => (at - deadline).div_ceil(86400),
+        _ => 0,
+    };
+    let penalty_percent = (late_days * policy.late_penalty_percent).min(100);
+    let earned = items.iter().map(|x| x.earned).sum();
+    Grade {
+        max: items.iter().map(|x| x.points).sum(),
+        items,
+        earned,
+        submitted_at,
+        late_days,
+        penalty_percent,
+        score: earned as f64 * (100 - penalty_percent) as f64 / 100.0,
+        needs_review: clock_skew.is_some() || checks.iter().any(|x| x.environment_failure),
+        clock_skew,
+    }
+}
+
+impl Grade {
+    pub fn print(&self) {
+        say!("grade:");
+        for x in &self.items {
+            let line = format!(
+                "  {:<24}{:>4} / {:<4}{}",
+                x.label(),
+                x.earned,
+                x.points,
+                x.status
+            );
+            match x.earned == x.points {
+                true => say!("{line}"),
+                false => say!("{}", line.yellow()),
+            }
+        }
+        if let Some(at) = self.submitted_at {
+            say!("submitted {}", datetime::utc(at));
+        }
+        if self.late_days > 0 {
+            let days = match self.late_days {
+                1 => "1 day".to_string(),
+                n => format!("{n} days"),
+            };
+            say!(
+                "{}",
+                format!("{days} late: -{}%", self.penalty_percent).yellow()
+            );
+        }
+        say!(
+            "{}: {} / {}",
+            "final grade".bold(),
+            format_score(self.score),
+            self.max
+        );
+        if let Some(skew) = &self.clock_skew {
+            say!("{}", format!("needs review: {skew}").yellow());
+        }
+        if self.needs_review && self.clock_skew.is_none() {
+            say!(
+                "{}",
+                "needs review: a check failed because of the toolchain or the machine, not the lab"
+                    .yellow()
+            );
+        }
+    }
+
+    pub fn json(&self) -> Json {
+        let items = self
+            .items
+            .iter()
+            .map(|x| {
+                Json::object([
+                    (
+                        if x.test { "test" } else { "check" },
+                        x.check.as_str().into(),
+                    ),
+                    ("points", x.points.into()),
+                    ("earned", x.earned.into()),
+                    ("status", x.status.as_str().into()),
+                ])
+            })
+            .collect();
+        Json::object([
+            ("score", self.score.into()),
+            ("max", self.max.into()),
+            ("earned", self.earned.into()),
+            ("submitted_at", self.submitted_at.into()),
+            ("late_days", self.late_days.into()),
+            ("penalty_percent", self.penalty_percent.into()),
+            ("needs_review", self.needs_review.into()),
+            ("clock_skew", self.clock_skew.as_deref().into()),
+            ("items", Json::Array(items)),
+        ])
+    }
+
+    /// A header and one row: the repo and lab, the totals, and the points from each check.
+    /// With `stable`, the repo is only its folder's name, which doesn't depend on where it was
+    /// checked out.
+    pub fn csv(&self, repo: &Utf8Path, lab: &str, stable: bool) -> String {
+        let mut header = vec![
+            "repo",
+            "lab",
+            "score",
+            "max",
+            "late_days",
+            "penalty_percent",
+            "needs_review",
+        ];
+        let labels: Vec<String> = self.items.iter().map(Item::label).collect();
+        header.extend(labels.iter().map(String::as_str));
+        let repo = match stable {
+            true => repo.file_name().unwrap_or(repo.as_str()),
+            false => repo.as_str(),
+        };
+        let mut row = vec![
+            repo.to_string(),
+            lab.to_string(),
+            format_score(self.score),
+            self.max.to_string(),
+            self.late_days.to_string(),
+            self.penalty_percent.to_string(),
+            self.needs_review.to_string(),
+        ];
+        row.extend(self.items.iter().map(|x| x.earned.to_string()));
+
+        let mut out = String::new();
+        for line in [
+            header.iter().map(|x| csv_field(x)).collect::<Vec<_>>(),
+            row.iter().map(|x| csv_field(x)).collect(),
+        ] {
+            writeln!(out, "{}", line.join(",")).expect("writing to a string");
+        }
+        out
+    }
+}
+
+impl Item {
+    /// Tests are told apart from checks with the same name.
+    fn label(&self) -> String {
+        match self.test {
+            true => format!("test {}", self.check),
+            false => self.check.clone(),
+        }
+    }
+}
+
+/// Whole scores without the `.0`.
+fn format_score(score: f64) -> String {
+    match score.fract() == 0.0 {
+        true => format!("{score:.0}"),
+        false => format!("{score:.2}"),
+    }
+}
+
+fn csv_field(x: &str) -> String {
+    match x.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", x.replace('"', "\"\"")),
+        false => x.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SkipReason;
+    use std::time::Duration;
+
+    const DAY: u64 = 86400;
+    const DEADLINE: u64 = 1_760_000_000;
+
+    fn policy(points: &[(&str, u64)]) -> GradingPolicy {
+        GradingPolicy {
+            points: points.iter().map(|(x, p)| (x.to_string(), *p)).collect(),
+            test_points: Vec::new(),
+            waived_checks: Vec::new(),
+            deadline: Some(DEADLINE),
+            late_penalty_percent: 10,
+        }
+    }
+
+    fn status(name: &'static str, passed: bool) -> CheckStatus {
+        CheckStatus {
+            name,
+            passed,
+            duration: Duration::ZERO,
+            partial: false,
+            skipped: None,
+            environment_failure: false,
+        }
+    }
+
+    fn skipped(name: &'static str, reason: SkipReason) -> CheckStatus {
+        CheckStatus {
+            skipped: Some(reason),
+            ..status(name, true)
+        }
+    }
+
+    fn grade(policy: &GradingPolicy, checks: &[CheckStatus]) -> Grade {
+        evaluate(policy, checks, &BTreeMap::new(), Some(DEADLINE), None)
+    }
+
+    fn earned(grade: &Grade) -> Vec<(&str, u64, &str)> {
+        let items = grade.items.iter();
+        items
+            .map(|x| (x.check.as_str(), x.earned, x.status.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn waived_checks_earn_their_points() {
+        let mut policy = policy(&[("clippy", 10), ("fmt", 5)]);
+        policy.waived_checks = vec!["clippy".into()];
+        let grade = grade(&policy, &[status("clippy", false), status("fmt", false)]);
+        assert_eq!(
+            earned(&grade),
+            [("clippy", 10, "waived"), ("fmt", 0, "failed")]
+        );
+        assert_eq!((grade.earned, grade.max, grade.score), (10, 15, 10.0));
+    }
+
+    #[test]
+    fn only_benign_skips_earn_points() {
+        let policy = policy(&[("api", 4), ("clippy", 10), ("publish", 2), ("tests", 6)]);
+        let checks = [
+            skipped("api", SkipReason::NotConfigured),
+            skipped("clippy", SkipReason::DependencyFailed("compile")),
+            skipped(
+                "publish",
+                SkipReason::NotApplicable("the lab isn't published"),
+            ),
+            skipped("tests", SkipReason::MissingTool("cargo".into())),
+        ];
+        let grade = grade(&policy, &checks);
+        let points: Vec<u64> = grade.items.iter().map(|x| x.earned).collect();
+        assert_eq!(points, [4, 0, 2, 0]);
+        assert_eq!(grade.items[1].status, "skipped: `compile` failed");
+        assert!(!grade.needs_review);
+    }
+
+    #[test]
+    fn checks_missing_from_the_run_earn_nothing() {
+        let grade = grade(&policy(&[("tests", 6)]), &[]);
+        assert_eq!(earned(&grade), [("tests", 0, "not run")]);
+    }
+
+    #[test]
+    fn environment_failures_need_review() {
+        let policy = policy(&[("compile", 10)]);
+        let failed = CheckStatus {
+            environment_failure: true,
+            ..status("compile", false)
+        };
+        let grade = grade(&policy, &[failed]);
+        assert_eq!(
+            earned(&grade),
+            [("compile", 0, "failed because of the environment")]
+        );
+        assert!(grade.needs_review);
+        assert!(grade.clock_skew.is_none());
+    }
+
+    #[test]
+    fn clock_skew_needs_review() {
+        let policy = policy(&[("compile", 10)]);
+        let grade = evaluate(
+            &policy,
+            &[status("compile", true)],
+            &BTreeMap::new(),
+            None,
+            Some("the clock is wrong".into()),
+        );
+        assert!(grade.needs_review);
+        assert_eq!(grade.score, 10.0);
+    }
+
+    #[test]
+    fn late_days_count_started_days() {
+        let policy = policy(&[("compile", 10)]);
+        let checks = [status("compile", true)];
+        let late = |at| evaluate(&policy, &checks, &BTreeMap::new(), Some(at), None);
+        for (at, days, score) in [
+            (DEADLINE - 1, 0, 10.0),
+            (DEADLINE, 0, 10.0),
+            (DEADLINE + 1, 1, 9.0),
+            (DEADLINE + DAY, 1, 9.0),
+            (DEADLINE + DAY + 1, 2, 8.0),
+        ] {
+            let grade = late(at);
+            assert_eq!((grade.late_days, grade.score), (days, score), "{at}");
+        }
+        let unknown = evaluate(&policy, &checks, &BTreeMap::new(), None, None);
+        assert_eq!(unknown.late_days, 0);
+    }
+
+    #[test]
+    fn the_penalty_stops_at_everything() {
+        let mut policy = policy(&[("compile", 10)]);
+        policy.late_penalty_percent = 40;
+        let checks = [status("compile", true)];
+        let at = Some(DEADLINE + 3 * DAY);
+        let grade = evaluate(&policy, &checks, &BTreeMap::new(), at, None);
+        assert_eq!((grade.late_days, grade.penalty_percent), (3, 100));
+        assert_eq!(grade.score, 0.0);
+    }
+
+    #[test]
+    fn tests_earn_partial_credit() {
+        let mut policy = policy(&[("tests", 2)]);
+        policy.test_points = vec![
+            ("tests::adds".into(), 3),
+            ("tests::parses".into(), 5),
+            ("tests::renamed".into(), 1),
+        ];
+        let tests = BTreeMap::from([
+            ("tests::adds".to_string(), true),
+            ("tests::parses".to_string(), false),
+            ("tests::extra".to_string(), true),
+        ]);
+        let checks = [status("tests", false)];
+        let grade = evaluate(&policy, &checks, &tests, None, None);
+        assert_eq!(
+            earned(&grade),
+            [
+                ("tests", 0, "failed"),
+                ("tests::adds", 3, "passed"),
+                ("tests::parses", 0, "failed"),
+                ("tests::renamed", 0, "not run"),
+            ]
+        );
+        assert_eq!((grade.earned, grade.max), (3, 11));
+        assert!(grade.csv(Utf8Path::new("repo"), "lab01", false).starts_with(
+            "repo,lab,score,max,late_days,penalty_percent,needs_review,tests,test tests::adds,"
+        ));
+
+        policy.waived_checks = vec!["tests".into()];
+        let waived = evaluate(&policy, &checks, &tests, None, None);
+        assert_eq!(waived.earned, waived.max);
+    }
+}
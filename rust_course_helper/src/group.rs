@@ -0,0 +1,123 @@
+//! Groups errors that share a file, so that one bad file doesn't bury everything else.
+
+use crate::{Diags, Severity};
+use camino::Utf8PathBuf;
+use std::collections::BTreeMap;
+
+/// A file involved in more than this many errors gets a summary of its own.
+const ROOT_CAUSE_THRESHOLD: usize = 2;
+
+pub struct RootCause {
+    pub path: Utf8PathBuf,
+    /// Indices into `Diags::problems`.
+    pub members: Vec<usize>,
+}
+
+impl Diags {
+    pub fn group_root_causes(&mut self) {
+        let mut by_path: BTreeMap<Utf8PathBuf, Vec<usize>> = BTreeMap::new();
+        for (i, problem) in self.problems.iter().enumerate() {
+            if problem.severity != Severity::Error {
+                continue;
+            }
+            // Most cargo failures point at the repo or lab folder; that's not a root cause.
+            for path in problem.paths.iter().filter(|x| !x.is_dir()) {
+                let members = by_path.entry(path.clone()).or_default();
+                if members.last() != Some(&i) {
+                    members.push(i);
+                }
+            }
+        }
+
+        let mut candidates: Vec<_> = by_path
+            .into_iter()
+            .filter(|(_, members)| members.len() > ROOT_CAUSE_THRESHOLD)
+            .collect();
+        if candidates.is_empty() {
+            return;
+        }
+        candidates.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(&b.0)));
+
+        for (path, members) in candidates {
+            // A problem can only be grouped once, under the file with the most problems.
+            let members: Vec<usize> = members
+                .into_iter()
+                .filter(|&i| self.problems[i].root_cause_group.is_none())
+                .collect();
+            if members.len() <= ROOT_CAUSE_THRESHOLD {
+                continue;
+            }
+            let id = self.root_causes.len();
+            for &i in &members {
+                self.problems[i].root_cause_group = Some(id);
+            }
+            self.root_causes.push(RootCause { path, members });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(name: &str) -> Utf8PathBuf {
+        Utf8PathBuf::from(format!("/nonexistent/{name}"))
+    }
+
+    fn diags(errors: &[&[&str]]) -> Diags {
+        let mut diags = Diags::default();
+        for paths in errors {
+            let paths: Vec<Utf8PathBuf> = paths.iter().map(|x| path(x)).collect();
+            diags.add("error", paths, None);
+        }
+        diags
+    }
+
+    #[test]
+    fn at_the_threshold_nothing_is_grouped() {
+        let mut diags = diags(&[&["a.rs"], &["a.rs"]]);
+        diags.group_root_causes();
+        assert!(diags.root_causes.is_empty());
+        assert!(diags.problems.iter().all(|x| x.root_cause_group.is_none()));
+    }
+
+    #[test]
+    fn over_the_threshold_the_file_is_a_root_cause() {
+        let mut diags = diags(&[&["a.rs"], &["a.rs"], &["a.rs"], &["b.rs"]]);
+        diags.group_root_causes();
+        assert_eq!(diags.root_causes.len(), 1);
+        assert_eq!(diags.root_causes[0].path, path("a.rs"));
+        assert_eq!(diags.root_causes[0].members, [0, 1, 2]);
+        assert_eq!(diags.problems[3].root_cause_group, None);
+    }
+
+    #[test]
+    fn warnings_dont_count() {
+        let mut diags = diags(&[&["a.rs"], &["a.rs"]]);
+        diags.warn("warning", path("a.rs"), None);
+        diags.group_root_causes();
+        assert!(diags.root_causes.is_empty());
+    }
+
+    #[test]
+    fn a_problem_with_several_paths_is_grouped_once() {
+        let mut diags = diags(&[
+            &["a.rs", "b.rs"],
+            &["a.rs", "b.rs"],
+            &["a.rs", "b.rs"],
+            &["b.rs"],
+        ]);
+        diags.group_root_causes();
+        // `b.rs` has the most problems, which leaves too few for `a.rs`.
+        assert_eq!(diags.root_causes.len(), 1);
+        assert_eq!(diags.root_causes[0].path, path("b.rs"));
+        assert_eq!(diags.root_causes[0].members, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn the_same_path_twice_in_one_problem_counts_once() {
+        let mut diags = diags(&[&["a.rs", "a.rs", "a.rs"]]);
+        diags.group_root_causes();
+        assert!(diags.root_causes.is_empty());
+    }
+}
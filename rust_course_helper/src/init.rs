@@ -0,0 +1,156 @@
+//! `init`: creates a lab folder that already passes the structural checks.
+
+use crate::checks::{CHECKS, Cost};
+use crate::config::{CrateType, LabConfig};
+use crate::fix::Fix;
+use crate::scope::Scope;
+use crate::{CheckResult, Context, Diags, git, run_plan, schedule};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs;
+
+/// The files to create, relative to the lab folder.
+fn lab_files(lab: &str, config: &LabConfig, repo: &Utf8Path) -> Vec<(Utf8PathBuf, String)> {
+    let mut authors = config.authors.clone();
+    if authors.is_empty()
+        && let Ok(name) = git::git(repo, &["config", "user.name"])
+    {
+        authors.push(match git::git(repo, &["config", "user.email"]) {
+            Ok(email) if !email.is_empty() => format!("{name} <{email}>"),
+            _ => name,
+        });
+    }
+
+    let mut manifest = format!(
+        "[package]\nname = \"{lab}\"\nversion = \"0.1.0\"\nedition = \"{}\"\n",
+        config.edition
+    );
+    if !authors.is_empty() {
+        let quoted: Vec<String> = authors.iter().map(|x| format!("{x:?}")).collect();
+        manifest += &format!("authors = [{}]\n", quoted.join(", "));
+    }
+    manifest += "\n[dependencies]\n";
+
+    let source = match config.crate_type {
+        CrateType::Bin => (
+            "src/main.rs",
+            "fn main() {\n    println!(\"Hello, world!\");\n}\n",
+        ),
+        CrateType::Lib => ("src/lib.rs", "// Your code goes here.\n"),
+    };
+    vec![
+        ("Cargo.toml".into(), manifest),
+        (source.0.into(), source.1.into()),
+    ]
+}
+
+/// Starter files, relative to `dir`.
+fn starter_files(dir: &Utf8Path) -> Result<Vec<(Utf8PathBuf, Vec<u8>)>, String> {
+    let mut files = Vec::new();
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(current) = dirs.pop() {
+        let entries = current
+            .read_dir_utf8()
+            .map_err(|e| format!("can't read starter files in {current}: {e}"))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("can't read {current}: {e}"))?;
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path.to_owned());
+                continue;
+            }
+            let data = fs::read(path).map_err(|e| format!("can't read {path}: {e}"))?;
+            let relative = path.strip_prefix(dir).expect("walked from dir");
+            files.push((relative.to_owned(), data));
+        }
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+pub fn init(
+    problems: &mut Diags,
+    repo: Utf8PathBuf,
+    lab: &str,
+    config_path: Option<&Utf8Path>,
+    force: bool,
+) -> CheckResult {
+    crate::validate_lab_name(problems, lab)?;
+    let lab_config = crate::load_config(problems, config_path, lab, None)?;
+    let lab_path = repo.join(lab);
+
+    let mut files: Vec<(Utf8PathBuf, Vec<u8>)> = lab_files(lab, &lab_config, &repo)
+        .into_iter()
+        .map(|(path, text)| (path, text.into_bytes()))
+        .collect();
+    if let Some(dir) = &lab_config.starter_dir {
+        let base = config_path
+            .and_then(Utf8Path::parent)
+            .unwrap_or(Utf8Path::new("."));
+        let starter = starter_files(&base.join(dir)).map_err(|e| problems.add(e, None, None))?;
+        for (path, data) in starter {
+            // Starter files replace the generated ones.
+            files.retain(|x| x.0 != path);
+            files.push((path, data));
+        }
+    }
+
+    let existing: Vec<Utf8PathBuf> = files
+        .iter()
+        .map(|(path, _)| lab_path.join(path))
+        .filter(|x| x.exists())
+        .collect();
+    if !existing.is_empty() && !force {
+        return Err(problems.add(
+            "these files already exist",
+            existing,
+            Some("rerun with --force to overwrite them".into()),
+        ));
+    }
+
+    for (path, data) in &files {
+        let path = lab_path.join(path);
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, data));
+        if let Err(e) = written {
+            return Err(problems.add(format!("can't write file: {e}"), path, None));
+        }
+        say!("created {path}");
+    }
+
+    let gitignore = repo.join(".gitignore");
+    let ignored =
+        fs::read_to_string(&gitignore).is_ok_and(|x| x.lines().any(|x| x.contains("target")));
+    if !ignored {
+        let fix = Fix::AppendLine {
+            path: gitignore.clone(),
+            line: "target/".into(),
+        };
+        fix.apply().map_err(|e| problems.add(e, gitignore, None))?;
+        say!("{fix}");
+    }
+
+    let mut context = Context {
+        problems,
+        repo_path: repo,
+        lab: lab.to_string(),
+        lab_path,
+        lab_config,
+        verbose: false,
+        build_timings: false,
+        child_env: Vec::new(),
+        env_overrides: Vec::new(),
+        writes_state: false,
+        scope: Scope::Full,
+        git_health: None,
+        skip_reason: None,
+        environment_failure: false,
+        parsed_sources: None,
+        tests: None,
+        lab_targets: None,
+    };
+    let plan = schedule::plan(CHECKS, &[]).map_err(|e| context.problems.add(e, None, None))?;
+    let structural: Vec<_> = plan.into_iter().filter(|x| x.cost == Cost::Cheap).collect();
+    run_plan(&mut context, &structural, false)
+}
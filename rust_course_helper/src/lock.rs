@@ -0,0 +1,297 @@
+//! Keeps two checker runs off the same repo, since they'd fight over cargo's locks and the
+//! state files and interleave their output.
+//!
+//! The lock is a file with the owner's PID and start time, removed when the run ends. It's in the
+//! system's temporary folder, named after the repo, so taking it never writes into the repo;
+//! `--read-only` runs keep theirs in their state folder, where `cache clean` takes it too. One
+//! left behind by a run that crashed is taken over once its process is gone, or after a few
+//! seconds when the run crashed before writing its PID.
+
+#[cfg(not(target_os = "linux"))]
+use crate::exec::{Exec, Stream};
+use crate::state::{self, LOCK_FILE};
+use crate::toml::{self, Value};
+use crate::{CheckError, Diags, cache, clock};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::time::{Duration, SystemTime};
+use std::{env, process, thread};
+
+/// How often a waiting run looks at the lock again.
+const POLL: Duration = Duration::from_millis(500);
+/// How long a run can take to write its PID into the lock it created. A lock without one that's
+/// older was left by a run that crashed before it could.
+const UNWRITTEN: Duration = Duration::from_secs(5);
+
+pub struct RunLock(Utf8PathBuf);
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// The run holding the lock, as far as its file says.
+struct Owner {
+    pid: Option<u32>,
+    started: Option<u64>,
+    /// What the file says, to tell whether it's still the same run's.
+    text: String,
+    /// When the file was last written.
+    modified: Option<SystemTime>,
+}
+
+impl Owner {
+    fn read(path: &Utf8Path) -> Option<Owner> {
+        let text = fs::read_to_string(path).ok()?;
+        let modified = fs::metadata(path).and_then(|x| x.modified()).ok();
+        // The owner may be still writing it.
+        let table = toml::parse(&text).unwrap_or_default();
+        let number = |key| match table.get(key) {
+            Some(Value::Integer(x)) => u64::try_from(*x).ok(),
+            _ => None,
+        };
+        Some(Owner {
+            pid: number("pid").and_then(|x| u32::try_from(x).ok()),
+            started: number("started"),
+            text,
+            modified,
+        })
+    }
+
+    fn describe(&self) -> String {
+        let mut text = "another checker run".to_string();
+        // A start in the future was written by a clock that was wrong then, or is now.
+        if let Some(started) = self.started.filter(|x| *x <= clock::now()) {
+            text += &format!(" started {}s ago", clock::now() - started);
+        }
+        if let Some(pid) = self.pid {
+            text += &format!(" (PID {pid})");
+        }
+        text + " is in progress on this repo"
+    }
+
+    /// Only sure about runs that are gone; anything unclear counts as running.
+    fn gone(&self) -> bool {
+        match self.pid {
+            Some(pid) => !is_running(pid),
+            // A modification time in the future isn't old.
+            None => self
+                .modified
+                .and_then(|x| SystemTime::now().duration_since(x).ok())
+                .is_some_and(|x| x > UNWRITTEN),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_running(pid: u32) -> bool {
+    Utf8Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_running(pid: u32) -> bool {
+    // Also fails for processes of other users, which can't have written this repo's lock.
+    Exec::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(Stream::Discard)
+        .stderr(Stream::Discard)
+        .run()
+        .map_or(true, |x| x.success())
+}
+
+#[cfg(not(unix))]
+fn is_running(pid: u32) -> bool {
+    let Ok(output) = Exec::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/NH"])
+        .stdin_null()
+        .stderr(Stream::Discard)
+        .run()
+    else {
+        return true;
+    };
+    output.stdout.text().contains(&pid.to_string())
+}
+
+/// Where the lock of `repo` is.
+fn path(repo: &Utf8Path) -> Result<Utf8PathBuf, String> {
+    if state::redirected() {
+        return state::create_state_path(repo, LOCK_FILE);
+    }
+    let dir = env::temp_dir().join("rust_course_helper_locks");
+    let dir = Utf8PathBuf::from_path_buf(dir)
+        .map_err(|_| "the temporary folder's path isn't UTF-8".to_string())?;
+    fs::create_dir_all(&dir).map_err(|e| format!("can't create {dir}: {e}"))?;
+    Ok(dir.join(format!("{}.lock", cache::repo_id(repo))))
+}
+
+/// Removes the lock of `stale`, a run that's gone. Other runs may be taking it over too, and one
+/// may have taken it already, so the file is first moved out of the way, where no other run can
+/// get to it, and only removed if it's still the one `stale` wrote; otherwise it's put back.
+/// Whether this run is the one that removed it.
+fn take_over(path: &Utf8Path, stale: &Owner) -> Result<bool, String> {
+    let moved = path.with_file_name(format!(
+        "{}.{}.stale",
+        path.file_name().unwrap_or_default(),
+        process::id()
+    ));
+    match fs::rename(path, &moved) {
+        Ok(()) => {}
+        // Another run moved it first.
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(format!("can't take over {path}: {e}")),
+    }
+    let stale = fs::read_to_string(&moved).is_ok_and(|x| x == stale.text);
+    if !stale {
+        // Unless yet another run took the lock meanwhile, in which case its own is kept.
+        let _ = fs::hard_link(&moved, path);
+    }
+    fs::remove_file(&moved).map_err(|e| format!("can't remove {moved}: {e}"))?;
+    Ok(stale)
+}
+
+/// Creates the lock file, if no other run has it.
+fn try_create(path: &Utf8Path) -> Result<Option<RunLock>, String> {
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(x) => x,
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => return Ok(None),
+        Err(e) => return Err(format!("can't create {path}: {e}")),
+    };
+    let lock = RunLock(path.to_path_buf());
+    write!(
+        file,
+        "pid = {}\nstarted = {}\n",
+        process::id(),
+        clock::now()
+    )
+    .map_err(|e| format!("can't write {path}: {e}"))?;
+    Ok(Some(lock))
+}
+
+/// Takes the repo's lock, waiting for the run holding it to finish with `wait`, or failing
+/// otherwise.
+pub fn acquire(problems: &mut Diags, repo: &Utf8Path, wait: bool) -> Result<RunLock, CheckError> {
+    let path = path(repo).map_err(|e| problems.add(e, None, None))?;
+    let mut said_waiting = false;
+    loop {
+        match try_create(&path) {
+            Ok(Some(lock)) => return Ok(lock),
+            Ok(None) => {}
+            Err(e) => return Err(problems.add(e, path, None)),
+        }
+        // Gone between the two calls; try again.
+        let Some(owner) = Owner::read(&path) else {
+            continue;
+        };
+        if owner.gone() {
+            if take_over(&path, &owner).map_err(|e| problems.add(e, path.clone(), None))? {
+                say!("taking over the lock of a checker run that didn't finish");
+            }
+            continue;
+        }
+        if !wait {
+            return Err(problems.add(
+                owner.describe(),
+                path,
+                Some(
+                    "wait for it to finish, or pass `--wait-for-lock` to wait for it automatically"
+                        .into(),
+                ),
+            ));
+        }
+        if !said_waiting {
+            say!("{}; waiting for it to finish", owner.describe());
+            said_waiting = true;
+        }
+        thread::sleep(POLL);
+    }
+}
+
+/// Takes the lock at `path` if no run has it, without waiting, taking over the lock of a run
+/// that crashed. For `cache clean`, which must not remove what a run is using.
+pub fn try_acquire(path: &Utf8Path) -> Result<Option<RunLock>, String> {
+    loop {
+        if let Some(lock) = try_create(path)? {
+            return Ok(Some(lock));
+        }
+        match Owner::read(path) {
+            // Gone between the two calls; try again.
+            None => continue,
+            Some(owner) if owner.gone() => {
+                take_over(path, &owner)?;
+            }
+            Some(_) => return Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+
+    /// A PID no process has.
+    const GONE: &str = "pid = 4294967295\nstarted = 1\n";
+
+    #[test]
+    fn held_locks_are_left_alone() {
+        let dir = TempDir::new("lock_held").unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        let lock = try_acquire(&path).unwrap().unwrap();
+        assert!(try_acquire(&path).unwrap().is_none());
+        drop(lock);
+        assert!(!path.exists());
+        assert!(try_acquire(&path).unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn locks_of_runs_that_are_gone_are_taken_over() {
+        let dir = TempDir::new("lock_stale").unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        fs::write(&path, GONE).unwrap();
+        let _lock = try_acquire(&path).unwrap().unwrap();
+        let owner = Owner::read(&path).unwrap();
+        assert_eq!(owner.pid, Some(process::id()));
+        // Nothing is left behind.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn old_locks_without_a_pid_are_taken_over() {
+        let dir = TempDir::new("lock_unwritten").unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        for text in ["", "pid = \"garbage\"\n"] {
+            fs::write(&path, text).unwrap();
+            // Still being written, as far as anyone can tell.
+            assert!(try_acquire(&path).unwrap().is_none());
+
+            let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(an_hour_ago).unwrap();
+            drop(file);
+            let lock = try_acquire(&path).unwrap().unwrap();
+            assert_eq!(Owner::read(&path).unwrap().pid, Some(process::id()));
+            drop(lock);
+        }
+    }
+
+    #[test]
+    fn locks_taken_meanwhile_are_put_back() {
+        let dir = TempDir::new("lock_raced").unwrap();
+        let path = dir.path().join(LOCK_FILE);
+        fs::write(&path, GONE).unwrap();
+        let stale = Owner::read(&path).unwrap();
+        // Another run took it over between this one reading it and taking it over.
+        let live = format!("pid = {}\nstarted = 2\n", process::id());
+        fs::write(&path, &live).unwrap();
+        assert!(!take_over(&path, &stale).unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), live);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        // And the run that took it over first leaves nothing for the others to do.
+        fs::remove_file(&path).unwrap();
+        assert!(!take_over(&path, &stale).unwrap());
+    }
+}
@@ -1,66 +1,822 @@
+#[macro_use]
+mod output;
+
+mod artifacts;
+mod badge;
+mod budget;
+mod bug_report;
+mod cache;
+mod capture;
 mod checks;
+mod ci;
+mod clock;
+mod config;
+mod datetime;
+mod diff;
+mod docs;
+mod emit;
+mod exec;
+mod expect;
+mod fix;
+mod flaky;
+mod git;
+mod grade;
+mod group;
+mod init;
+mod json;
+mod lock;
+#[cfg(feature = "notify")]
+mod notify;
+mod priority;
+mod readonly;
+mod receipt;
+mod report;
+mod schedule;
+mod scope;
+mod selftest;
+mod sha256;
+mod similarity;
+mod state;
+mod temp;
+mod timings;
+mod toml;
+mod usage;
+mod usage_log;
+mod zip;
 
-use crate::checks::CHECKS;
-use camino::Utf8PathBuf;
-use clap::Parser;
+use crate::checks::{CHECKS, Check, ParsedFile, Target, TestFn};
+use crate::config::{LabConfig, LabMetadata};
+use crate::emit::EmitFormat;
+use crate::fix::Fix;
+use crate::group::RootCause;
+use crate::receipt::Receipt;
+use crate::scope::Scope;
+use crate::usage::Usage;
+use camino::{Utf8Path, Utf8PathBuf};
+use clap::{Parser, Subcommand};
 use colored::Colorize;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::Write as _;
 use std::process::ExitCode;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Exits with 1 when the lab has problems, and with 3 when every check that failed did so because of the toolchain or the machine, like a compiler crash."
+)]
 struct Args {
-    #[arg(short, long)]
-    repo: Utf8PathBuf,
-    #[arg(short, long)]
-    lab: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    check: CheckArgs,
+}
+
+#[derive(clap::Args)]
+struct CheckArgs {
+    #[arg(short, long, required_unless_present_any = ["show_config", "show_plan"], value_parser = Utf8PathParser)]
+    repo: Option<Utf8PathBuf>,
+    #[arg(short, long, required = true)]
+    lab: Option<String>,
     #[arg(short, long)]
     verbose: bool,
+    /// Course config file with per-lab settings
+    #[arg(long, value_parser = Utf8PathParser)]
+    config: Option<Utf8PathBuf>,
+    /// Course track, selecting the track's settings from the config
+    #[arg(long, requires = "config")]
+    track: Option<String>,
+    /// Print the settings that apply to this lab and track, without running any checks
+    #[arg(long)]
+    show_config: bool,
+    /// Print the order the checks would run in, without running them
+    #[arg(long)]
+    show_plan: bool,
+    /// Stop after the first check that fails
+    #[arg(long)]
+    fail_fast: bool,
+    /// Write a receipt of this run to the given file, or to `.checker/receipt.toml`
+    #[arg(long, num_args = 0..=1, value_parser = Utf8PathParser)]
+    receipt: Option<Option<Utf8PathBuf>>,
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+    /// Write these reports to stdout as delimited sections, and everything else to stderr
+    #[arg(long, value_enum, value_delimiter = ',', conflicts_with = "format")]
+    emit: Vec<EmitFormat>,
+    /// After the run, apply the available fixes and check again
+    #[arg(long)]
+    apply_fixes: bool,
+    /// Apply fixes without asking
+    #[arg(long, requires = "apply_fixes")]
+    yes: bool,
+    /// Succeed only if the run finds exactly the problems listed in this file
+    #[arg(long, value_parser = Utf8PathParser)]
+    expect: Option<Utf8PathBuf>,
+    /// Show how long each check and command took, and the resources they used
+    #[arg(long)]
+    timings: bool,
+    /// Time how long each crate takes to compile, and name the slowest dependencies when the
+    /// build is slow
+    #[arg(long)]
+    build_timings: bool,
+    /// Report the checks that made the run take longer than this many seconds; overrides the
+    /// config's `soft_budget`
+    #[arg(long, value_name = "SECONDS")]
+    soft_budget: Option<u64>,
+    /// Only scan files changed since this git revision; checks that build still see everything
+    #[arg(long, value_name = "REF", conflicts_with = "receipt")]
+    since: Option<String>,
+    /// Write an SVG badge showing how many checks passed, with its numbers in a `.json` file
+    /// next to it
+    #[arg(long, value_name = "PATH", value_parser = Utf8PathParser)]
+    badge: Option<Utf8PathBuf>,
+    /// Keep the files checks produce, like the formatting diff, in this folder
+    #[arg(long, value_name = "DIR", value_parser = Utf8PathParser)]
+    artifacts_dir: Option<Utf8PathBuf>,
+    /// Make reports, the CI summary and the grade CSV the same for every run on the same commit:
+    /// sorted, with paths relative to the repo and no durations. Receipts keep the real time
+    #[arg(long)]
+    stable_output: bool,
+    /// Remember each check's result for the commit, and report checks whose result changed
+    /// since an earlier run on the same commit
+    #[arg(long)]
+    track_flakiness: bool,
+    /// Append an anonymous line with each check's result and duration to this file, for
+    /// `usage-report`
+    #[arg(long, value_parser = Utf8PathParser)]
+    usage_log: Option<Utf8PathBuf>,
+    /// Also show how similar the lab's files are to the same lab's in this repo, for reviews;
+    /// never fails the run
+    #[arg(long, value_name = "REPO", value_parser = Utf8PathParser)]
+    compare: Option<Utf8PathBuf>,
+    /// Write a zip with the run's output, report, config, and tools' versions here, to attach
+    /// to a bug report about the checker; written even if the checker crashes
+    #[arg(long, value_name = "PATH", value_parser = Utf8PathParser)]
+    bug_report: Option<Utf8PathBuf>,
+    /// If another run is checking the same repo, wait for it instead of failing
+    #[arg(long)]
+    wait_for_lock: bool,
+    /// Format the output for this CI system's logs, and report the verdict to it
+    #[arg(long, value_enum, value_name = "SYSTEM")]
+    ci: Option<ci::Ci>,
+    /// Print only the most important problems, this many at most; reports still have all of
+    /// them
+    #[arg(long, value_name = "N")]
+    max_problems: Option<usize>,
+    /// Print every problem, even with `--max-problems`
+    #[arg(long)]
+    full: bool,
+    /// Print the problems once every check has run, instead of as each check finishes. Always
+    /// the case when the output isn't a terminal, and with `--max-problems`, `--ci` or another
+    /// format
+    #[arg(long)]
+    print_at_end: bool,
+    /// Never write into the repo: keep state files and builds in a temporary folder, refuse
+    /// fixes, and fail if the repo's files changed anyway
+    #[arg(long, conflicts_with = "apply_fixes")]
+    read_only: bool,
+    /// Set by `grade`.
+    #[arg(skip)]
+    grade: Option<grade::Output>,
+    /// Show a desktop notification when a run that took a while finishes
+    #[cfg(feature = "notify")]
+    #[arg(long)]
+    notify: bool,
+}
+
+/// Parses paths on the command line, explaining what's wrong with ones that aren't UTF-8
+/// instead of clap's generic message.
+#[derive(Clone)]
+struct Utf8PathParser;
+
+impl clap::builder::TypedValueParser for Utf8PathParser {
+    type Value = Utf8PathBuf;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<Utf8PathBuf, clap::Error> {
+        Utf8PathBuf::from_path_buf(value.into()).map_err(|path| {
+            let arg = arg.map_or("a path".into(), |x| format!("`{x}`"));
+            let message = format!(
+                "{arg} is `{}`, which isn't valid UTF-8; the checker only works with UTF-8 paths\n\n\
+                 tip: rename the folders and files with invalid characters, shown as � above\n",
+                path.to_string_lossy()
+            );
+            clap::Error::raw(clap::error::ErrorKind::InvalidUtf8, message).with_cmd(cmd)
+        })
+    }
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum Format {
+    #[default]
+    Human,
+    Json,
+    /// One line per problem, for editors
+    Short,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks that a receipt is authentic and belongs to the submitted repo
+    VerifyReceipt {
+        #[arg(value_parser = Utf8PathParser)]
+        receipt: Utf8PathBuf,
+        /// The submitted repo
+        #[arg(short, long, value_parser = Utf8PathParser)]
+        repo: Utf8PathBuf,
+        /// The receipt's commit must be an ancestor of this revision
+        #[arg(long, default_value = "HEAD")]
+        rev: String,
+        /// Course config file with the receipt secret
+        #[arg(long, value_parser = Utf8PathParser)]
+        config: Option<Utf8PathBuf>,
+    },
+    /// Creates a lab folder with everything the checks expect
+    Init {
+        #[arg(short, long)]
+        lab: String,
+        /// The repo to create the lab in
+        #[arg(short, long, default_value = ".", value_parser = Utf8PathParser)]
+        repo: Utf8PathBuf,
+        /// Course config file with the lab's crate type and starter files
+        #[arg(long, value_parser = Utf8PathParser)]
+        config: Option<Utf8PathBuf>,
+        /// Overwrite files that already exist
+        #[arg(long)]
+        force: bool,
+    },
+    /// Runs every check on a lab known to be fine, to find problems with the environment
+    Selftest {
+        #[arg(short, long)]
+        verbose: bool,
+    },
+    /// Takes apart the output of `--emit`
+    SplitEmit {
+        /// File with the emitted stream; stdin if missing
+        #[arg(value_parser = Utf8PathParser)]
+        input: Option<Utf8PathBuf>,
+        /// Print the body of this section, like `json`
+        #[arg(long, required_unless_present = "out_dir", conflicts_with = "out_dir")]
+        section: Option<String>,
+        /// Write every section to a file in this folder
+        #[arg(long, value_parser = Utf8PathParser)]
+        out_dir: Option<Utf8PathBuf>,
+    },
+    /// Sums up a `--usage-log` file: how often each check failed and how long it took
+    UsageReport {
+        #[arg(value_parser = Utf8PathParser)]
+        log: Utf8PathBuf,
+        /// Only count runs of this lab
+        #[arg(short, long)]
+        lab: Option<String>,
+    },
+    /// Shows or cleans up the cache of `--read-only` runs
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// Runs the checks and works out the lab's grade from the config's grading policy
+    Grade {
+        #[command(flatten)]
+        check: Box<CheckArgs>,
+        /// Print the grade as CSV for gradebooks, and everything else to stderr
+        #[arg(long, conflicts_with_all = ["format", "emit"])]
+        csv: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Prints where the cache is
+    Dir,
+    /// Prints how much of it is builds and how much state files
+    Size,
+    /// Removes the cache of every repo, except ones a run is using
+    Clean {
+        /// Only remove what no run used for this long, like `30d` or `12h`
+        #[arg(long, value_name = "AGE", value_parser = cache::parse_age)]
+        older_than: Option<u64>,
+        /// Only remove these
+        #[arg(long, value_enum)]
+        category: Option<cache::Category>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    Error,
+    Warning,
 }
 
 struct Diag {
+    /// The check that found the problem, if it was found by one.
+    check: Option<&'static str>,
+    severity: Severity,
     text: String,
-    path: Option<Utf8PathBuf>,
+    paths: Vec<Utf8PathBuf>,
     help: Option<String>,
+    fix: Option<Fix>,
+    /// Index into `Diags::root_causes`, if this problem was grouped under one.
+    root_cause_group: Option<usize>,
+    /// Left out of the printed list by `--max-problems`.
+    hidden: bool,
+    /// Already printed, when its check finished.
+    printed: bool,
+    /// The course's page about this kind of problem.
+    url: Option<String>,
+    /// Structured data for reports, never printed. Field names are stable:
+    ///
+    /// - `committed_files`: `state_file_count`; or `bad_file_count`, `largest_file` and
+    ///   `largest_file_bytes`
+    /// - `tests`: `missing_file` and `found_relative_to` (`lab`, `repo` or `none`) for files
+    ///   tests couldn't open; `test_function_count` when no tests ran
+    /// - `line_length`, `should_panic`, `include_paths`, `unstable_features`: `line`, in the
+    ///   first path
+    /// - `compiler_warnings`: `line` and `target_kind` (`lib`, `bin`, `test`, `bench` or
+    ///   `example`) for problems in code `cargo build` skips; `dependency`,
+    ///   `dependency_version` and `failure` (`compile`, `build script` or `rust version`) for
+    ///   dependencies that failed to build; `build_seconds` for a slow build with
+    ///   `--build-timings`
+    /// - the soft budget warning: `budget_seconds`, `total_seconds` and `over_budget_checks`
+    /// - checks that run cargo: `retried` when a locked file made cargo run twice;
+    ///   `rustc_version` and `query_stack` when the compiler crashed
+    /// - `untracked_sources`: `untracked_file_count` and `ignored_file_count`
+    /// - `git_lfs`: `lfs_file_count`, `pointer_file_count` and `lfs_installed`
+    /// - `lab_markers`: `marker`, the config's marker the problem is about
+    fields: BTreeMap<String, String>,
+}
+
+struct DiagPaths(Vec<Utf8PathBuf>);
+
+impl From<Utf8PathBuf> for DiagPaths {
+    fn from(value: Utf8PathBuf) -> Self {
+        DiagPaths(vec![value])
+    }
+}
+impl From<Option<Utf8PathBuf>> for DiagPaths {
+    fn from(value: Option<Utf8PathBuf>) -> Self {
+        DiagPaths(value.into_iter().collect())
+    }
+}
+impl From<Vec<Utf8PathBuf>> for DiagPaths {
+    fn from(value: Vec<Utf8PathBuf>) -> Self {
+        DiagPaths(value)
+    }
 }
 
+const MAX_PRINTED_PATHS: usize = 20;
+/// The exit code for runs that only failed because of the toolchain or the machine.
+const ENVIRONMENT_FAILURE: u8 = 3;
+
 #[derive(Default)]
 struct Diags {
     problems: Vec<Diag>,
+    /// The repo that was checked.
+    repo: Option<Utf8PathBuf>,
+    /// The lab that was checked, and what the config says about it.
+    lab: Option<(String, LabMetadata)>,
+    /// The folder the lab was found in, when the config lets it differ from the lab name.
+    lab_dir: Option<String>,
+    /// The order the checks were scheduled in.
+    plan: Vec<&'static str>,
+    /// Every check that ran, and whether it passed.
+    checks: Vec<CheckStatus>,
+    /// Every command the checks ran.
+    commands: Vec<CommandRecord>,
+    /// Whether each test `cargo test` ran passed, by name, when the grading policy gives tests
+    /// points.
+    test_results: BTreeMap<String, bool>,
+    current_check: Option<&'static str>,
+    root_causes: Vec<RootCause>,
+    /// The `--since` revision, when file scans were limited to what changed since it.
+    since: Option<String>,
+    /// Checks whose result differs from an earlier run on the same commit.
+    unstable: Vec<flaky::Unstable>,
+    /// Worked out by `grade`.
+    grade: Option<grade::Grade>,
+    /// Where `--artifacts-dir` keeps the checks' files.
+    artifacts_dir: Option<Utf8PathBuf>,
+    artifacts: Vec<artifacts::Artifact>,
+    /// Print each check's problems as soon as it finishes, instead of all of them at the end.
+    live: bool,
+}
+
+struct CheckStatus {
+    name: &'static str,
+    /// Skipped checks count as passed.
+    passed: bool,
+    duration: Duration,
+    /// Whether the check only looked at the files changed since `--since`.
+    partial: bool,
+    skipped: Option<SkipReason>,
+    /// Failed because of the toolchain or the machine rather than the lab, like a compiler
+    /// crash.
+    environment_failure: bool,
+}
+
+impl CheckStatus {
+    fn ran(&self) -> bool {
+        self.skipped.is_none()
+    }
+}
+
+/// Why a check didn't run, or didn't do anything when it did.
+#[derive(Clone, PartialEq, Eq)]
+enum SkipReason {
+    /// Listed in the config's `skip_checks`.
+    Disabled,
+    /// Needs settings the config doesn't have.
+    NotConfigured,
+    /// Has nothing to look at in this lab.
+    NotApplicable(&'static str),
+    /// A check it builds on failed.
+    DependencyFailed(&'static str),
+    /// `--fail-fast` stopped the run before it.
+    FailFast,
+    /// A tool it needs isn't installed.
+    MissingTool(String),
 }
 
+impl SkipReason {
+    /// Whether the student can't do anything about it, so it isn't worth pointing out.
+    fn benign(&self) -> bool {
+        match self {
+            SkipReason::Disabled | SkipReason::NotConfigured | SkipReason::NotApplicable(_) => true,
+            SkipReason::DependencyFailed(_) | SkipReason::FailFast | SkipReason::MissingTool(_) => {
+                false
+            }
+        }
+    }
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Disabled => write!(f, "disabled by the course config"),
+            SkipReason::NotConfigured => write!(f, "not set up for this lab"),
+            SkipReason::NotApplicable(x) => write!(f, "{x}"),
+            SkipReason::DependencyFailed(x) => write!(f, "`{x}` failed"),
+            SkipReason::FailFast => write!(f, "an earlier check failed and --fail-fast is on"),
+            SkipReason::MissingTool(x) => write!(f, "{x} not installed"),
+        }
+    }
+}
+
+struct CommandRecord {
+    check: Option<&'static str>,
+    command: String,
+    duration: Duration,
+    /// Missing on platforms where it can't be collected.
+    usage: Option<Usage>,
+}
+
+#[derive(Clone, Copy)]
 struct CheckError;
 
 type CheckResult = std::result::Result<(), CheckError>;
 
 impl Diags {
-    fn add<S1>(&mut self, text: S1, path: Option<Utf8PathBuf>, help: Option<String>) -> CheckError
-    where
-        S1: Into<String>,
-    {
+    /// Whether checks failed, but none of them because of the lab.
+    fn environment_failure(&self) -> bool {
+        let failed = || self.checks.iter().filter(|x| !x.passed);
+        failed().next().is_some() && failed().all(|x| x.environment_failure)
+    }
+    fn push(
+        &mut self,
+        severity: Severity,
+        text: String,
+        paths: DiagPaths,
+        help: Option<String>,
+        fix: Option<Fix>,
+    ) {
         self.problems.push(Diag {
-            text: text.into(),
-            path,
+            check: self.current_check,
+            severity,
+            text,
+            paths: paths.0,
             help,
+            fix,
+            root_cause_group: None,
+            hidden: false,
+            printed: false,
+            url: None,
+            fields: BTreeMap::new(),
         });
+    }
+    /// Adds structured data to the problem recorded last.
+    fn fields<const N: usize>(&mut self, fields: [(&str, String); N]) {
+        if let Some(problem) = self.problems.last_mut() {
+            problem
+                .fields
+                .extend(fields.into_iter().map(|(k, v)| (k.to_string(), v)));
+        }
+    }
+    fn add<S1, P>(&mut self, text: S1, paths: P, help: Option<String>) -> CheckError
+    where
+        S1: Into<String>,
+        P: Into<DiagPaths>,
+    {
+        self.push(Severity::Error, text.into(), paths.into(), help, None);
         CheckError
     }
-    fn print(self) {
+    /// Like `add`, for problems that have a mechanical fix.
+    fn add_fixable<S1, P>(
+        &mut self,
+        text: S1,
+        paths: P,
+        help: Option<String>,
+        fix: Fix,
+    ) -> CheckError
+    where
+        S1: Into<String>,
+        P: Into<DiagPaths>,
+    {
+        self.push(Severity::Error, text.into(), paths.into(), help, Some(fix));
+        CheckError
+    }
+    fn record_command(&mut self, command: String, duration: Duration, usage: Option<Usage>) {
+        self.commands.push(CommandRecord {
+            check: self.current_check,
+            command,
+            duration,
+            usage,
+        });
+    }
+    /// Records a problem that doesn't make the check fail.
+    fn warn<S1, P>(&mut self, text: S1, paths: P, help: Option<String>)
+    where
+        S1: Into<String>,
+        P: Into<DiagPaths>,
+    {
+        self.push(Severity::Warning, text.into(), paths.into(), help, None);
+    }
+    /// Like `warn`, for problems that have a mechanical fix.
+    fn warn_fixable<S1, P>(&mut self, text: S1, paths: P, help: Option<String>, fix: Fix)
+    where
+        S1: Into<String>,
+        P: Into<DiagPaths>,
+    {
+        self.push(
+            Severity::Warning,
+            text.into(),
+            paths.into(),
+            help,
+            Some(fix),
+        );
+    }
+    fn print(&self, verbose: bool) {
+        self.print_problems(verbose);
+        // Benign skips are the norm, so they're only listed in verbose mode.
+        let skipped: Vec<(&str, &SkipReason)> = self
+            .checks
+            .iter()
+            .filter_map(|x| Some((x.name, x.skipped.as_ref()?)))
+            .filter(|(_, x)| verbose || !x.benign())
+            .collect();
+        if !skipped.is_empty() {
+            say!("skipped checks:");
+            for (name, reason) in skipped {
+                let line = format!("  {name}: skipped: {reason}");
+                match reason.benign() {
+                    true => say!("{}", line.dimmed()),
+                    false => say!("{}", line.yellow()),
+                }
+            }
+            say!();
+        }
+        if !self.unstable.is_empty() {
+            say!(
+                "{}",
+                "unstable checks, with different results for the same commit:".yellow()
+            );
+            for x in &self.unstable {
+                say!(
+                    "  {}: {} at {}, {} at {}",
+                    x.check,
+                    flaky::status(x.earlier.passed),
+                    datetime::utc(x.earlier.at),
+                    flaky::status(x.now.passed),
+                    datetime::utc(x.now.at)
+                );
+            }
+            say!();
+        }
+        let partial: Vec<&str> = self
+            .checks
+            .iter()
+            .filter(|x| x.partial)
+            .map(|x| x.name)
+            .collect();
+        if let Some(base) = &self.since
+            && !partial.is_empty()
+        {
+            say!(
+                "{}: {}; unchanged files weren't checked",
+                format!("partial scan (since {base})").yellow(),
+                partial.join(", ")
+            );
+        }
+    }
+    /// With `live`, prints the problems found since the last call, so each check's problems are
+    /// printed when it finishes. Grouping and `--max-problems` need every problem, so `live`
+    /// is off with `--max-problems`, and groups are only listed in the summary.
+    fn print_new(&mut self) {
+        if !self.live {
+            return;
+        }
+        for problem in self.problems.iter_mut().filter(|x| !x.printed) {
+            problem.print();
+            problem.printed = true;
+        }
+    }
+    /// How many problems each check found, for after they were printed one check at a time.
+    fn print_summary(&self) {
+        say!("summary of the problems found:");
+        let mut names: Vec<&str> = self.checks.iter().map(|x| x.name).collect();
+        names.push("checker");
+        for name in names {
+            let found = || {
+                self.problems
+                    .iter()
+                    .filter(move |x| x.check.unwrap_or("checker") == name)
+            };
+            let errors = found().filter(|x| x.severity == Severity::Error).count();
+            let warnings = found().count() - errors;
+            if errors + warnings > 0 {
+                say!("  {errors:>3} errors {warnings:>3} warnings  {name}");
+            }
+        }
+        say!();
+    }
+    fn print_problems(&self, verbose: bool) {
         if self.problems.is_empty() {
-            println!("no problems found");
+            say!("no problems found");
             return;
         }
 
-        println!("\nsome problems were found:");
+        let printed = self.problems.iter().filter(|x| x.printed).count();
+        match printed {
+            0 => say!("\nsome problems were found:"),
+            _ => self.print_summary(),
+        }
 
-        for problem in self.problems {
-            println!("{}: {}", "checker error".bright_red(), problem.text);
-            if let Some(path) = problem.path {
-                println!("{}: {}", "path".purple(), path);
+        // Problems shown as their checks finished are only summed up here, grouped or not.
+        for group in &self.root_causes {
+            let shown: Vec<usize> = group
+                .members
+                .iter()
+                .copied()
+                .filter(|&i| !self.problems[i].hidden)
+                .collect();
+            if shown.is_empty() {
+                continue;
             }
-            if let Some(help) = problem.help {
-                println!("{}: {}", "help".blue(), help);
+            say!(
+                "{}: this file is the root cause of {} problems",
+                "checker error".bright_red(),
+                group.members.len()
+            );
+            say!("{}: {}", "path".purple(), group.path);
+            for i in shown {
+                let problem = &self.problems[i];
+                let first_line = problem.text.lines().next().unwrap_or_default();
+                say!(
+                    "  - [{}] {}",
+                    problem.check.unwrap_or("checker"),
+                    first_line
+                );
             }
+            say!();
+        }
 
-            println!();
+        let (grouped, ungrouped): (Vec<_>, Vec<_>) = self
+            .problems
+            .iter()
+            .filter(|x| !x.hidden && !x.printed)
+            .partition(|x| x.root_cause_group.is_some());
+        for problem in ungrouped {
+            problem.print();
+        }
+
+        if !grouped.is_empty() {
+            if verbose {
+                say!("details of grouped problems:\n");
+                for problem in grouped {
+                    problem.print();
+                }
+            } else {
+                say!(
+                    "rerun with --verbose to see the details of {} grouped problems\n",
+                    grouped.len()
+                );
+            }
+        }
+
+        let hidden = self.problems.iter().filter(|x| x.hidden).count();
+        if hidden > 0 {
+            let count = match hidden {
+                1 => "1 more problem".to_string(),
+                n => format!("{n} more problems"),
+            };
+            say!(
+                "{}\n",
+                format!("…and {count} hidden; rerun with --full").yellow()
+            );
+        }
+    }
+}
+
+impl Diag {
+    fn print(&self) {
+        let label = match self.severity {
+            Severity::Error => "checker error".bright_red(),
+            Severity::Warning => "checker warning".yellow(),
+        };
+        say!("{}: {}", label, self.text);
+        for path in self.paths.iter().take(MAX_PRINTED_PATHS) {
+            say!("{}: {}", "path".purple(), path);
+        }
+        if self.paths.len() > MAX_PRINTED_PATHS {
+            say!("..and {} more", self.paths.len() - MAX_PRINTED_PATHS);
+        }
+        let url = self.url.as_deref();
+        match (&self.help, url) {
+            (Some(help), Some(url)) if !docs::hyperlinks_supported() => {
+                say!("{}: {help}; see {url}", "help".blue())
+            }
+            (Some(help), _) => say!("{}: {}", "help".blue(), help),
+            (None, Some(url)) if !docs::hyperlinks_supported() => {
+                say!("{}: see {url}", "help".blue())
+            }
+            (None, _) => {}
+        }
+        if let Some(url) = url
+            && docs::hyperlinks_supported()
+        {
+            say!("{}: {}", "docs".blue(), docs::hyperlink(url, url));
+        }
+        if let Some(fix) = &self.fix {
+            say!("{}: {}", "fix".green(), fix);
+        }
+
+        say!();
+    }
+}
+
+/// Finds the lab's folder among the ones the config allows. Several matching folders are an
+/// error, but the first one is still checked.
+fn find_lab_dir(
+    problems: &mut Diags,
+    repo: &Utf8Path,
+    lab: &str,
+    candidates: &[String],
+) -> (String, CheckResult) {
+    let mut found: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let names = match candidate.contains('*') {
+            false => vec![candidate.clone()],
+            true => {
+                let mut names: Vec<String> = repo
+                    .read_dir_utf8()
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .map(|x| x.file_name().to_string())
+                    .filter(|x| !x.starts_with('.') && x != "target")
+                    .filter(|x| checks::matches_pattern(candidate, x))
+                    .collect();
+                names.sort();
+                names
+            }
+        };
+        for name in names {
+            if repo.join(&name).is_dir() && !found.contains(&name) {
+                found.push(name);
+            }
+        }
+    }
+
+    match found.as_slice() {
+        [] => (lab.to_string(), Ok(())),
+        [x] => (x.clone(), Ok(())),
+        [x, ..] => {
+            let text = format!(
+                "several folders could be {lab}: {}; checking `{x}`",
+                found.join(", ")
+            );
+            let paths: Vec<Utf8PathBuf> = found.iter().map(|x| repo.join(x)).collect();
+            let help = Some("keep exactly one folder for the lab".into());
+            (x.clone(), Err(problems.add(text, paths, help)))
         }
     }
 }
@@ -81,42 +837,828 @@ fn validate_lab_name(problems: &mut Diags, name: &str) -> CheckResult {
 struct Context<'x> {
     problems: &'x mut Diags,
     repo_path: Utf8PathBuf,
+    /// The lab's name, which the folder at `lab_path` can differ from with `lab_dirs`.
+    lab: String,
     lab_path: Utf8PathBuf,
+    lab_config: LabConfig,
     verbose: bool,
+    /// `--build-timings`.
+    build_timings: bool,
+    /// The config's `env` variables, and `CARGO_TARGET_DIR` for read-only runs, set for every
+    /// command checks run.
+    child_env: Vec<(String, String)>,
+    /// Variables of `child_env` that replaced a command's own setting, noted once each.
+    env_overrides: Vec<String>,
+    /// Whether this run writes files into the state folder.
+    writes_state: bool,
+    /// Files the file-scanning checks look at.
+    scope: Scope,
+    /// Whether git works in the repo, once a check asked.
+    git_health: Option<git::Health>,
+    /// Set by a check that had nothing to do.
+    skip_reason: Option<SkipReason>,
+    /// Set by a check that failed because of the toolchain or the machine.
+    environment_failure: bool,
+    /// The lab's parsed sources and test functions, once a check needed them.
+    parsed_sources: Option<Rc<Vec<ParsedFile>>>,
+    tests: Option<Rc<Vec<TestFn>>>,
+    /// The lab package's targets, once a check needed them; `Some(None)` when cargo couldn't
+    /// say.
+    lab_targets: Option<Option<Rc<Vec<Target>>>>,
+}
+
+impl Context<'_> {
+    /// Ends a check that has nothing to do, saying why.
+    fn skip(&mut self, reason: SkipReason) -> CheckResult {
+        self.skip_reason = Some(reason);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl<'x> Context<'x> {
+    /// A context for running single checks on a lab in `repo`, with the default config.
+    fn for_lab(problems: &'x mut Diags, repo: &Utf8Path, lab_dir: &str) -> Context<'x> {
+        Context {
+            problems,
+            repo_path: repo.to_owned(),
+            lab: lab_dir.to_string(),
+            lab_path: repo.join(lab_dir),
+            lab_config: LabConfig::default(),
+            verbose: false,
+            build_timings: false,
+            child_env: Vec::new(),
+            env_overrides: Vec::new(),
+            writes_state: false,
+            scope: Scope::Full,
+            git_health: None,
+            skip_reason: None,
+            environment_failure: false,
+            parsed_sources: None,
+            tests: None,
+            lab_targets: None,
+        }
+    }
+}
+
+fn load_config(
+    problems: &mut Diags,
+    path: Option<&Utf8Path>,
+    lab: &str,
+    track: Option<&str>,
+) -> Result<LabConfig, CheckError> {
+    match path {
+        Some(path) => {
+            config::load(path, lab, track).map_err(|e| problems.add(e, path.to_owned(), None))
+        }
+        None => Ok(LabConfig::default()),
+    }
+}
+
+/// Runs one check, turning its errors into warnings if the config says so.
+fn run_check(ctx: &mut Context, check: &Check) -> CheckResult {
+    ctx.problems.current_check = Some(check.name);
+    ctx.skip_reason = None;
+    ctx.environment_failure = false;
+    let first = ctx.problems.problems.len();
+    let r = (check.run)(ctx);
+    if !ctx.lab_config.only_warns(check.name) {
+        return r;
+    }
+    for problem in &mut ctx.problems.problems[first..] {
+        problem.severity = Severity::Warning;
+    }
+    Ok(())
+}
+
+/// Runs the checks in order, recording how each went.
+fn run_plan(ctx: &mut Context, plan: &[&Check], fail_fast: bool) -> CheckResult {
+    let mut result = Ok(());
+    for check in plan {
+        // Whatever the check would find is explained by the failed one.
+        let failed_dependency = check.after.iter().copied().find(|x| {
+            ctx.problems
+                .checks
+                .iter()
+                .any(|c| c.name == *x && !c.passed)
+        });
+        let skipped = if ctx.lab_config.skips(check.name) {
+            Some(SkipReason::Disabled)
+        } else if fail_fast && result.is_err() {
+            Some(SkipReason::FailFast)
+        } else {
+            failed_dependency.map(SkipReason::DependencyFailed)
+        };
+        if let Some(reason) = skipped {
+            ctx.problems.checks.push(CheckStatus {
+                name: check.name,
+                passed: true,
+                duration: Duration::ZERO,
+                partial: false,
+                skipped: Some(reason),
+                environment_failure: false,
+            });
+            continue;
+        }
+
+        let start = Instant::now();
+        ci::start_group(check.name);
+        let r = run_check(ctx, check);
+        ci::end_group();
+        ctx.problems.checks.push(CheckStatus {
+            name: check.name,
+            passed: r.is_ok(),
+            duration: start.elapsed(),
+            partial: check.scans_files && ctx.scope.base().is_some(),
+            skipped: ctx.skip_reason.take(),
+            environment_failure: std::mem::take(&mut ctx.environment_failure),
+        });
+        result = result.and(r);
+        if ctx.problems.live {
+            link_docs(ctx);
+            ctx.problems.print_new();
+        }
+    }
+    ctx.problems.current_check = None;
+    result
 }
 
-fn main_impl(problems: &mut Diags) -> CheckResult {
-    let args = Args::parse();
+/// Links the problems found so far to the course's pages about them.
+fn link_docs(ctx: &mut Context) {
+    for x in &mut ctx.problems.problems {
+        if x.url.is_none()
+            && let Some(check) = x.check
+        {
+            x.url = ctx.lab_config.docs.url(check);
+        }
+    }
+}
+
+/// Runs the checks, with cargo building into `target_dir` instead of the lab's own folder when
+/// it's given.
+fn run_checks(problems: &mut Diags, args: CheckArgs, target_dir: Option<&Utf8Path>) -> CheckResult {
+    let lab = args.lab.expect("required by clap");
+
+    validate_lab_name(problems, &lab)?;
+
+    let lab_config = load_config(
+        problems,
+        args.config.as_deref(),
+        &lab,
+        args.track.as_deref(),
+    )?;
+    if args.show_config {
+        lab_config.show(&lab, args.track.as_deref());
+        return Ok(());
+    }
+    cache::clean_old(lab_config.cache_max_age_days);
+    let plan = schedule::plan(CHECKS, &lab_config.check_order)
+        .map_err(|e| problems.add(e, args.config.clone(), None))?;
+    if args.show_plan {
+        schedule::show(&plan);
+        return Ok(());
+    }
+    problems.plan = plan.iter().map(|x| x.name).collect();
+    let repo = args.repo.expect("required by clap");
+    bug_report::add_run(&lab, args.track.as_deref(), &repo, &lab_config, &plan);
+    problems.repo = Some(repo.clone());
+    if let Some(dir) = &args.artifacts_dir {
+        fs::create_dir_all(dir)
+            .map_err(|e| problems.add(format!("can't create {dir}: {e}"), dir.clone(), None))?;
+        problems.artifacts_dir = Some(dir.clone());
+    }
 
-    validate_lab_name(problems, &args.lab)?;
+    if let Some(header) = lab_config.metadata.header(&lab) {
+        say!("{header}\n");
+    }
+    problems.lab = Some((lab.clone(), lab_config.metadata.clone()));
+    let skew = clock::skew(git::commit_time(&repo).ok());
+    if let Some(skew) = &skew {
+        problems.warn(
+            skew.clone(),
+            None,
+            Some("deadlines go by the commits' times, so check the date on the machines the commits were made on and on this one; `timedatectl set-ntp true` sets a Linux machine's clock from the network".into()),
+        );
+    }
+    // A missing repo is reported by the checks, and shouldn't get a lock.
+    let _lock = match repo.is_dir() {
+        true => Some(lock::acquire(problems, &repo, args.wait_for_lock)?),
+        false => None,
+    };
 
-    let lab_path = args.repo.join(args.lab);
+    let (lab_dir, lab_dir_result) = match lab_config.lab_dirs.is_empty() {
+        true => (lab.clone(), Ok(())),
+        false => find_lab_dir(problems, &repo, &lab, &lab_config.lab_dirs),
+    };
+    if lab_dir != lab {
+        say!("checking {lab} in folder `{lab_dir}`\n");
+    }
+    if !lab_config.lab_dirs.is_empty() {
+        problems.lab_dir = Some(lab_dir.clone());
+    }
+    let lab_path = repo.join(&lab_dir);
+    let scope = match &args.since {
+        Some(base) => Scope::since(problems, &repo, base),
+        None => Scope::Full,
+    };
+    problems.since = scope.base().map(String::from);
+    let budget = args
+        .soft_budget
+        .or(lab_config.soft_budget)
+        .map(Duration::from_secs);
+    let mut child_env = lab_config.child_env(&repo, &lab_path);
+    // Last, so it wins over the config's.
+    if let Some(dir) = target_dir {
+        child_env.push(("CARGO_TARGET_DIR".into(), dir.to_string()));
+    }
     let mut context = Context {
         problems,
-        repo_path: args.repo,
+        repo_path: repo,
+        lab: lab.clone(),
         lab_path,
+        lab_config,
         verbose: args.verbose,
+        build_timings: args.build_timings,
+        child_env,
+        env_overrides: Vec::new(),
+        writes_state: !state::redirected()
+            && (matches!(args.receipt, Some(None)) || budget.is_some() || args.track_flakiness),
+        scope,
+        git_health: None,
+        skip_reason: None,
+        environment_failure: false,
+        parsed_sources: None,
+        tests: None,
+        lab_targets: None,
     };
 
-    let mut result = Ok(());
-    for f in CHECKS {
-        let r = f(&mut context);
-        result = result.and(r);
+    let mut result = lab_dir_result.and(run_plan(&mut context, &plan, args.fail_fast));
+    link_docs(&mut context);
+
+    if let Some(other) = &args.compare {
+        similarity::compare(context.problems, &context.lab_path, &other.join(&lab_dir));
+    }
+
+    if let Some(budget) = budget {
+        let history = budget::load_history(&context.repo_path);
+        budget::report(context.problems, budget, &history);
+        if let Err(e) = budget::save_history(&context.repo_path, history, &context.problems.checks)
+        {
+            context.problems.warn(e, None, None);
+        }
     }
 
+    if args.track_flakiness {
+        flaky::track(&mut context);
+    }
+
+    if let Some(path) = &args.receipt {
+        let path = match path {
+            Some(x) => Ok(x.clone()),
+            None => state::create_state_path(&context.repo_path, state::RECEIPT_FILE),
+        };
+        let path = match path {
+            Ok(x) if x.starts_with(&context.lab_path) => {
+                return Err(context.problems.add(
+                    "receipts can't be written inside the lab folder",
+                    x,
+                    Some("leave out the path to write it to the `.checker` folder".into()),
+                ));
+            }
+            Ok(x) => x,
+            Err(e) => return Err(context.problems.add(e, None, None)),
+        };
+        // Receipts always get the real time, even with `--stable-output`, since they're
+        // evidence of when the run happened.
+        let receipt = Receipt::new(&context.repo_path, &lab, &context.problems.checks);
+        let secret = receipt::secret(context.lab_config.receipt_secret.as_deref());
+        if let Err(e) = receipt::write(&path, &receipt, secret.as_deref()) {
+            return Err(context.problems.add(e, path.clone(), None));
+        }
+        match secret {
+            Some(_) => say!("receipt written to {path}"),
+            None => say!(
+                "receipt written to {path}; it's unverifiable because no course secret is configured"
+            ),
+        }
+    }
+
+    if args.apply_fixes {
+        // The fixes' re-run decides the checks' part of the verdict, but not the rest of it.
+        result = lab_dir_result.and(fix::apply_fixes(&mut context, args.yes));
+        link_docs(&mut context);
+    }
+
+    if let Some(path) = &args.usage_log
+        && let Err(e) = usage_log::append(path, &lab, &context.problems.checks)
+    {
+        context.problems.warn(e, path.clone(), None);
+    }
+
+    let written = context
+        .problems
+        .artifacts
+        .iter()
+        .filter(|x| x.path.is_some());
+    if let (Some(dir), n @ 1..) = (&args.artifacts_dir, written.count()) {
+        say!("{n} artifacts written to {dir}");
+    }
+
+    if args.grade.is_some() {
+        let policy = &context.lab_config.grading;
+        if policy.points.is_empty() {
+            return Err(context.problems.add(
+                "the config doesn't give points to any check, so there's nothing to grade",
+                None,
+                Some("add a `[defaults.points]` table, like `clippy = 10`".into()),
+            ));
+        }
+        let submitted_at = git::commit_time(&context.repo_path).ok();
+        context.problems.grade = Some(grade::evaluate(
+            policy,
+            &context.problems.checks,
+            &context.problems.test_results,
+            submitted_at,
+            skew,
+        ));
+    }
+
+    if let Some(path) = &args.badge {
+        if readonly::inside(&context.lab_path, path) {
+            return Err(context.problems.add(
+                "badges can't be written inside the lab folder",
+                path.clone(),
+                Some("write it next to the README at the repo root".into()),
+            ));
+        }
+        if let Err(e) = badge::write(path, context.problems) {
+            return Err(context.problems.add(e, path.clone(), None));
+        }
+    }
+
+    result
+}
+
+/// `--read-only`: sends everything the run writes elsewhere, and makes sure the repo didn't
+/// change anyway.
+fn run_read_only(problems: &mut Diags, args: CheckArgs) -> CheckResult {
+    // A missing repo is reported by the checks.
+    let Some(repo) = args.repo.clone().filter(|x| x.is_dir()) else {
+        return run_checks(problems, args, None);
+    };
+    for path in [
+        args.receipt.clone().flatten(),
+        args.badge.clone(),
+        args.usage_log.clone(),
+        args.bug_report.clone(),
+        args.artifacts_dir.as_ref().map(|x| x.join("artifact")),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if readonly::inside(&repo, &path) {
+            return Err(problems.add(
+                "the run is read-only, so it can't write files into the repo",
+                path,
+                Some("write it somewhere outside the repo".into()),
+            ));
+        }
+    }
+    let dir = readonly::cache_dir(&repo).map_err(|e| problems.add(e, None, None))?;
+    state::redirect(dir.join("state"));
+    if args.verbose {
+        say!("read-only: keeping state files and builds in {dir}");
+    }
+
+    let target_dir = dir.join("target");
+    unchanged(problems, &repo, |problems| {
+        run_checks(problems, args, Some(&target_dir))
+    })
+}
+
+/// Runs `run`, and fails if the files in `repo` changed meanwhile.
+fn unchanged(
+    problems: &mut Diags,
+    repo: &Utf8Path,
+    run: impl FnOnce(&mut Diags) -> CheckResult,
+) -> CheckResult {
+    let before = readonly::snapshot(repo);
+    let mut result = run(problems);
+    match (before, readonly::snapshot(repo)) {
+        (Ok(before), Ok(after)) => {
+            let changed = readonly::changes(&before, &after);
+            if !changed.is_empty() {
+                result = Err(problems.add(
+                    format!(
+                        "internal error: {} files in the repo changed during a read-only run",
+                        changed.len()
+                    ),
+                    changed.iter().map(|x| repo.join(x)).collect::<Vec<_>>(),
+                    Some("this is a bug in the checker, or the lab's code wrote them when a check ran it; please report it".into()),
+                ));
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => problems.warn(
+            format!("can't tell whether the read-only run left the repo unchanged: {e}"),
+            repo.to_owned(),
+            None,
+        ),
+    }
     result
 }
 
+fn main_impl(problems: &mut Diags, args: Args) -> CheckResult {
+    match args.command {
+        Some(Command::VerifyReceipt {
+            receipt,
+            repo,
+            rev,
+            config,
+        }) => {
+            let lab = receipt::read_lab(&receipt).unwrap_or_default();
+            let lab_config = load_config(problems, config.as_deref(), &lab, None)?;
+            let secret = receipt::secret(lab_config.receipt_secret.as_deref());
+            receipt::verify(problems, &receipt, &repo, &rev, secret.as_deref())
+        }
+        Some(Command::Init {
+            lab,
+            repo,
+            config,
+            force,
+        }) => init::init(problems, repo, &lab, config.as_deref(), force),
+        Some(Command::Selftest { verbose }) => selftest::selftest(problems, verbose),
+        Some(Command::SplitEmit {
+            input,
+            section,
+            out_dir,
+        }) => emit::split_emit(input.as_deref(), section.as_deref(), out_dir.as_deref())
+            .map_err(|e| problems.add(e, input, None)),
+        Some(Command::UsageReport { log, lab }) => {
+            usage_log::report(&log, lab.as_deref()).map_err(|e| problems.add(e, log, None))
+        }
+        Some(Command::Cache { action }) => {
+            let result = match action {
+                CacheCommand::Dir => cache::root().map(|x| say!("{x}")),
+                CacheCommand::Size => cache::show_size(),
+                CacheCommand::Clean {
+                    older_than,
+                    category,
+                } => {
+                    let categories = match category {
+                        Some(x) => vec![x],
+                        None => cache::Category::ALL.to_vec(),
+                    };
+                    cache::clean(older_than.unwrap_or(0), &categories)
+                }
+            };
+            result.map_err(|e| problems.add(e, None, None))
+        }
+        Some(Command::Grade { .. }) => unreachable!("`grade` is turned into a check run"),
+        None if args.check.read_only => run_read_only(problems, args.check),
+        None => run_checks(problems, args.check, None),
+    }
+}
+
 fn main() -> ExitCode {
-    let mut problems = Diags::default();
-    let r = main_impl(&mut problems);
-    problems.print();
+    let code = run();
+    bug_report::write();
+    code
+}
 
-    let (result_text, ret) = match r {
-        Ok(_) => ("success".green(), ExitCode::SUCCESS),
-        Err(_) => ("failure".red(), ExitCode::FAILURE),
+fn run() -> ExitCode {
+    let mut args = Args::parse();
+    // `grade` is a check run that also works out the grade, so it's handled like one.
+    match args.command.take() {
+        Some(Command::Grade { check, csv }) => {
+            args.check = *check;
+            args.check.grade = Some(match csv {
+                true => grade::Output::Csv,
+                false => grade::Output::Report,
+            });
+        }
+        command => args.command = command,
+    }
+    let grade_csv = matches!(args.check.grade, Some(grade::Output::Csv));
+    let format = match args.command {
+        Some(_) => Format::Human,
+        None => args.check.format,
+    };
+    let verbose = args.check.verbose;
+    let timings = args.check.timings;
+    let emit = args.check.emit.clone();
+    let expect_path = args.check.expect.clone();
+    let stable = args.check.stable_output;
+    // Runs that only print the config or the plan, and check nothing.
+    let shows_only = args.command.is_none() && (args.check.show_config || args.check.show_plan);
+    // When the run started, if it should notify when it's done.
+    #[cfg(feature = "notify")]
+    let notify = (args.check.notify && args.command.is_none()).then(Instant::now);
+    if !emit.is_empty()
+        || grade_csv
+        || matches!(format, Format::Json | Format::Short)
+        || matches!(args.command, Some(Command::SplitEmit { .. }))
+    {
+        output::send_to_stderr();
+    }
+    // Editors show the lines as they are, escape codes included.
+    if matches!(format, Format::Short) && env::var_os("CLICOLOR_FORCE").is_none() {
+        colored::control::set_override(false);
+    }
+
+    if let Some(path) = &args.check.bug_report {
+        bug_report::start(path.clone());
+    }
+
+    let max_problems = args.check.max_problems.filter(|_| !args.check.full);
+    let ci = args.check.ci;
+    if ci.is_some() {
+        ci::enable_groups();
+    }
+
+    let mut problems = Diags {
+        live: output::is_terminal()
+            && args.command.is_none()
+            && !args.check.print_at_end
+            && matches!(format, Format::Human)
+            && emit.is_empty()
+            && max_problems.is_none()
+            && ci.is_none(),
+        ..Diags::default()
+    };
+    let r = main_impl(&mut problems, args);
+    if let Some(max) = max_problems {
+        problems.hide_extra_problems(max);
+    }
+    problems.group_root_causes();
+    #[cfg(feature = "notify")]
+    if let Some(start) = notify
+        && start.elapsed() >= notify::NOTIFY_AFTER
+    {
+        let verdict = if r.is_ok() { "success" } else { "failure" };
+        let lab = problems.lab.as_ref().map_or("the lab", |x| x.0.as_str());
+        let count = match problems.problems.len() {
+            1 => "1 problem".to_string(),
+            n => format!("{n} problems"),
+        };
+        notify::send(&format!("checker finished: {verdict} — {count} in {lab}"));
+    }
+    // Loaded after the run so a bad file is reported like any other problem, but kept out of
+    // the comparison.
+    let expectations = expect_path.map(|path| {
+        expect::load(&path).map_err(|e| {
+            problems.add(e, path, None);
+        })
+    });
+    let differences = match &expectations {
+        Some(Ok(x)) => Some(expect::differences(&problems, x)),
+        _ => None,
+    };
+    let environment_failure = problems.environment_failure();
+    let exit_code = |ok: bool| match (&expectations, &differences) {
+        (Some(_), Some(x)) if x.is_empty() => ExitCode::SUCCESS,
+        (Some(_), _) => ExitCode::FAILURE,
+        (None, _) if ok => ExitCode::SUCCESS,
+        (None, _) if environment_failure => ExitCode::from(ENVIRONMENT_FAILURE),
+        (None, _) => ExitCode::FAILURE,
     };
-    println!("\nchecker finished with result: {}", result_text);
+
+    if ci == Some(ci::Ci::Github)
+        && let Err(e) = ci::finish(&problems, r.is_ok(), stable)
+    {
+        problems.warn(e, None, None);
+    }
+    if bug_report::enabled() {
+        let report = report::json(&problems, r.is_ok(), stable);
+        bug_report::add("report.json", "the JSON report", report.to_string());
+    }
+
+    match format {
+        Format::Json => {
+            let _ = writeln!(
+                output::Sink::stdout(),
+                "{}",
+                report::json(&problems, r.is_ok(), stable)
+            );
+            return exit_code(r.is_ok());
+        }
+        Format::Short => {
+            let _ = write!(output::Sink::stdout(), "{}", report::short(&problems));
+            return exit_code(r.is_ok());
+        }
+        Format::Human => {}
+    }
+    // A summary would suggest the checks ran.
+    if shows_only && r.is_ok() {
+        return exit_code(true);
+    }
+    if timings {
+        problems.print_timings();
+    }
+    problems.print(verbose);
+    if let Some(grade) = &problems.grade {
+        say!();
+        grade.print();
+    }
+
+    let result_text = match r {
+        Ok(_) => "success".green(),
+        Err(_) => "failure".red(),
+    };
+    say!("\nchecker finished with result: {}", result_text);
+    if let Some(differences) = &differences {
+        match differences.is_empty() {
+            true => say!("the run found exactly the expected problems"),
+            false => {
+                say!("the run doesn't match the expectations:");
+                for x in differences {
+                    say!("  {x}");
+                }
+            }
+        }
+    }
+    let ret = exit_code(r.is_ok());
+
+    let mut stdout = output::Sink::stdout();
+    if grade_csv
+        && let (Some(grade), Some(repo), Some((lab, _))) =
+            (&problems.grade, &problems.repo, &problems.lab)
+    {
+        let _ = write!(stdout, "{}", grade.csv(repo, lab, stable));
+    }
+    for format in emit {
+        let body = match format {
+            EmitFormat::Json => report::json(&problems, r.is_ok(), stable).to_string(),
+        };
+        let _ = emit::write_section(&mut stdout, format, &body);
+    }
 
     ret
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_skip() -> Diags {
+        let reasons = [
+            ("disabled", SkipReason::Disabled),
+            ("not_configured", SkipReason::NotConfigured),
+            (
+                "not_applicable",
+                SkipReason::NotApplicable("the lab has no binary"),
+            ),
+            ("dependency_failed", SkipReason::DependencyFailed("compile")),
+            ("fail_fast", SkipReason::FailFast),
+            (
+                "missing_tool",
+                SkipReason::MissingTool("cargo-audit".into()),
+            ),
+        ];
+        let checks = reasons
+            .into_iter()
+            .map(|(name, reason)| CheckStatus {
+                name,
+                passed: true,
+                duration: Duration::ZERO,
+                partial: false,
+                skipped: Some(reason),
+                environment_failure: false,
+            })
+            .collect();
+        Diags {
+            checks,
+            ..Diags::default()
+        }
+    }
+
+    const TEXTS: [(&str, &str); 6] = [
+        ("disabled", "disabled by the course config"),
+        ("not_configured", "not set up for this lab"),
+        ("not_applicable", "the lab has no binary"),
+        ("dependency_failed", "`compile` failed"),
+        ("fail_fast", "an earlier check failed and --fail-fast is on"),
+        ("missing_tool", "cargo-audit not installed"),
+    ];
+
+    #[test]
+    fn summary_lists_skips_the_student_can_act_on() {
+        colored::control::set_override(false);
+        let problems = every_skip();
+        let quiet = output::capture(|| problems.print(false));
+        let verbose = output::capture(|| problems.print(true));
+        for (name, text) in TEXTS {
+            let line = format!("  {name}: skipped: {text}\n");
+            let benign = matches!(name, "disabled" | "not_configured" | "not_applicable");
+            assert_eq!(quiet.contains(&line), !benign, "{name}");
+            assert!(verbose.contains(&line), "{name}");
+        }
+    }
+
+    #[test]
+    fn json_has_every_skip_reason() {
+        let report = report::json(&every_skip(), true, true).to_string();
+        for (name, text) in TEXTS {
+            let quoted = json::Json::from(text).to_string();
+            assert!(report.contains(&format!("\"{name}\"")), "{name}");
+            assert!(report.contains(&format!("\"skipped\": {quoted}")), "{name}");
+        }
+    }
+
+    #[test]
+    fn github_summary_has_every_skip_reason() {
+        let summary = ci::summary(&every_skip(), true, false);
+        for (name, text) in TEXTS {
+            assert!(
+                summary.contains(&format!("| {name} | skipped: {text} | 0.0s |")),
+                "{name}"
+            );
+        }
+    }
+
+    /// A repo with a committed file, an ignored file, and an ignored folder.
+    fn repo(name: &str) -> temp::TempDir {
+        let dir = temp::TempDir::new(name).unwrap();
+        let repo = dir.path();
+        fs::create_dir_all(repo.join("target/debug")).unwrap();
+        fs::write(repo.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(repo.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(repo.join("run.log"), "old\n").unwrap();
+        fs::write(repo.join("target/debug/lab01"), "binary").unwrap();
+        exec::Exec::new("git")
+            .args(["init", "-q"])
+            .cwd(repo)
+            .run()
+            .unwrap();
+        dir
+    }
+
+    fn changed_by(name: &str, write: impl FnOnce(&Utf8Path)) -> Vec<String> {
+        let dir = repo(name);
+        let mut problems = Diags::default();
+        let result = unchanged(&mut problems, dir.path(), |_| {
+            write(dir.path());
+            Ok(())
+        });
+        let Some(problem) = problems.problems.first() else {
+            assert!(result.is_ok());
+            return Vec::new();
+        };
+        assert!(result.is_err());
+        assert!(problem.text.contains("changed during a read-only run"));
+        let paths = problem.paths.iter();
+        paths
+            .map(|x| x.strip_prefix(dir.path()).unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn tripwire_fires_on_writes_into_the_repo() {
+        assert!(changed_by("tripwire_nothing", |_| {}).is_empty());
+        let changed = changed_by("tripwire_source", |repo| {
+            fs::write(repo.join("main.rs"), "fn main() { todo!() }\n").unwrap();
+            fs::write(repo.join("new.rs"), "").unwrap();
+        });
+        assert_eq!(changed, ["main.rs", "new.rs"]);
+    }
+
+    #[test]
+    fn tripwire_sees_ignored_files() {
+        let changed = changed_by("tripwire_ignored", |repo| {
+            fs::write(repo.join("run.log"), "new\n").unwrap();
+            fs::write(repo.join("other.log"), "").unwrap();
+        });
+        assert_eq!(changed, ["other.log", "run.log"]);
+        let changed = changed_by("tripwire_ignored_folder", |repo| {
+            fs::write(repo.join("target/debug/lab01"), "rebuilt binary").unwrap();
+        });
+        assert_eq!(changed, ["target"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn paths_that_arent_utf8_are_explained() {
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let path = OsString::from_vec(b"/home/student/lab\xff01".to_vec());
+        let args = [
+            "rust_course_helper".into(),
+            "--lab".into(),
+            "lab01".into(),
+            "--repo".into(),
+            path,
+        ];
+        let e = Args::try_parse_from(args).err().unwrap();
+        assert_eq!(e.kind(), clap::error::ErrorKind::InvalidUtf8);
+        let message = e.to_string();
+        assert!(
+            message.contains("`/home/student/lab\u{FFFD}01`"),
+            "{message}"
+        );
+        assert!(message.contains("isn't valid UTF-8"), "{message}");
+
+        let valid: [OsString; 5] =
+            ["rust_course_helper", "--lab", "lab01", "--repo", "/tmp/lab"].map(OsString::from);
+        assert!(Args::try_parse_from(valid).is_ok());
+    }
+}
@@ -0,0 +1,63 @@
+//! `--notify`: a desktop notification when a long run finishes, so students can look away.
+//!
+//! Notifications go through what the platform already has: `notify-send` on Linux and BSDs,
+//! `osascript` on macOS, and a PowerShell balloon on Windows. Without a desktop session, like
+//! over SSH or in CI, nothing is sent, and failures are ignored.
+
+use crate::exec::{Exec, Stream};
+use std::env;
+use std::time::Duration;
+
+/// Runs shorter than this are watched anyway, so they don't notify.
+pub const NOTIFY_AFTER: Duration = Duration::from_secs(30);
+/// How long sending may take before the checker stops waiting for it.
+const SEND_TIMEOUT: Duration = Duration::from_secs(3);
+const TITLE: &str = "rust_course_helper";
+
+fn has_desktop() -> bool {
+    if env::var_os("CI").is_some() || env::var_os("SSH_CONNECTION").is_some() {
+        return false;
+    }
+    if cfg!(any(target_os = "macos", windows)) {
+        return true;
+    }
+    env::var_os("DISPLAY").is_some() || env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+fn command(body: &str) -> Exec {
+    if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{TITLE}\"",
+            body.replace(['"', '\\'], "")
+        );
+        Exec::new("osascript").args(["-e", &script])
+    } else if cfg!(windows) {
+        let body = body.replace('\'', "''");
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+            $n = New-Object System.Windows.Forms.NotifyIcon; \
+            $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+            $n.ShowBalloonTip(5000, '{TITLE}', '{body}', 'Info'); Start-Sleep 6; $n.Dispose()"
+        );
+        Exec::new("powershell").args(["-NoProfile", "-NonInteractive", "-Command", &script])
+    } else {
+        Exec::new("notify-send").args(["--app-name", TITLE, TITLE, body])
+    }
+}
+
+/// Sends `body` as a notification, if there's anywhere to send it.
+pub fn send(body: &str) {
+    if !has_desktop() {
+        return;
+    }
+    let exec = command(body)
+        .stdin_null()
+        .stdout(Stream::Discard)
+        .stderr(Stream::Discard)
+        .timeout(SEND_TIMEOUT);
+    // The Windows balloon stays up for a while; it doesn't need the checker to wait.
+    let _ = match cfg!(windows) {
+        true => exec.spawn().map(drop),
+        false => exec.run().map(drop),
+    };
+}
@@ -0,0 +1,144 @@
+//! Where human-readable output goes.
+//!
+//! Normally that's stdout, but when stdout carries machine-readable reports (`--emit`) it has
+//! to stay clean, so everything meant for people goes to stderr instead.
+//!
+//! Everything printed goes through `Sink`. When a stream can't be written, like when it's piped
+//! into `head` and `head` is done, output to it stops but the run goes on, so the exit code is
+//! still the verdict. Errors other than the reader going away are reported on stderr once.
+//!
+//! For `--bug-report`, everything written is also kept in a log, and `capture` takes what
+//! something prints instead of printing it.
+
+use std::fmt;
+use std::io::{self, ErrorKind, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+/// Set once a stream couldn't be written.
+static STDOUT_GONE: AtomicBool = AtomicBool::new(false);
+static STDERR_GONE: AtomicBool = AtomicBool::new(false);
+/// Everything written so far, once `keep_log` was called.
+static LOG: Mutex<Option<String>> = Mutex::new(None);
+/// What `say!` printed while `capture` ran.
+static CAPTURED: Mutex<Option<String>> = Mutex::new(None);
+
+/// Still usable after a panic while it was held, since the panic hook reads the log.
+fn lock(x: &Mutex<Option<String>>) -> MutexGuard<'_, Option<String>> {
+    x.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn keep_log() {
+    *lock(&LOG) = Some(String::new());
+}
+
+/// What was written since `keep_log`.
+pub fn log() -> Option<String> {
+    lock(&LOG).clone()
+}
+
+/// What `f` prints with `say!`, instead of printing it.
+pub fn capture(f: impl FnOnce()) -> String {
+    *lock(&CAPTURED) = Some(String::new());
+    f();
+    lock(&CAPTURED).take().unwrap_or_default()
+}
+
+pub fn send_to_stderr() {
+    TO_STDERR.store(true, Ordering::Relaxed);
+}
+
+pub fn is_stderr() -> bool {
+    TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// Whether human-readable output goes to a terminal.
+pub fn is_terminal() -> bool {
+    match is_stderr() {
+        true => io::stderr().is_terminal(),
+        false => io::stdout().is_terminal(),
+    }
+}
+
+pub struct Sink {
+    stderr: bool,
+}
+
+impl Sink {
+    /// Where human-readable output goes.
+    pub fn human() -> Sink {
+        Sink {
+            stderr: is_stderr(),
+        }
+    }
+
+    /// Stdout, for machine-readable reports.
+    pub fn stdout() -> Sink {
+        Sink { stderr: false }
+    }
+
+    fn gone(&self) -> &'static AtomicBool {
+        match self.stderr {
+            true => &STDERR_GONE,
+            false => &STDOUT_GONE,
+        }
+    }
+}
+
+impl Write for Sink {
+    /// Never fails: output that can't be written is dropped.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Some(log) = lock(&LOG).as_mut() {
+            log.push_str(&String::from_utf8_lossy(buf));
+        }
+        if self.gone().load(Ordering::Relaxed) {
+            return Ok(buf.len());
+        }
+        let result = match self.stderr {
+            true => io::stderr().lock().write_all(buf),
+            false => io::stdout().lock().write_all(buf),
+        };
+        if let Err(e) = result {
+            self.gone().store(true, Ordering::Relaxed);
+            if e.kind() != ErrorKind::BrokenPipe && !self.stderr {
+                let _ = writeln!(Sink { stderr: true }, "can't write to stdout: {e}");
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = match self.stderr {
+            true => io::stderr().flush(),
+            false => io::stdout().flush(),
+        };
+        Ok(())
+    }
+}
+
+/// What `say!` and `say_inline!` use.
+pub fn print(args: fmt::Arguments) {
+    if let Some(captured) = lock(&CAPTURED).as_mut() {
+        let _ = fmt::write(captured, args);
+        return;
+    }
+    let _ = Sink::human().write_fmt(args);
+}
+
+/// `println!` for human-readable output.
+macro_rules! say {
+    () => {
+        $crate::output::print(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+/// `print!` for human-readable output.
+macro_rules! say_inline {
+    ($($arg:tt)*) => {
+        $crate::output::print(format_args!($($arg)*))
+    };
+}
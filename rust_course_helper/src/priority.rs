@@ -0,0 +1,113 @@
+//! `--max-problems`: which problems are shown when there are too many to read.
+//!
+//! Problems are ranked by their check's category, then errors before warnings, then in the
+//! order they were found, and only the first ones are printed. Reports always have all of them.
+
+use crate::checks::{CHECKS, Category};
+use crate::{Diag, Diags, Severity};
+
+/// Problems found outside a check, like a bad config, stop everything else.
+fn category(problem: &Diag) -> Category {
+    problem
+        .check
+        .and_then(|x| CHECKS.iter().find(|c| c.name == x))
+        .map_or(Category::Environment, |x| x.category)
+}
+
+/// Indices of the `max` most important problems, in the order they were found.
+pub fn select(problems: &[Diag], max: usize) -> Vec<usize> {
+    let mut ranked: Vec<usize> = (0..problems.len()).collect();
+    ranked.sort_by_key(|&i| {
+        let x = &problems[i];
+        (category(x), x.severity == Severity::Warning, i)
+    });
+    ranked.truncate(max);
+    ranked.sort();
+    ranked
+}
+
+impl Diags {
+    /// Runs before grouping, so which problems are shown doesn't depend on how they're grouped.
+    pub fn hide_extra_problems(&mut self, max: usize) {
+        let shown = select(&self.problems, max);
+        for (i, x) in self.problems.iter_mut().enumerate() {
+            x.hidden = !shown.contains(&i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Problems from `found`, each a check, or `None` for the checker itself, and whether it's
+    /// only a warning.
+    fn problems(found: &[(Option<&'static str>, bool)]) -> Vec<Diag> {
+        let mut problems = Diags::default();
+        for (i, (check, warning)) in found.iter().enumerate() {
+            problems.current_check = *check;
+            match warning {
+                true => problems.warn(format!("problem {i}"), None, None),
+                false => _ = problems.add(format!("problem {i}"), None, None),
+            }
+        }
+        problems.problems
+    }
+
+    #[test]
+    fn earlier_categories_come_first() {
+        let found = problems(&[
+            (Some("fmt"), false),
+            (Some("tests"), false),
+            (Some("clippy"), false),
+            (Some("compiler_warnings"), false),
+            (None, false),
+            (Some("git_repo"), false),
+        ]);
+        // The checker's own problems count as the environment's.
+        assert_eq!(select(&found, 2), [4, 5]);
+        assert_eq!(select(&found, 3), [3, 4, 5]);
+        assert_eq!(select(&found, 5), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn errors_come_before_warnings() {
+        let found = problems(&[
+            (Some("clippy"), true),
+            (Some("fmt"), false),
+            (Some("clippy"), false),
+        ]);
+        assert_eq!(select(&found, 1), [2]);
+        assert_eq!(select(&found, 2), [0, 2]);
+        // Category outranks severity.
+        let found = problems(&[(Some("fmt"), false), (Some("compiler_warnings"), true)]);
+        assert_eq!(select(&found, 1), [1]);
+    }
+
+    #[test]
+    fn ties_keep_the_order_they_were_found_in() {
+        let found = problems(&[
+            (Some("fmt"), false),
+            (Some("line_length"), false),
+            (Some("fmt"), false),
+        ]);
+        assert_eq!(select(&found, 2), [0, 1]);
+    }
+
+    #[test]
+    fn limits() {
+        let found = problems(&[(Some("fmt"), false), (Some("clippy"), true)]);
+        assert!(select(&found, 0).is_empty());
+        assert_eq!(select(&found, 1), [1]);
+        assert_eq!(select(&found, 10), [0, 1]);
+        assert!(select(&[], 1).is_empty());
+
+        let mut problems = Diags {
+            problems: found,
+            ..Diags::default()
+        };
+        problems.hide_extra_problems(1);
+        let hidden: Vec<bool> = problems.problems.iter().map(|x| x.hidden).collect();
+        assert_eq!(hidden, [true, false]);
+    }
+}
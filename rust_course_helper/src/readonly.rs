@@ -0,0 +1,127 @@
+//! `--read-only`: runs that leave the student's repo exactly as it was.
+//!
+//! State files and builds go to a folder in the system's temporary folder instead, one per
+//! repo so histories still carry over between runs. Fixes, and reports written into the repo,
+//! are refused. The repo's files are compared at the end with how they were at the start, and
+//! any difference fails the run, since it means a write slipped past all that.
+
+use crate::cache;
+use crate::exec::Exec;
+use crate::sha256::{sha256, to_hex};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::UNIX_EPOCH;
+
+/// Where a read-only run of `repo` keeps what it would have written there.
+pub fn cache_dir(repo: &Utf8Path) -> Result<Utf8PathBuf, String> {
+    let dir = cache::root()?.join(cache::repo_id(repo));
+    fs::create_dir_all(&dir).map_err(|e| format!("can't create {dir}: {e}"))?;
+    cache::touch(&dir)?;
+    Ok(dir)
+}
+
+/// Whether `path` is somewhere in `repo`, whether it exists yet or not.
+pub fn inside(repo: &Utf8Path, path: &Utf8Path) -> bool {
+    let canonical = |x: &Utf8Path| x.canonicalize_utf8().unwrap_or_else(|_| x.to_owned());
+    let parent = match path.parent() {
+        Some(x) if !x.as_str().is_empty() => x,
+        _ => Utf8Path::new("."),
+    };
+    canonical(parent).starts_with(canonical(repo))
+}
+
+/// The files git sees as changed, untracked and ignored ones included, with a hash of what's in
+/// them. Files that aren't listed are as committed, so they don't need hashing. Ignored folders,
+/// like an old `target/`, can be huge, so what's in them is compared by size and modification
+/// time instead.
+#[derive(PartialEq, Eq)]
+pub struct Snapshot(BTreeMap<String, String>);
+
+pub fn snapshot(repo: &Utf8Path) -> Result<Snapshot, String> {
+    // Without optional locks, git doesn't refresh the index, so looking doesn't write.
+    let output = Exec::new("git")
+        .args([
+            "--no-optional-locks",
+            "status",
+            "--porcelain=v1",
+            "-z",
+            "--untracked-files=all",
+            "--ignored=matching",
+        ])
+        .cwd(repo)
+        .stdin_null()
+        .limit(usize::MAX)
+        .run()
+        .map_err(|e| format!("git failed: {e}"))?;
+    if !output.success() {
+        return Err(format!(
+            "`git status` failed: {}",
+            output.stderr.text().trim()
+        ));
+    }
+    // Not trimmed: the status of the first file can start with a space.
+    let status = output.stdout.text();
+    let mut files = BTreeMap::new();
+    let mut entries = status.split('\0').filter(|x| !x.is_empty());
+    while let Some(entry) = entries.next() {
+        let Some((code, path)) = entry.split_at_checked(3) else {
+            continue;
+        };
+        // Renames and copies are followed by the path they came from.
+        if code.contains(['R', 'C']) {
+            entries.next();
+        }
+        let contents = match path.ends_with('/') {
+            true => to_hex(&sha256(listing(&repo.join(path)).as_bytes())),
+            false => match fs::read(repo.join(path)) {
+                Ok(x) => to_hex(&sha256(&x)),
+                Err(_) => "missing".into(),
+            },
+        };
+        files.insert(path.to_string(), format!("{}{contents}", code.trim()));
+    }
+    Ok(Snapshot(files))
+}
+
+/// Each file in `dir` and the folders in it, with its size and modification time.
+fn listing(dir: &Utf8Path) -> String {
+    fn add(listing: &mut String, dir: &Utf8Path, prefix: &str) {
+        let Ok(entries) = dir.read_dir_utf8() else {
+            return;
+        };
+        let mut entries: Vec<_> = entries.flatten().collect();
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
+        for entry in entries {
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            let path = format!("{prefix}{}", entry.file_name());
+            if metadata.is_dir() {
+                add(listing, entry.path(), &format!("{path}/"));
+                continue;
+            }
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |x| x.as_nanos());
+            *listing += &format!("{path} {} {modified}\n", metadata.len());
+        }
+    }
+    let mut listing = String::new();
+    add(&mut listing, dir, "");
+    listing
+}
+
+/// The files that changed between the two snapshots.
+pub fn changes(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut paths: Vec<&String> = before.0.keys().chain(after.0.keys()).collect();
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .filter(|x| before.0.get(*x) != after.0.get(*x))
+        .cloned()
+        .collect()
+}
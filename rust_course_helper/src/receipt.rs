@@ -0,0 +1,317 @@
+//! Receipts prove that a run happened on a given commit with a given result.
+//!
+//! The receipt is a small TOML file. When a course secret is available its fields are signed
+//! with HMAC-SHA-256, so a student can't edit a failing receipt into a passing one.
+
+use crate::sha256::{hmac_sha256, to_hex};
+use crate::state;
+use crate::toml::{self, Table, Value};
+use crate::{CheckResult, CheckStatus, Diags, clock, git};
+use camino::Utf8Path;
+use std::fmt::Write as _;
+use std::fs;
+
+/// Environment variable that takes precedence over `receipt_secret` from the config.
+pub const SECRET_ENV: &str = "RUST_COURSE_HELPER_SECRET";
+const FORMAT: &str = "rust_course_helper receipt v1";
+
+pub struct Receipt {
+    pub checker_version: String,
+    pub commit: String,
+    pub lab: String,
+    pub timestamp: u64,
+    pub checks: Vec<(String, bool)>,
+}
+
+impl Receipt {
+    pub fn new(repo: &Utf8Path, lab: &str, checks: &[CheckStatus]) -> Receipt {
+        Receipt {
+            checker_version: env!("CARGO_PKG_VERSION").to_string(),
+            commit: git::head_commit(repo).unwrap_or_else(|_| "unknown".to_string()),
+            lab: lab.to_string(),
+            timestamp: clock::now(),
+            checks: checks
+                .iter()
+                .filter(|x| x.ran())
+                .map(|x| (x.name.to_string(), x.passed))
+                .collect(),
+        }
+    }
+
+    /// The exact bytes that get signed. Every field is included, so any edit is detected.
+    fn signed_message(&self) -> String {
+        let mut s = format!(
+            "{FORMAT}\nchecker_version={}\ncommit={}\nlab={}\ntimestamp={}\n",
+            self.checker_version, self.commit, self.lab, self.timestamp
+        );
+        let mut checks = self.checks.clone();
+        checks.sort();
+        for (name, passed) in checks {
+            writeln!(s, "check.{name}={}", status(passed)).expect("writing to a string");
+        }
+        s
+    }
+
+    pub fn sign(&self, secret: &str) -> String {
+        to_hex(&hmac_sha256(
+            secret.as_bytes(),
+            self.signed_message().as_bytes(),
+        ))
+    }
+
+    pub fn render(&self, secret: Option<&str>) -> String {
+        let mut s = format!(
+            "format = {FORMAT:?}\nchecker_version = {:?}\ncommit = {:?}\nlab = {:?}\ntimestamp = {}\n",
+            self.checker_version, self.commit, self.lab, self.timestamp
+        );
+        match secret {
+            Some(secret) => writeln!(s, "hmac = {:?}", self.sign(secret)),
+            None => writeln!(s, "unverifiable = true"),
+        }
+        .expect("writing to a string");
+        s += "\n[checks]\n";
+        for (name, passed) in &self.checks {
+            writeln!(s, "{name} = {:?}", status(*passed)).expect("writing to a string");
+        }
+        s
+    }
+
+    fn parse(table: &Table) -> Result<Receipt, String> {
+        let string = |key: &str| {
+            table
+                .get(key)
+                .and_then(Value::as_str)
+                .map(String::from)
+                .ok_or_else(|| format!("receipt has no `{key}`"))
+        };
+        if string("format")? != FORMAT {
+            return Err("receipt has an unknown format".into());
+        }
+        let timestamp = match table.get("timestamp") {
+            Some(Value::Integer(x)) if *x >= 0 => *x as u64,
+            _ => return Err("receipt has no valid `timestamp`".into()),
+        };
+        let Some(Value::Table(checks)) = table.get("checks") else {
+            return Err("receipt has no `checks`".into());
+        };
+        let checks = checks
+            .iter()
+            .map(|(name, value)| match value.as_str() {
+                Some("passed") => Ok((name.clone(), true)),
+                Some("failed") => Ok((name.clone(), false)),
+                _ => Err(format!("check `{name}` has an invalid status")),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Receipt {
+            checker_version: string("checker_version")?,
+            commit: string("commit")?,
+            lab: string("lab")?,
+            timestamp,
+            checks,
+        })
+    }
+}
+
+fn status(passed: bool) -> &'static str {
+    if passed { "passed" } else { "failed" }
+}
+
+/// Compares in constant time so the signature can't be guessed byte by byte.
+fn same_signature(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+pub fn write(path: &Utf8Path, receipt: &Receipt, secret: Option<&str>) -> Result<(), String> {
+    state::write_atomic(path, &receipt.render(secret))
+}
+
+/// Resolves the secret from the environment first, then from the config.
+pub fn secret(config_secret: Option<&str>) -> Option<String> {
+    std::env::var(SECRET_ENV)
+        .ok()
+        .filter(|x| !x.is_empty())
+        .or_else(|| config_secret.map(String::from))
+}
+
+/// Reads just the lab from a receipt, so the right config can be loaded to verify it.
+pub fn read_lab(path: &Utf8Path) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let table = toml::parse(&text).ok()?;
+    table.get("lab")?.as_str().map(String::from)
+}
+
+/// Verifies the receipt at `path` against the pushed repo at `repo`.
+pub fn verify(
+    problems: &mut Diags,
+    path: &Utf8Path,
+    repo: &Utf8Path,
+    rev: &str,
+    secret: Option<&str>,
+) -> CheckResult {
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::parse(&text).map_err(|e| e.to_string()))
+        .and_then(|table| Ok((Receipt::parse(&table)?, table)));
+    let (receipt, table) = match parsed {
+        Ok(x) => x,
+        Err(e) => {
+            return Err(problems.add(format!("receipt is invalid: {e}"), path.to_owned(), None));
+        }
+    };
+
+    let Some(hmac) = table.get("hmac").and_then(Value::as_str) else {
+        return Err(problems.add(
+            "receipt was produced without a course secret and can't be verified",
+            path.to_owned(),
+            None,
+        ));
+    };
+    let Some(secret) = secret else {
+        return Err(problems.add(
+            "no course secret is configured, so the receipt can't be verified",
+            path.to_owned(),
+            Some(format!(
+                "set `{SECRET_ENV}` or `receipt_secret` in the config"
+            )),
+        ));
+    };
+    if !same_signature(hmac, &receipt.sign(secret)) {
+        return Err(problems.add(
+            "receipt signature doesn't match; the receipt was modified or signed with a different secret",
+            path.to_owned(),
+            None,
+        ));
+    }
+
+    if let Err(e) = git::git(repo, &["merge-base", "--is-ancestor", &receipt.commit, rev]) {
+        return Err(problems.add(
+            format!(
+                "receipt commit `{}` is not part of `{rev}` in the submitted repo",
+                receipt.commit
+            ),
+            vec![path.to_owned(), repo.to_owned()],
+            Some(e),
+        ));
+    }
+
+    say!(
+        "receipt is valid: lab `{}`, commit {}, checker {}, {} of {} checks passed",
+        receipt.lab,
+        receipt.commit,
+        receipt.checker_version,
+        receipt.checks.iter().filter(|(_, x)| *x).count(),
+        receipt.checks.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temp::TempDir;
+    use camino::Utf8PathBuf;
+
+    const SECRET: &str = "course secret";
+
+    /// A repo with one commit and a receipt for it, signed with `SECRET`.
+    fn signed(name: &str) -> (TempDir, Utf8PathBuf, Utf8PathBuf) {
+        let dir = TempDir::new(name).unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        git::git(&repo, &["init", "-q"]).unwrap();
+        git::git(
+            &repo,
+            &[
+                "-c",
+                "user.name=test",
+                "-c",
+                "user.email=test@example.com",
+                "commit",
+                "-q",
+                "--allow-empty",
+                "-m",
+                "lab",
+            ],
+        )
+        .unwrap();
+        let receipt = Receipt {
+            checker_version: "1.0.0".into(),
+            commit: git::head_commit(&repo).unwrap(),
+            lab: "lab01".into(),
+            timestamp: 1_700_000_000,
+            checks: vec![("build".into(), true), ("tests".into(), false)],
+        };
+        let path = dir.path().join("receipt.toml");
+        write(&path, &receipt, Some(SECRET)).unwrap();
+        (dir, repo, path)
+    }
+
+    fn verify_text(path: &Utf8Path, repo: &Utf8Path, secret: Option<&str>) -> Option<String> {
+        let mut problems = Diags::default();
+        verify(&mut problems, path, repo, "HEAD", secret)
+            .err()
+            .map(|_| problems.problems.into_iter().next().unwrap().text)
+    }
+
+    fn edit(path: &Utf8Path, from: &str, to: &str) {
+        let text = fs::read_to_string(path).unwrap();
+        assert!(text.contains(from), "{text}");
+        fs::write(path, text.replace(from, to)).unwrap();
+    }
+
+    #[test]
+    fn written_receipts_verify() {
+        let (_dir, repo, path) = signed("receipt_round_trip");
+        assert_eq!(verify_text(&path, &repo, Some(SECRET)), None);
+        assert_eq!(read_lab(&path).as_deref(), Some("lab01"));
+    }
+
+    #[test]
+    fn edited_receipts_are_rejected() {
+        let edits = [
+            ("tests = \"failed\"", "tests = \"passed\""),
+            ("timestamp = 1700000000", "timestamp = 1800000000"),
+            ("lab = \"lab01\"", "lab = \"lab02\""),
+            ("checker_version = \"1.0.0\"", "checker_version = \"1.0.1\""),
+        ];
+        for (from, to) in edits {
+            let (_dir, repo, path) = signed("receipt_edited");
+            edit(&path, from, to);
+            let text = verify_text(&path, &repo, Some(SECRET)).unwrap();
+            assert!(text.contains("signature doesn't match"), "{from}: {text}");
+        }
+    }
+
+    #[test]
+    fn removed_checks_are_rejected() {
+        let (_dir, repo, path) = signed("receipt_removed_check");
+        edit(&path, "tests = \"failed\"\n", "");
+        let text = verify_text(&path, &repo, Some(SECRET)).unwrap();
+        assert!(text.contains("signature doesn't match"), "{text}");
+    }
+
+    #[test]
+    fn other_secrets_are_rejected() {
+        let (_dir, repo, path) = signed("receipt_other_secret");
+        let text = verify_text(&path, &repo, Some("guessed")).unwrap();
+        assert!(text.contains("signature doesn't match"), "{text}");
+        let text = verify_text(&path, &repo, None).unwrap();
+        assert!(text.contains("no course secret is configured"), "{text}");
+    }
+
+    #[test]
+    fn commits_outside_the_repo_are_rejected() {
+        let (_dir, repo, path) = signed("receipt_other_commit");
+        let mut receipt =
+            Receipt::parse(&toml::parse(&fs::read_to_string(&path).unwrap()).unwrap()).unwrap();
+        receipt.commit = "0123456789012345678901234567890123456789".into();
+        write(&path, &receipt, Some(SECRET)).unwrap();
+        let text = verify_text(&path, &repo, Some(SECRET)).unwrap();
+        assert!(text.contains("is not part of `HEAD`"), "{text}");
+    }
+}
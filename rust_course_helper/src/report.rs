@@ -0,0 +1,405 @@
+//! Machine-readable renderings of a run.
+
+use crate::flaky::{self, Outcome};
+use crate::grade::Grade;
+use crate::json::Json;
+use crate::{Diag, Diags, Severity};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::fmt::Write;
+
+/// Shows paths relative to the repo, whichever of its names a check used: the one it was given,
+/// the absolute one, or the canonical one, which goes through symlinks. Windows separators
+/// become `/`, so the same repo gives the same report on every system.
+pub struct RepoPaths {
+    roots: Vec<Utf8PathBuf>,
+    /// What the roots look like inside text, longest first.
+    prefixes: Vec<String>,
+}
+
+impl RepoPaths {
+    pub fn new(repo: &Utf8Path) -> RepoPaths {
+        let mut roots = vec![repo.to_owned()];
+        if let Ok(x) = std::path::absolute(repo)
+            && let Ok(x) = Utf8PathBuf::from_path_buf(x)
+        {
+            roots.push(x);
+        }
+        if let Ok(x) = repo.canonicalize_utf8() {
+            // Windows' canonical paths start with `\\?\`, which other paths don't.
+            if let Some(x) = x.as_str().strip_prefix(r"\\?\") {
+                roots.push(x.into());
+            }
+            roots.push(x);
+        }
+        roots.dedup();
+        let mut prefixes: Vec<String> = roots
+            .iter()
+            // A relative root could match the middle of other paths.
+            .filter(|x| x.is_absolute())
+            .map(|x| x.as_str().trim_end_matches(['/', '\\']))
+            // The file system's root, which every absolute path starts with.
+            .filter(|x| !x.is_empty())
+            .flat_map(|x| {
+                [
+                    format!("{x}/"),
+                    format!("{x}\\"),
+                    x.replace('\\', "/") + "/",
+                ]
+            })
+            .collect();
+        prefixes.sort_by_key(|x| std::cmp::Reverse(x.len()));
+        prefixes.dedup();
+        RepoPaths { roots, prefixes }
+    }
+
+    pub fn path(&self, path: &str) -> String {
+        let relative = |path: &Utf8Path| {
+            self.roots
+                .iter()
+                .find_map(|x| path.strip_prefix(x).ok().map(|x| x.to_string()))
+        };
+        let path_buf = Utf8Path::new(path);
+        let relative = relative(path_buf)
+            .or_else(|| relative(&path_buf.canonicalize_utf8().ok()?))
+            .unwrap_or_else(|| self.text(path));
+        relative.replace('\\', "/")
+    }
+
+    pub fn text(&self, text: &str) -> String {
+        let mut text = text.to_string();
+        for prefix in &self.prefixes {
+            text = text.replace(prefix.as_str(), "");
+        }
+        text
+    }
+}
+
+/// The problems, in the order stable reports list them.
+pub fn stable_order(problems: &Diags) -> Vec<&Diag> {
+    let mut diags: Vec<_> = problems.problems.iter().collect();
+    diags.sort_by(|a, b| {
+        (a.check, a.severity == Severity::Warning, &a.paths, &a.text).cmp(&(
+            b.check,
+            b.severity == Severity::Warning,
+            &b.paths,
+            &b.text,
+        ))
+    });
+    diags
+}
+
+/// With `stable`, everything that changes between runs on the same commit is left out or made
+/// deterministic, so reports can be diffed.
+pub fn json(problems: &Diags, success: bool, stable: bool) -> Json {
+    // Paths are shown relative to the repo, wherever they appear.
+    let repo_paths = problems
+        .repo
+        .as_deref()
+        .filter(|_| stable)
+        .map(RepoPaths::new);
+    let text = |x: &str| -> Json {
+        match &repo_paths {
+            Some(repo) => repo.text(x).into(),
+            None => x.into(),
+        }
+    };
+    let path = |x: &Utf8Path| -> Json {
+        match &repo_paths {
+            Some(repo) => repo.path(x.as_str()).into(),
+            None => x.as_str().into(),
+        }
+    };
+
+    let mut checks: Vec<_> = problems.checks.iter().collect();
+    if stable {
+        checks.sort_by_key(|x| x.name);
+    }
+    let checks = checks
+        .into_iter()
+        .map(|x| {
+            let mut fields = vec![
+                ("name".to_string(), x.name.into()),
+                ("passed".to_string(), x.passed.into()),
+            ];
+            if !stable {
+                fields.push(("duration".into(), x.duration.as_secs_f64().into()));
+            }
+            fields.push(("partial".into(), x.partial.into()));
+            fields.push(("environment_failure".into(), x.environment_failure.into()));
+            let unstable = problems.unstable.iter().any(|u| u.check == x.name);
+            fields.push(("unstable".into(), unstable.into()));
+            fields.push((
+                "skipped".into(),
+                x.skipped.as_ref().map(|x| x.to_string()).into(),
+            ));
+            Json::Object(fields)
+        })
+        .collect();
+
+    let diags = match stable {
+        true => stable_order(problems),
+        false => problems.problems.iter().collect(),
+    };
+    let diags = diags
+        .into_iter()
+        .map(|x| {
+            Json::object([
+                ("check", x.check.into()),
+                (
+                    "severity",
+                    match x.severity {
+                        Severity::Error => "error",
+                        Severity::Warning => "warning",
+                    }
+                    .into(),
+                ),
+                ("text", text(&x.text)),
+                (
+                    "paths",
+                    Json::Array(x.paths.iter().map(|x| path(x)).collect()),
+                ),
+                ("help", x.help.as_deref().map(text).into()),
+                ("url", x.url.as_deref().into()),
+                ("fix", x.fix.as_ref().map(|x| text(&x.to_string())).into()),
+                ("root_cause_group", x.root_cause_group.into()),
+                (
+                    "fields",
+                    Json::Object(x.fields.iter().map(|(k, v)| (k.clone(), text(v))).collect()),
+                ),
+            ])
+        })
+        .collect();
+
+    let root_causes = problems
+        .root_causes
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            Json::object([
+                ("id", i.into()),
+                ("path", path(&x.path)),
+                ("count", x.members.len().into()),
+            ])
+        })
+        .collect();
+
+    let mut commands: Vec<_> = problems.commands.iter().collect();
+    if stable {
+        commands.sort_by(|a, b| (a.check, &a.command).cmp(&(b.check, &b.command)));
+    }
+    let commands = commands
+        .into_iter()
+        .map(|x| {
+            let mut fields = vec![
+                ("check".to_string(), x.check.into()),
+                ("command".to_string(), text(&x.command)),
+            ];
+            if stable {
+                return Json::Object(fields);
+            }
+            fields.push(("duration".into(), x.duration.as_secs_f64().into()));
+            if let Some(usage) = x.usage {
+                fields.extend([
+                    ("user_time".to_string(), usage.user.as_secs_f64().into()),
+                    ("system_time".to_string(), usage.system.as_secs_f64().into()),
+                    ("peak_rss".to_string(), usage.peak_rss.into()),
+                ]);
+            }
+            Json::Object(fields)
+        })
+        .collect();
+
+    let outcome = |x: &Outcome| {
+        let mut fields = vec![("status".to_string(), flaky::status(x.passed).into())];
+        if !stable {
+            fields.push(("at".into(), x.at.into()));
+        }
+        Json::Object(fields)
+    };
+    let unstable = problems
+        .unstable
+        .iter()
+        .map(|x| {
+            Json::object([
+                ("check", x.check.into()),
+                ("commit", x.commit.as_str().into()),
+                ("earlier", outcome(&x.earlier)),
+                ("now", outcome(&x.now)),
+            ])
+        })
+        .collect();
+
+    let artifacts = problems
+        .artifacts
+        .iter()
+        .map(|x| {
+            Json::object([
+                ("check", x.check.into()),
+                ("name", x.name.as_str().into()),
+                ("media_type", x.media_type.into()),
+                ("bytes", x.bytes.into()),
+                ("sha256", x.sha256.as_str().into()),
+                ("path", x.path.as_deref().map(path).into()),
+            ])
+        })
+        .collect();
+
+    let metadata = problems.lab.as_ref().map(|(lab, x)| {
+        let mut fields = vec![("lab".to_string(), lab.as_str().into())];
+        if let Some(dir) = &problems.lab_dir {
+            fields.push(("folder".into(), dir.as_str().into()));
+        }
+        if let Some(title) = &x.title {
+            fields.push(("title".into(), title.as_str().into()));
+        }
+        if let Some(week) = x.week {
+            fields.push(("week".into(), week.into()));
+        }
+        if !x.tags.is_empty() {
+            fields.push(("tags".into(), x.tags.clone().into()));
+        }
+        if let Some(url) = &x.statement_url {
+            fields.push(("statement_url".into(), url.as_str().into()));
+        }
+        Json::Object(fields)
+    });
+
+    Json::object([
+        ("result", if success { "success" } else { "failure" }.into()),
+        ("metadata", metadata.into()),
+        ("plan", problems.plan.clone().into()),
+        ("since", problems.since.as_deref().into()),
+        ("checks", Json::Array(checks)),
+        ("commands", Json::Array(commands)),
+        ("problems", Json::Array(diags)),
+        ("root_cause_groups", Json::Array(root_causes)),
+        ("unstable", Json::Array(unstable)),
+        ("grade", problems.grade.as_ref().map(Grade::json).into()),
+        ("artifacts", Json::Array(artifacts)),
+    ])
+}
+
+/// `--format short`: one `path:line:col: severity: [check] message` line per problem, for
+/// editors' quickfix lists. Only the first line of the text is kept.
+pub fn short(problems: &Diags) -> String {
+    let mut out = String::new();
+    for x in &problems.problems {
+        let path = x.paths.first().map_or("-", |x| x.as_str());
+        let line = x.fields.get("line").map_or("-", String::as_str);
+        let severity = match x.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let text = x.text.lines().next().unwrap_or_default();
+        let check = x.check.unwrap_or("checker");
+        writeln!(out, "{path}:{line}:-: {severity}: [{check}] {text}")
+            .expect("writing to a string");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GradingPolicy;
+    use crate::temp::TempDir;
+    use crate::{CheckStatus, ci, grade};
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::time::Duration;
+
+    fn diags(repo: &Utf8Path, reversed: bool, seconds: u64) -> Diags {
+        let mut problems = Diags {
+            repo: Some(repo.to_owned()),
+            ..Diags::default()
+        };
+        let mut found = vec![
+            (
+                "formatting",
+                repo.join("src/main.rs"),
+                format!("{repo}/src/main.rs isn't formatted"),
+            ),
+            (
+                "line_length",
+                repo.join("src/lib.rs"),
+                "a line is too long".to_string(),
+            ),
+        ];
+        if reversed {
+            found.reverse();
+        }
+        for (check, path, text) in found {
+            problems.current_check = Some(check);
+            problems.add(text, path, None);
+            problems.checks.push(CheckStatus {
+                name: check,
+                passed: false,
+                duration: Duration::from_secs(seconds),
+                partial: false,
+                skipped: None,
+                environment_failure: false,
+            });
+        }
+        problems
+    }
+
+    #[test]
+    fn stable_runs_are_identical() {
+        let dir = TempDir::new("report_stable").unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(&repo).unwrap();
+        let (first, second) = (diags(&repo, false, 1), diags(&repo, true, 7));
+        let report = json(&first, false, true).to_string();
+        assert_eq!(report, json(&second, false, true).to_string());
+        assert!(!report.contains(repo.as_str()));
+        assert!(report.contains(r#""src/main.rs""#));
+        assert!(report.contains(r#""src/main.rs isn't formatted""#));
+
+        let summary = ci::summary(&first, false, true);
+        assert_eq!(summary, ci::summary(&second, false, true));
+        assert!(!summary.contains(repo.as_str()));
+        assert!(summary.contains("[formatting] src/main.rs isn't formatted (`src/main.rs`)"));
+        assert!(!summary.contains("1.0s"));
+
+        let policy = GradingPolicy {
+            points: vec![("formatting".into(), 5), ("line_length".into(), 5)],
+            test_points: Vec::new(),
+            waived_checks: Vec::new(),
+            deadline: None,
+            late_penalty_percent: 0,
+        };
+        let csv = |x: &Diags| {
+            grade::evaluate(&policy, &x.checks, &BTreeMap::new(), None, None)
+                .csv(&repo, "lab01", true)
+        };
+        assert_eq!(csv(&first), csv(&second));
+        assert!(csv(&first).contains("\nrepo,lab01,"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn repos_behind_symlinks_give_the_same_report() {
+        let dir = TempDir::new("report_symlink").unwrap();
+        let repo = dir.path().join("repo");
+        let link = dir.path().join("link");
+        fs::create_dir_all(repo.join("src")).unwrap();
+        std::os::unix::fs::symlink(&repo, &link).unwrap();
+        let real = json(&diags(&repo, false, 1), false, true).to_string();
+        // Checks that canonicalize their paths give the real one, even though the run was given
+        // the symlink.
+        let mut through_link = diags(&link, false, 1);
+        through_link.problems[0].paths = vec![repo.join("src/main.rs")];
+        assert_eq!(real, json(&through_link, false, true).to_string());
+    }
+
+    #[test]
+    fn windows_separators_become_slashes() {
+        let dir = TempDir::new("report_separators").unwrap();
+        let paths = RepoPaths::new(dir.path());
+        assert_eq!(paths.path(r"src\bin\main.rs"), "src/bin/main.rs");
+        let path = format!(r"{}\src\lib.rs", dir.path());
+        assert_eq!(paths.path(&path), "src/lib.rs");
+        let text = format!("see {}/Cargo.toml", dir.path());
+        assert_eq!(paths.text(&text), "see Cargo.toml");
+    }
+}
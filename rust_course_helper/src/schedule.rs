@@ -0,0 +1,181 @@
+//! The order checks run in.
+//!
+//! Checks run after the checks they declare in `after`. Among the ones that are ready, cheaper
+//! ones run first, then by name, so the order only changes when the checks do. A config can
+//! pin its own order with `check_order`, as long as it keeps those constraints.
+
+use crate::checks::Check;
+
+/// Orders `checks`. Listed ones in `pinned` come first, in that order, then the rest.
+pub fn plan<'c>(checks: &'c [Check], pinned: &[String]) -> Result<Vec<&'c Check>, String> {
+    for check in checks {
+        if let Some(x) = check
+            .after
+            .iter()
+            .find(|x| !checks.iter().any(|c| c.name == **x))
+        {
+            return Err(format!(
+                "check `{}` runs after unknown check `{x}`",
+                check.name
+            ));
+        }
+    }
+    let default = sort(checks)?;
+    if pinned.is_empty() {
+        return Ok(default);
+    }
+
+    let mut plan: Vec<&Check> = Vec::new();
+    for name in pinned {
+        let Some(check) = checks.iter().find(|x| x.name == name) else {
+            return Err(format!("`check_order` names unknown check `{name}`"));
+        };
+        if plan.iter().any(|x| x.name == check.name) {
+            return Err(format!("`check_order` lists `{name}` twice"));
+        }
+        if let Some(dep) = check
+            .after
+            .iter()
+            .find(|x| !plan.iter().any(|c| c.name == **x))
+        {
+            return Err(format!(
+                "`check_order` puts `{name}` before `{dep}`, but `{name}` has to run after it"
+            ));
+        }
+        plan.push(check);
+    }
+    for check in default {
+        if !plan.iter().any(|x| x.name == check.name) {
+            plan.push(check);
+        }
+    }
+    Ok(plan)
+}
+
+/// Topological sort that picks the cheapest ready check, then the first by name.
+fn sort(checks: &[Check]) -> Result<Vec<&Check>, String> {
+    let mut plan: Vec<&Check> = Vec::new();
+    let mut left: Vec<&Check> = checks.iter().collect();
+    while !left.is_empty() {
+        let ready = left
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| {
+                x.after
+                    .iter()
+                    .all(|dep| plan.iter().any(|c| c.name == *dep))
+            })
+            .min_by_key(|(_, x)| (x.cost, x.name));
+        let Some((i, _)) = ready else {
+            let names: Vec<String> = left.iter().map(|x| format!("`{}`", x.name)).collect();
+            return Err(format!(
+                "checks {} have to run after each other in a cycle",
+                names.join(", ")
+            ));
+        };
+        plan.push(left.remove(i));
+    }
+    Ok(plan)
+}
+
+/// Prints the order for `--show-plan`.
+pub fn show(plan: &[&Check]) {
+    for (i, check) in plan.iter().enumerate() {
+        let mut line = format!("{:>3}. {:<24}{}", i + 1, check.name, check.cost.name());
+        if !check.after.is_empty() {
+            line += &format!(", after {}", check.after.join(", "));
+        }
+        say!("{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checks::{Category, Cost};
+
+    fn check(name: &'static str, cost: Cost, after: &'static [&'static str]) -> Check {
+        Check {
+            name,
+            run: |_| Ok(()),
+            after,
+            cost,
+            scans_files: false,
+            category: Category::Structure,
+        }
+    }
+
+    fn names(plan: &[&Check]) -> Vec<&'static str> {
+        plan.iter().map(|x| x.name).collect()
+    }
+
+    fn pinned(names: &[&str]) -> Vec<String> {
+        names.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn cheap_checks_run_first_then_by_name() {
+        let checks = [
+            check("build", Cost::Expensive, &[]),
+            check("b", Cost::Cheap, &[]),
+            check("a", Cost::Cheap, &[]),
+            check("version", Cost::Moderate, &[]),
+        ];
+        let plan = plan(&checks, &[]).unwrap();
+        assert_eq!(names(&plan), ["a", "b", "version", "build"]);
+    }
+
+    #[test]
+    fn checks_run_after_their_dependencies() {
+        let checks = [
+            check("a", Cost::Cheap, &["build"]),
+            check("build", Cost::Expensive, &[]),
+            check("z", Cost::Cheap, &[]),
+        ];
+        let plan = plan(&checks, &[]).unwrap();
+        assert_eq!(names(&plan), ["z", "build", "a"]);
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let checks = [
+            check("a", Cost::Cheap, &["b"]),
+            check("b", Cost::Cheap, &["a"]),
+            check("c", Cost::Cheap, &[]),
+        ];
+        let e = plan(&checks, &[]).map(|x| names(&x)).unwrap_err();
+        assert!(e.contains("`a`, `b`") && e.contains("cycle"), "{e}");
+    }
+
+    #[test]
+    fn unknown_after_targets_are_errors() {
+        let checks = [check("a", Cost::Cheap, &["missing"])];
+        let e = plan(&checks, &[]).map(|x| names(&x)).unwrap_err();
+        assert!(e.contains("unknown check `missing`"), "{e}");
+    }
+
+    #[test]
+    fn pinned_checks_come_first_in_their_order() {
+        let checks = [
+            check("a", Cost::Cheap, &[]),
+            check("b", Cost::Cheap, &[]),
+            check("build", Cost::Expensive, &[]),
+        ];
+        let plan = plan(&checks, &pinned(&["build", "b"])).unwrap();
+        assert_eq!(names(&plan), ["build", "b", "a"]);
+    }
+
+    #[test]
+    fn pinned_orders_have_to_keep_the_constraints() {
+        let checks = [
+            check("a", Cost::Cheap, &["build"]),
+            check("build", Cost::Expensive, &[]),
+        ];
+        let e = plan(&checks, &pinned(&["a", "build"]))
+            .map(|x| names(&x))
+            .unwrap_err();
+        assert!(e.contains("puts `a` before `build`"), "{e}");
+        assert!(plan(&checks, &pinned(&["nope"])).is_err());
+        assert!(plan(&checks, &pinned(&["build", "build"])).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! `--since`: which files the file-scanning checks look at.
+
+use crate::{Diags, git};
+use camino::{Utf8Path, Utf8PathBuf};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+#[derive(Clone, Default)]
+pub enum Scope {
+    #[default]
+    Full,
+    /// Files changed since `base`, committed or not, and untracked files.
+    Since {
+        base: String,
+        paths: Rc<BTreeSet<Utf8PathBuf>>,
+    },
+}
+
+impl Scope {
+    /// The files changed since `base`, or a full scan with a warning if git can't tell.
+    pub fn since(problems: &mut Diags, repo: &Utf8Path, base: &str) -> Scope {
+        match changed_paths(repo, base) {
+            Ok(paths) => Scope::Since {
+                base: base.to_string(),
+                paths: Rc::new(paths),
+            },
+            Err(e) => {
+                problems.warn(
+                    format!(
+                        "can't find the files changed since `{base}`, so every file is checked: {e}"
+                    ),
+                    repo.to_owned(),
+                    None,
+                );
+                Scope::Full
+            }
+        }
+    }
+
+    pub fn includes(&self, path: &Utf8Path) -> bool {
+        match self {
+            Scope::Full => true,
+            Scope::Since { paths, .. } => paths.contains(path),
+        }
+    }
+
+    pub fn base(&self) -> Option<&str> {
+        match self {
+            Scope::Full => None,
+            Scope::Since { base, .. } => Some(base),
+        }
+    }
+}
+
+fn changed_paths(repo: &Utf8Path, base: &str) -> Result<BTreeSet<Utf8PathBuf>, String> {
+    git::git(
+        repo,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{base}^{{commit}}"),
+        ],
+    )
+    .map_err(|_| format!("`{base}` isn't a commit"))?;
+    let range = format!("{base}..HEAD");
+    let committed = git::git(repo, &["diff", "--name-only", "-z", &range])?;
+    let uncommitted = git::git(repo, &["diff", "--name-only", "-z", "HEAD"])?;
+    let untracked = git::git(repo, &["ls-files", "-z", "--others", "--exclude-standard"])?;
+    let paths = [committed, uncommitted, untracked]
+        .iter()
+        .flat_map(|x| x.split('\0'))
+        .filter(|x| !x.is_empty())
+        .map(|x| repo.join(x))
+        .collect();
+    Ok(paths)
+}
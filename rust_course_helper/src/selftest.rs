@@ -0,0 +1,119 @@
+//! `selftest`: runs every check on a lab that's known to be fine, to tell problems with the
+//! student's environment apart from problems with their code.
+
+use crate::checks::CHECKS;
+use crate::config::LabConfig;
+use crate::exec::{Exec, Stream};
+use crate::scope::Scope;
+use crate::temp::TempDir;
+use crate::{CheckResult, Context, Diags, run_plan, schedule};
+use camino::Utf8Path;
+use std::fs;
+
+/// The lab, relative to the repo. Made up of files embedded in the binary.
+const FILES: &[(&str, &str)] = &[
+    (
+        ".gitignore",
+        include_str!("../fixtures/selftest/gitignore.in"),
+    ),
+    (
+        "lab01/Cargo.toml",
+        include_str!("../fixtures/selftest/lab01/Cargo.toml.in"),
+    ),
+    (
+        "lab01/src/main.rs",
+        include_str!("../fixtures/selftest/lab01/src/main.rs"),
+    ),
+];
+const LAB: &str = "lab01";
+
+fn create_repo(repo: &Utf8Path) -> Result<(), String> {
+    for (path, text) in FILES {
+        let path = repo.join(path);
+        path.parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, text))
+            .map_err(|e| format!("can't write {path}: {e}"))?;
+    }
+
+    let git = |args: &[&str]| {
+        let output = Exec::new("git")
+            .args([
+                "-c",
+                "user.name=selftest",
+                "-c",
+                "user.email=selftest@localhost",
+            ])
+            .args(args.iter().copied())
+            .cwd(repo)
+            .stdout(Stream::Discard)
+            .stderr(Stream::Discard)
+            .run()
+            .map_err(|e| format!("can't run git: {e}"))?;
+        match output.success() {
+            true => Ok(()),
+            false => Err(format!(
+                "`git {}` failed: {}",
+                args.join(" "),
+                output.status
+            )),
+        }
+    };
+    git(&["init", "--quiet"])?;
+    git(&["add", "."])?;
+    git(&["commit", "--quiet", "-m", "selftest"])
+}
+
+pub fn selftest(problems: &mut Diags, verbose: bool) -> CheckResult {
+    // Removes the temporary repo, however the test ends.
+    let temp = TempDir::new("selftest").map_err(|e| problems.add(e, None, None))?;
+    let dir = temp.path().to_owned();
+    if let Err(e) = create_repo(&dir) {
+        return Err(problems.add(
+            format!("can't set up the test repo: {e}"),
+            dir,
+            Some("check that git is installed and the disk isn't full".into()),
+        ));
+    }
+
+    say!("checking a known good lab in {dir}");
+    let mut context = Context {
+        problems,
+        repo_path: dir.clone(),
+        lab: LAB.to_string(),
+        lab_path: dir.join(LAB),
+        lab_config: LabConfig::default(),
+        verbose,
+        build_timings: false,
+        child_env: Vec::new(),
+        env_overrides: Vec::new(),
+        writes_state: false,
+        scope: Scope::Full,
+        git_health: None,
+        skip_reason: None,
+        environment_failure: false,
+        parsed_sources: None,
+        tests: None,
+        lab_targets: None,
+    };
+    let plan = schedule::plan(CHECKS, &[]).map_err(|e| context.problems.add(e, None, None))?;
+    let result = run_plan(&mut context, &plan, false);
+    drop(temp);
+
+    let failed: Vec<&str> = context
+        .problems
+        .checks
+        .iter()
+        .filter(|x| !x.passed)
+        .map(|x| x.name)
+        .collect();
+    match failed.is_empty() {
+        true => say!("your environment can run every check"),
+        false => say!(
+            "these checks fail even on a lab that's known to be fine, so the problem is your \
+            environment, not your code: {}",
+            failed.join(", ")
+        ),
+    }
+    result
+}
@@ -0,0 +1,172 @@
+//! How similar two labs' sources are, for `--compare`.
+//!
+//! Files are reduced to fingerprints: the code is split into tokens with names and literals
+//! replaced by placeholders, so renaming doesn't hide anything and comments and formatting
+//! don't count, and runs of tokens are hashed. Winnowing keeps the smallest hash of each
+//! window, which keeps the fingerprints small while any long enough shared run still shares
+//! one. Two files' similarity is the share of fingerprints they have in common.
+
+use crate::artifacts;
+use crate::checks::rust_sources;
+use crate::{Diags, diff};
+use camino::{Utf8Path, Utf8PathBuf};
+use proc_macro2::{TokenStream, TokenTree};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::fs;
+
+/// Tokens hashed together. Shorter runs are common in any Rust code.
+const RUN_LENGTH: usize = 8;
+/// Hashes winnowing picks one from.
+const WINDOW: usize = 4;
+/// Pairs whose differences go in the report.
+const MAX_EXCERPTS: usize = 3;
+const MAX_EXCERPT_LINES: usize = 20;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "false",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while",
+];
+
+fn tokens(stream: TokenStream, out: &mut Vec<String>) {
+    for token in stream {
+        match token {
+            TokenTree::Group(x) => {
+                out.push(format!("{:?}", x.delimiter()));
+                tokens(x.stream(), out);
+                out.push("end".into());
+            }
+            TokenTree::Ident(x) => {
+                let name = x.to_string();
+                out.push(match KEYWORDS.contains(&name.as_str()) {
+                    true => name,
+                    false => "name".into(),
+                });
+            }
+            TokenTree::Punct(x) => out.push(x.as_char().to_string()),
+            TokenTree::Literal(_) => out.push("literal".into()),
+        }
+    }
+}
+
+/// FNV-1a, so fingerprints are the same in every run.
+fn hash(tokens: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in tokens.iter().flat_map(|x| x.bytes().chain([0])) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The fingerprints of a file's code. Files that don't lex have none.
+pub fn fingerprints(text: &str) -> BTreeSet<u64> {
+    let Ok(stream) = text.parse::<TokenStream>() else {
+        return BTreeSet::new();
+    };
+    let mut all = Vec::new();
+    tokens(stream, &mut all);
+    let hashes: Vec<u64> = all.windows(RUN_LENGTH).map(hash).collect();
+    if hashes.len() < WINDOW {
+        return hashes.into_iter().collect();
+    }
+    hashes
+        .windows(WINDOW)
+        .filter_map(|x| x.iter().min().copied())
+        .collect()
+}
+
+/// The share of fingerprints the two have in common, from 0 to 1.
+pub fn similarity(a: &BTreeSet<u64>, b: &BTreeSet<u64>) -> f64 {
+    let union = a.union(b).count();
+    match union {
+        0 => 0.0,
+        n => a.intersection(b).count() as f64 / n as f64,
+    }
+}
+
+struct Source {
+    /// Relative to the lab folder.
+    path: Utf8PathBuf,
+    text: String,
+    fingerprints: BTreeSet<u64>,
+}
+
+fn sources(lab: &Utf8Path) -> Vec<Source> {
+    rust_sources(lab)
+        .into_iter()
+        .filter_map(|path| {
+            let text = fs::read_to_string(&path).ok()?;
+            Some(Source {
+                path: path.strip_prefix(lab).ok()?.to_owned(),
+                fingerprints: fingerprints(&text),
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Compares the lab in `lab` with the same lab in another repo, `other`, printing each file's
+/// most similar file in the other lab. The table and the differences of the most similar pairs
+/// are attached as an artifact. It's for reviewers, so it never fails the run.
+pub fn compare(problems: &mut Diags, lab: &Utf8Path, other: &Utf8Path) {
+    for path in [lab, other] {
+        if !path.is_dir() {
+            problems.warn(
+                format!("can't compare the labs: {path} isn't a folder"),
+                path.to_owned(),
+                Some("pass the other repo's root to `--compare`, with the lab in the same folder as in this one".into()),
+            );
+            return;
+        }
+    }
+    let left = sources(lab);
+    let right = sources(other);
+    let mut pairs: Vec<(&Source, Option<&Source>, f64)> = left
+        .iter()
+        .map(|a| {
+            let best = right
+                .iter()
+                .map(|b| (b, similarity(&a.fingerprints, &b.fingerprints)))
+                .max_by(|x, y| x.1.total_cmp(&y.1));
+            match best {
+                Some((b, score)) => (a, Some(b), score),
+                None => (a, None, 0.0),
+            }
+        })
+        .collect();
+    pairs.sort_by(|a, b| b.2.total_cmp(&a.2).then(a.0.path.cmp(&b.0.path)));
+
+    let mut table = format!("similarity of {lab} and {other}:\n");
+    for (a, b, score) in &pairs {
+        let b = b.map_or("-".to_string(), |x| x.path.to_string());
+        let _ = writeln!(table, "{:>5.0}%  {}  ~  {b}", score * 100.0, a.path);
+    }
+    let matched: BTreeSet<&Utf8Path> = pairs
+        .iter()
+        .filter_map(|x| Some(x.1?.path.as_path()))
+        .collect();
+    for b in right.iter().filter(|x| !matched.contains(x.path.as_path())) {
+        let _ = writeln!(table, "{:>6}  -  ~  {}", "", b.path);
+    }
+    say!("\n{table}");
+
+    let mut report = table;
+    for (a, b, score) in pairs.iter().take(MAX_EXCERPTS) {
+        let Some(b) = b.filter(|_| *score > 0.0) else {
+            continue;
+        };
+        let _ = write!(
+            report,
+            "\n--- {}\n+++ {}\n{}\n",
+            a.path,
+            b.path,
+            diff::excerpt(&a.text, &b.text, MAX_EXCERPT_LINES)
+        );
+    }
+    problems.current_check = Some("compare");
+    artifacts::add(problems, "similarity.txt", "text/plain", report.as_bytes());
+    problems.current_check = None;
+}
@@ -0,0 +1,57 @@
+//! Files the checker generates inside the student's repo.
+//!
+//! Everything lives in one folder at the repo root, never inside the lab folder, so it can't
+//! end up in the lab's crate. The committed-files and gitignore checks use these names too.
+//! `--read-only` runs keep them somewhere else; see `redirect`.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use std::sync::OnceLock;
+use std::{fs, process};
+
+pub const STATE_DIR: &str = ".checker";
+pub const RECEIPT_FILE: &str = "receipt.toml";
+pub const TIMINGS_FILE: &str = "timings.toml";
+pub const OUTCOMES_FILE: &str = "outcomes.toml";
+pub const LOCK_FILE: &str = "run.lock";
+
+static REDIRECT: OnceLock<Utf8PathBuf> = OnceLock::new();
+
+/// Keeps the state files in `dir` instead of the repo, for the rest of the run.
+pub fn redirect(dir: Utf8PathBuf) {
+    let _ = REDIRECT.set(dir);
+}
+
+/// Whether the state files are kept outside the repo.
+pub fn redirected() -> bool {
+    REDIRECT.get().is_some()
+}
+
+fn state_dir(repo: &Utf8Path) -> Utf8PathBuf {
+    match REDIRECT.get() {
+        Some(x) => x.clone(),
+        None => repo.join(STATE_DIR),
+    }
+}
+
+pub fn state_path(repo: &Utf8Path, file: &str) -> Utf8PathBuf {
+    state_dir(repo).join(file)
+}
+
+/// Creates the state folder if needed and returns the path of `file` inside it.
+pub fn create_state_path(repo: &Utf8Path, file: &str) -> Result<Utf8PathBuf, String> {
+    let dir = state_dir(repo);
+    fs::create_dir_all(&dir).map_err(|e| format!("can't create {dir}: {e}"))?;
+    Ok(state_path(repo, file))
+}
+
+/// Writes `text` to a temporary file next to `path` and renames it over `path`, so readers, and
+/// runs racing this one, see either the old contents or the new ones.
+pub fn write_atomic(path: &Utf8Path, text: &str) -> Result<(), String> {
+    let file_name = path.file_name().unwrap_or_default();
+    let temp = path.with_file_name(format!(".{file_name}.{}.tmp", process::id()));
+    let result = fs::write(&temp, text).and_then(|_| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result.map_err(|e| format!("can't write {path}: {e}"))
+}
@@ -0,0 +1,32 @@
+//! Temporary folders that are removed however the code using them ends.
+
+use camino::{Utf8Path, Utf8PathBuf};
+use std::{env, fs, process};
+
+pub struct TempDir(Utf8PathBuf);
+
+impl TempDir {
+    /// Creates an empty folder in the system's temporary folder, named after `name` and this
+    /// process.
+    pub fn new(name: &str) -> Result<TempDir, String> {
+        let dir = env::temp_dir().join(format!("rust_course_helper_{name}_{}", process::id()));
+        let dir = Utf8PathBuf::from_path_buf(dir)
+            .map_err(|_| "the temporary folder's path isn't UTF-8".to_string())?;
+        let _ = fs::remove_dir_all(&dir);
+        // Owned before it exists, so it's cleaned up even if creating it half fails.
+        let temp = TempDir(dir);
+        fs::create_dir_all(&temp.0)
+            .map_err(|e| format!("can't create a temporary folder {}: {e}", temp.0))?;
+        Ok(temp)
+    }
+
+    pub fn path(&self) -> &Utf8Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,61 @@
+//! `--timings`: how long checks took and what the commands they ran used.
+
+use crate::Diags;
+use crate::usage::Usage;
+use std::time::Duration;
+
+fn print_row(duration: Duration, usage: Option<Usage>, name: &str) {
+    let usage = match usage {
+        Some(x) => format!(
+            "{:>8.2}s {:>8.2}s {:>8.1}",
+            x.user.as_secs_f64(),
+            x.system.as_secs_f64(),
+            x.peak_rss as f64 / (1024.0 * 1024.0)
+        ),
+        None => format!("{:>9} {:>9} {:>8}", "-", "-", "-"),
+    };
+    say!("{:>8.2}s {usage}  {name}", duration.as_secs_f64());
+}
+
+/// Combines usage, or gives up if any command has none so totals never undercount.
+fn total_usage<'a>(usages: impl IntoIterator<Item = &'a Option<Usage>>) -> Option<Usage> {
+    usages
+        .into_iter()
+        .try_fold(Usage::default(), |acc, x| Some(acc.combine((*x)?)))
+}
+
+impl Diags {
+    pub fn print_timings(&self) {
+        say!("\ntimings:");
+        say!(
+            "{:>9} {:>9} {:>9} {:>8}  check / command",
+            "wall",
+            "user",
+            "system",
+            "peak MB"
+        );
+        for check in self.checks.iter().filter(|x| x.ran()) {
+            let commands: Vec<_> = self
+                .commands
+                .iter()
+                .filter(|x| x.check == Some(check.name))
+                .collect();
+            let usage = match commands.is_empty() {
+                true => None,
+                false => total_usage(commands.iter().map(|x| &x.usage)),
+            };
+            print_row(check.duration, usage, check.name);
+            for command in commands {
+                print_row(
+                    command.duration,
+                    command.usage,
+                    &format!("  {}", command.command),
+                );
+            }
+        }
+
+        let total: Duration = self.checks.iter().map(|x| x.duration).sum();
+        let usage = total_usage(self.commands.iter().map(|x| &x.usage));
+        print_row(total, usage, "total");
+    }
+}
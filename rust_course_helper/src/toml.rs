@@ -0,0 +1,732 @@
+//! A small TOML parser, good enough for `Cargo.toml` files and the course config.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Datetime(String),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(x) => Some(x),
+            _ => None,
+        }
+    }
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(x) => Some(x),
+            _ => None,
+        }
+    }
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Value::Table(x) => Some(x),
+            _ => None,
+        }
+    }
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Datetime(_) => "datetime",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+pub fn parse(text: &str) -> Result<Table, Error> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser.document()
+}
+
+/// Merges `overlay` into `base`, recursing into tables present in both.
+pub fn merge(base: &mut Table, overlay: &Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(b)), Value::Table(o)) => merge(b, o),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, Error> {
+        Err(Error {
+            line: self.line,
+            message: message.into(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_at(i) == Some(c))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), Error> {
+        match self.bump() {
+            Some(x) if x == c => Ok(()),
+            Some(x) => self.error(format!("expected `{c}`, found `{x}`")),
+            None => self.error(format!("expected `{c}`, found end of file")),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skips whitespace, newlines and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), Error> {
+        self.skip_spaces();
+        self.skip_comment();
+        if self.peek() == Some('\r') {
+            self.bump();
+        }
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.error(format!("unexpected `{c}` at end of line")),
+        }
+    }
+
+    fn document(&mut self) -> Result<Table, Error> {
+        let mut root = Table::new();
+        let mut current: Vec<String> = Vec::new();
+
+        loop {
+            self.skip_trivia();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.bump();
+                    let is_array = self.peek() == Some('[');
+                    if is_array {
+                        self.bump();
+                    }
+                    self.skip_spaces();
+                    let path = self.key()?;
+                    self.skip_spaces();
+                    self.expect(']')?;
+                    if is_array {
+                        self.expect(']')?;
+                        let line = self.line;
+                        let (last, parent) = path.split_last().expect("keys are never empty");
+                        let parent = table_mut(&mut root, parent, line)?;
+                        let entry = parent
+                            .entry(last.clone())
+                            .or_insert_with(|| Value::Array(Vec::new()));
+                        match entry {
+                            Value::Array(items) => items.push(Value::Table(Table::new())),
+                            _ => return self.error(format!("`{last}` is not an array of tables")),
+                        }
+                    } else {
+                        table_mut(&mut root, &path, self.line)?;
+                    }
+                    current = path;
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let key = self.key()?;
+                    self.skip_spaces();
+                    self.expect('=')?;
+                    self.skip_spaces();
+                    let value = self.value()?;
+                    let line = self.line;
+                    let table = table_mut(&mut root, &current, line)?;
+                    insert(table, &key, value, line)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+
+        Ok(root)
+    }
+
+    /// Parses a possibly dotted key.
+    fn key(&mut self) -> Result<Vec<String>, Error> {
+        let mut parts = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut s = String::new();
+                    while let Some(c) = self.peek() {
+                        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                            s.push(c);
+                            self.bump();
+                        } else {
+                            break;
+                        }
+                    }
+                    if s.is_empty() {
+                        return match self.peek() {
+                            Some(c) => self.error(format!("expected a key, found `{c}`")),
+                            None => self.error("expected a key, found end of file"),
+                        };
+                    }
+                    s
+                }
+            };
+            parts.push(part);
+            self.skip_spaces();
+            if self.peek() == Some('.') {
+                self.bump();
+            } else {
+                return Ok(parts);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, Error> {
+        match self.peek() {
+            Some('"') if self.starts_with("\"\"\"") => {
+                self.multiline_basic_string().map(Value::String)
+            }
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') if self.starts_with("'''") => {
+                self.multiline_literal_string().map(Value::String)
+            }
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            Some(_) if self.starts_with("true") => {
+                self.pos += 4;
+                Ok(Value::Boolean(true))
+            }
+            Some(_) if self.starts_with("false") => {
+                self.pos += 5;
+                Ok(Value::Boolean(false))
+            }
+            Some(_) => self.scalar(),
+            None => self.error("expected a value, found end of file"),
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, Error> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        loop {
+            self.skip_trivia();
+            if self.peek() == Some(']') {
+                self.bump();
+                return Ok(Value::Array(items));
+            }
+            items.push(self.value()?);
+            self.skip_trivia();
+            match self.bump() {
+                Some(',') => {}
+                Some(']') => return Ok(Value::Array(items)),
+                _ => return self.error("expected `,` or `]` in array"),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, Error> {
+        self.expect('{')?;
+        let mut table = Table::new();
+        self.skip_trivia();
+        if self.peek() == Some('}') {
+            self.bump();
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_trivia();
+            let key = self.key()?;
+            self.skip_spaces();
+            self.expect('=')?;
+            self.skip_spaces();
+            let value = self.value()?;
+            insert(&mut table, &key, value, self.line)?;
+            self.skip_trivia();
+            match self.bump() {
+                Some(',') => {}
+                Some('}') => return Ok(Value::Table(table)),
+                _ => return self.error("expected `,` or `}` in inline table"),
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Value, Error> {
+        let mut s = String::new();
+        while let Some(c) = self.peek() {
+            let is_time_space = c == ' '
+                && s.len() == 10
+                && s.as_bytes()[4] == b'-'
+                && self.peek_at(1).is_some_and(|x| x.is_ascii_digit());
+            if c.is_ascii_alphanumeric() || "+-._:".contains(c) || is_time_space {
+                s.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if s.is_empty() {
+            return self.error(format!(
+                "expected a value, found `{}`",
+                self.peek().unwrap_or(' ')
+            ));
+        }
+
+        let bytes = s.as_bytes();
+        let digits_then = |n: usize, sep: u8| {
+            bytes.len() > n && bytes[..n].iter().all(u8::is_ascii_digit) && bytes[n] == sep
+        };
+        if digits_then(4, b'-') || digits_then(2, b':') {
+            return Ok(Value::Datetime(s));
+        }
+
+        let digits = s.replace('_', "");
+        let (sign, unsigned) = match digits.strip_prefix('-') {
+            Some(rest) => (-1, rest),
+            None => (1, digits.strip_prefix('+').unwrap_or(&digits)),
+        };
+        let radix = [("0x", 16), ("0o", 8), ("0b", 2)]
+            .into_iter()
+            .find_map(|(prefix, radix)| unsigned.strip_prefix(prefix).map(|x| (x, radix)));
+        if let Some((rest, radix)) = radix {
+            if let Ok(x) = i64::from_str_radix(rest, radix) {
+                return Ok(Value::Integer(sign * x));
+            }
+        } else if let Ok(x) = digits.parse::<i64>() {
+            return Ok(Value::Integer(x));
+        } else if let Ok(x) = digits.parse::<f64>() {
+            return Ok(Value::Float(x));
+        }
+
+        self.error(format!("invalid value `{s}`"))
+    }
+
+    fn escape(&mut self, out: &mut String) -> Result<(), Error> {
+        let c = match self.bump() {
+            Some('b') => '\u{8}',
+            Some('t') => '\t',
+            Some('n') => '\n',
+            Some('f') => '\u{c}',
+            Some('r') => '\r',
+            Some('e') => '\u{1b}',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(u @ ('u' | 'U')) => {
+                let len = if u == 'u' { 4 } else { 8 };
+                let mut hex = String::new();
+                for _ in 0..len {
+                    match self.bump() {
+                        Some(c) => hex.push(c),
+                        None => return self.error("unterminated unicode escape"),
+                    }
+                }
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => c,
+                    None => return self.error(format!("invalid unicode escape `{hex}`")),
+                }
+            }
+            Some(c) => return self.error(format!("invalid escape `\\{c}`")),
+            None => return self.error("unterminated string"),
+        };
+        out.push(c);
+        Ok(())
+    }
+
+    fn basic_string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => self.escape(&mut s)?,
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, Error> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(s),
+                Some('\n') | None => return self.error("unterminated string"),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn skip_first_newline(&mut self) {
+        if self.starts_with("\r\n") {
+            self.bump();
+        }
+        if self.peek() == Some('\n') {
+            self.bump();
+        }
+    }
+
+    /// Consumes the closing delimiter, allowing up to two extra quotes to belong to the content.
+    fn multiline_end(&mut self, quote: char, s: &mut String) -> bool {
+        let q: String = [quote; 3].iter().collect();
+        if !self.starts_with(&q) {
+            return false;
+        }
+        let mut extra = 0;
+        while extra < 2 && self.peek_at(3 + extra) == Some(quote) {
+            extra += 1;
+        }
+        for _ in 0..extra {
+            s.push(quote);
+        }
+        self.pos += 3 + extra;
+        true
+    }
+
+    fn multiline_basic_string(&mut self) -> Result<String, Error> {
+        self.pos += 3;
+        self.skip_first_newline();
+        let mut s = String::new();
+        loop {
+            if self.multiline_end('"', &mut s) {
+                return Ok(s);
+            }
+            match self.bump() {
+                Some('\\') => {
+                    let save = (self.pos, self.line);
+                    self.skip_spaces();
+                    if matches!(self.peek(), Some('\n' | '\r')) {
+                        self.skip_trivia_whitespace();
+                    } else {
+                        (self.pos, self.line) = save;
+                        self.escape(&mut s)?;
+                    }
+                }
+                Some(c) => s.push(c),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn skip_trivia_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.bump();
+        }
+    }
+
+    fn multiline_literal_string(&mut self) -> Result<String, Error> {
+        self.pos += 3;
+        self.skip_first_newline();
+        let mut s = String::new();
+        loop {
+            if self.multiline_end('\'', &mut s) {
+                return Ok(s);
+            }
+            match self.bump() {
+                Some(c) => s.push(c),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+}
+
+fn table_mut<'t>(
+    mut table: &'t mut Table,
+    path: &[String],
+    line: usize,
+) -> Result<&'t mut Table, Error> {
+    for key in path {
+        let entry = table
+            .entry(key.clone())
+            .or_insert_with(|| Value::Table(Table::new()));
+        table = match entry {
+            Value::Table(t) => t,
+            Value::Array(items) => match items.last_mut() {
+                Some(Value::Table(t)) => t,
+                _ => {
+                    return Err(Error {
+                        line,
+                        message: format!("`{key}` is not a table"),
+                    });
+                }
+            },
+            _ => {
+                return Err(Error {
+                    line,
+                    message: format!("`{key}` is not a table"),
+                });
+            }
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Table, key: &[String], value: Value, line: usize) -> Result<(), Error> {
+    let (last, parents) = key.split_last().expect("keys are never empty");
+    let table = table_mut(table, parents, line)?;
+    if table.contains_key(last) {
+        return Err(Error {
+            line,
+            message: format!("duplicate key `{}`", key.join(".")),
+        });
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(x: &str) -> Value {
+        Value::String(x.into())
+    }
+
+    fn get<'t>(table: &'t Table, path: &str) -> &'t Value {
+        let mut parts = path.split('.');
+        let mut value = &table[parts.next().unwrap()];
+        for part in parts {
+            value = &value.as_table().unwrap()[part];
+        }
+        value
+    }
+
+    fn error(text: &str) -> Error {
+        parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn tables() {
+        let table = parse(
+            r#"
+# A comment
+name = "root"
+
+[package]
+name = "lab01"   # after a value
+edition = "2024"
+
+[dependencies.serde]
+version = "1"
+
+[[bin]]
+name = "a"
+
+[[bin]]
+name = "b"
+path = "src/b.rs"
+
+[target."cfg(unix)".dependencies]
+libc = "0.2"
+"#,
+        )
+        .unwrap();
+        assert_eq!(get(&table, "name"), &string("root"));
+        assert_eq!(get(&table, "package.name"), &string("lab01"));
+        assert_eq!(get(&table, "dependencies.serde.version"), &string("1"));
+        let bins = table["bin"].as_array().unwrap();
+        assert_eq!(bins.len(), 2);
+        assert_eq!(bins[1].as_table().unwrap()["path"], string("src/b.rs"));
+        assert_eq!(
+            get(&table, "target.cfg(unix).dependencies.libc"),
+            &string("0.2")
+        );
+    }
+
+    #[test]
+    fn dotted_keys_and_inline_tables() {
+        let table = parse(
+            r#"
+package.name = "lab01"
+serde = { version = "1", features = ["derive"], nested = { a = 1 } }
+empty = {}
+"#,
+        )
+        .unwrap();
+        assert_eq!(get(&table, "package.name"), &string("lab01"));
+        assert_eq!(get(&table, "serde.version"), &string("1"));
+        assert_eq!(get(&table, "serde.nested.a"), &Value::Integer(1));
+        assert_eq!(
+            get(&table, "serde.features"),
+            &Value::Array(vec![string("derive")])
+        );
+        assert_eq!(table["empty"], Value::Table(Table::new()));
+    }
+
+    #[test]
+    fn arrays() {
+        let table = parse(
+            "a = [1, 2, 3]\nb = [\n  \"x\", # comment\n  \"y\",\n]\nc = []\nd = [[1], [\"two\"]]\n",
+        )
+        .unwrap();
+        let ints = (1..=3).map(Value::Integer).collect();
+        assert_eq!(table["a"], Value::Array(ints));
+        assert_eq!(table["b"], Value::Array(vec![string("x"), string("y")]));
+        assert_eq!(table["c"], Value::Array(Vec::new()));
+        assert_eq!(
+            table["d"],
+            Value::Array(vec![
+                Value::Array(vec![Value::Integer(1)]),
+                Value::Array(vec![string("two")]),
+            ])
+        );
+    }
+
+    #[test]
+    fn scalars() {
+        let table = parse(
+            "a = 1_000\nb = -7\nc = 0x1F\nd = 0o17\ne = 0b101\nf = 1.5\ng = true\nh = false\n\
+             i = 2026-10-20T12:00:00Z\nj = 2026-10-20 12:00:00\nk = 12:30:00\n",
+        )
+        .unwrap();
+        assert_eq!(table["a"], Value::Integer(1000));
+        assert_eq!(table["b"], Value::Integer(-7));
+        assert_eq!(table["c"], Value::Integer(31));
+        assert_eq!(table["d"], Value::Integer(15));
+        assert_eq!(table["e"], Value::Integer(5));
+        assert_eq!(table["f"], Value::Float(1.5));
+        assert_eq!(table["g"], Value::Boolean(true));
+        assert_eq!(table["h"], Value::Boolean(false));
+        assert_eq!(table["i"], Value::Datetime("2026-10-20T12:00:00Z".into()));
+        assert_eq!(table["j"], Value::Datetime("2026-10-20 12:00:00".into()));
+        assert_eq!(table["k"], Value::Datetime("12:30:00".into()));
+    }
+
+    #[test]
+    fn strings_and_escapes() {
+        let table = parse(concat!(
+            r#"basic = "tab\there \"quoted\" \\ \u00e9 \U0001F980""#,
+            "\n",
+            r#"literal = 'C:\Users\no escapes'"#,
+            "\n",
+            "multi = \"\"\"\nfirst\nsecond \\\n   joined\"\"\"\n",
+            "multi_literal = '''\nkeep \\n as is'''\n",
+            "quotes = \"\"\"two \"\" quotes\"\"\"\"\"\n",
+            "crlf = \"value\"\r\n",
+        ))
+        .unwrap();
+        assert_eq!(table["basic"], string("tab\there \"quoted\" \\ é 🦀"));
+        assert_eq!(table["literal"], string(r"C:\Users\no escapes"));
+        assert_eq!(table["multi"], string("first\nsecond joined"));
+        assert_eq!(table["multi_literal"], string(r"keep \n as is"));
+        assert_eq!(table["quotes"], string("two \"\" quotes\"\""));
+        assert_eq!(table["crlf"], string("value"));
+    }
+
+    #[test]
+    fn errors() {
+        let e = error("a = 1\na = 2\n");
+        assert_eq!((e.line, e.message.as_str()), (2, "duplicate key `a`"));
+        assert_eq!(error("a = \"open\n").message, "unterminated string");
+        assert_eq!(error(r#"a = "\q""#).message, "invalid escape `\\q`");
+        assert_eq!(
+            error(r#"a = "\uD800""#).message,
+            "invalid unicode escape `D800`"
+        );
+        assert_eq!(error("a = [1 2]").message, "expected `,` or `]` in array");
+        assert_eq!(
+            error("a = { b = 1").message,
+            "expected `,` or `}` in inline table"
+        );
+        assert_eq!(error("a = 1 b").message, "unexpected `b` at end of line");
+        assert_eq!(error("a =").message, "expected a value, found end of file");
+        assert_eq!(error("a = 1x").message, "invalid value `1x`");
+        assert_eq!(error("= 1").message, "expected a key, found `=`");
+        assert_eq!(error("[a\n").message, "expected `]`, found `\n`");
+        assert_eq!(error("a = 1\n[a]\n").message, "`a` is not a table");
+        assert_eq!(
+            error("a = 1\n[[a]]\n").message,
+            "`a` is not an array of tables"
+        );
+        assert_eq!(
+            error("\n\n\nx = @").to_string(),
+            "line 4: expected a value, found `@`"
+        );
+    }
+
+    #[test]
+    fn merging() {
+        let mut base = parse("a = 1\n[t]\nx = 1\ny = 2\n").unwrap();
+        let overlay = parse("b = 2\n[t]\ny = 3\n").unwrap();
+        merge(&mut base, &overlay);
+        assert_eq!(base, parse("a = 1\nb = 2\n[t]\nx = 1\ny = 3\n").unwrap());
+    }
+}
@@ -0,0 +1,232 @@
+//! Resource usage of child processes.
+//!
+//! On Unix the child is reaped with `wait4` so its rusage comes back with the exit status; on
+//! Windows the process handle is queried after it exits. Elsewhere there's no usage, and the
+//! exit status is the same as what `Child::wait` would return either way.
+
+use std::io;
+use std::process::{Child, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Default)]
+pub struct Usage {
+    pub user: Duration,
+    pub system: Duration,
+    /// Peak resident set size, in bytes.
+    pub peak_rss: u64,
+}
+
+impl Usage {
+    /// Combines the usage of commands that ran one after the other.
+    pub fn combine(self, other: Usage) -> Usage {
+        Usage {
+            user: self.user + other.user,
+            system: self.system + other.system,
+            peak_rss: self.peak_rss.max(other.peak_rss),
+        }
+    }
+}
+
+#[cfg(all(
+    target_pointer_width = "64",
+    any(target_os = "linux", target_os = "macos")
+))]
+mod sys {
+    use super::Usage;
+    use std::ffi::{c_int, c_long};
+    use std::io;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{Child, ExitStatus};
+    use std::time::Duration;
+
+    #[repr(C)]
+    struct Timeval {
+        tv_sec: i64,
+        #[cfg(target_os = "linux")]
+        tv_usec: i64,
+        #[cfg(target_os = "macos")]
+        tv_usec: i32,
+    }
+
+    #[repr(C)]
+    struct Rusage {
+        ru_utime: Timeval,
+        ru_stime: Timeval,
+        ru_maxrss: c_long,
+        rest: [c_long; 13],
+    }
+
+    const WNOHANG: c_int = 1;
+
+    unsafe extern "C" {
+        fn wait4(pid: c_int, status: *mut c_int, options: c_int, rusage: *mut Rusage) -> c_int;
+    }
+
+    fn duration(x: &Timeval) -> Duration {
+        Duration::from_secs(x.tv_sec.max(0) as u64) + Duration::from_micros(x.tv_usec.max(0) as u64)
+    }
+
+    pub fn wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Option<Usage>)>> {
+        let pid = child.id() as c_int;
+        let options = if block { 0 } else { WNOHANG };
+        let mut status: c_int = 0;
+        // SAFETY: all-zero is a valid `Rusage`.
+        let mut rusage: Rusage = unsafe { std::mem::zeroed() };
+        loop {
+            // SAFETY: both pointers point at live, writable values of the right type.
+            let result = unsafe { wait4(pid, &mut status, options, &mut rusage) };
+            if result == pid {
+                break;
+            }
+            if result == 0 {
+                return Ok(None);
+            }
+            let error = io::Error::last_os_error();
+            if error.kind() != io::ErrorKind::Interrupted {
+                return Err(error);
+            }
+        }
+
+        // Linux reports kilobytes, macOS bytes.
+        let unit = if cfg!(target_os = "linux") { 1024 } else { 1 };
+        let usage = Usage {
+            user: duration(&rusage.ru_utime),
+            system: duration(&rusage.ru_stime),
+            peak_rss: rusage.ru_maxrss.max(0) as u64 * unit,
+        };
+        Ok(Some((ExitStatus::from_raw(status), Some(usage))))
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use super::Usage;
+    use std::ffi::c_void;
+    use std::io;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::{Child, ExitStatus};
+    use std::time::Duration;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct FileTime {
+        low: u32,
+        high: u32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct ProcessMemoryCounters {
+        cb: u32,
+        page_fault_count: u32,
+        peak_working_set_size: usize,
+        working_set_size: usize,
+        quota_peak_paged_pool_usage: usize,
+        quota_paged_pool_usage: usize,
+        quota_peak_non_paged_pool_usage: usize,
+        quota_non_paged_pool_usage: usize,
+        pagefile_usage: usize,
+        peak_pagefile_usage: usize,
+    }
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetProcessTimes(
+            process: *mut c_void,
+            creation: *mut FileTime,
+            exit: *mut FileTime,
+            kernel: *mut FileTime,
+            user: *mut FileTime,
+        ) -> i32;
+        fn K32GetProcessMemoryInfo(
+            process: *mut c_void,
+            counters: *mut ProcessMemoryCounters,
+            cb: u32,
+        ) -> i32;
+    }
+
+    fn duration(x: &FileTime) -> Duration {
+        // FILETIME counts 100 nanosecond intervals.
+        Duration::from_nanos((((x.high as u64) << 32) | x.low as u64) * 100)
+    }
+
+    fn usage(child: &Child) -> Option<Usage> {
+        let handle = child.as_raw_handle();
+        let mut times: [FileTime; 4] = Default::default();
+        let mut counters = ProcessMemoryCounters {
+            cb: size_of::<ProcessMemoryCounters>() as u32,
+            ..Default::default()
+        };
+        let [creation, exit, kernel, user] = &mut times;
+        // SAFETY: the handle stays open until `child` is dropped, and the out pointers are live.
+        let ok = unsafe {
+            GetProcessTimes(handle, creation, exit, kernel, user) != 0
+                && K32GetProcessMemoryInfo(handle, &mut counters, counters.cb) != 0
+        };
+        ok.then(|| Usage {
+            user: duration(user),
+            system: duration(kernel),
+            peak_rss: counters.peak_working_set_size as u64,
+        })
+    }
+
+    pub fn wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Option<Usage>)>> {
+        let status = if block {
+            Some(child.wait()?)
+        } else {
+            child.try_wait()?
+        };
+        Ok(status.map(|x| (x, usage(child))))
+    }
+}
+
+#[cfg(not(any(
+    windows,
+    all(
+        target_pointer_width = "64",
+        any(target_os = "linux", target_os = "macos")
+    )
+)))]
+mod sys {
+    use super::Usage;
+    use std::io;
+    use std::process::{Child, ExitStatus};
+
+    pub fn wait(child: &mut Child, block: bool) -> io::Result<Option<(ExitStatus, Option<Usage>)>> {
+        let status = if block {
+            Some(child.wait()?)
+        } else {
+            child.try_wait()?
+        };
+        Ok(status.map(|x| (x, None)))
+    }
+}
+
+/// Like `Child::wait`, but also returns the resource usage if it's available.
+pub fn wait(child: &mut Child) -> io::Result<(ExitStatus, Option<Usage>)> {
+    Ok(sys::wait(child, true)?.expect("blocking wait always returns a status"))
+}
+
+/// Like `Child::try_wait`, but also returns the resource usage if it's available.
+pub fn try_wait(child: &mut Child) -> io::Result<Option<(ExitStatus, Option<Usage>)>> {
+    sys::wait(child, false)
+}
+
+/// Like `try_wait`, but waits up to `timeout` for the child to exit. The child is left running
+/// when it doesn't.
+pub fn wait_timeout(
+    child: &mut Child,
+    timeout: Duration,
+) -> io::Result<Option<(ExitStatus, Option<Usage>)>> {
+    let start = Instant::now();
+    loop {
+        if let Some(status) = try_wait(child)? {
+            return Ok(Some(status));
+        }
+        if start.elapsed() >= timeout {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
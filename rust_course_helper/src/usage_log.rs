@@ -0,0 +1,161 @@
+//! `--usage-log`: a local record of how runs went, for seeing which checks students fail most.
+//!
+//! Each run appends one JSON line with the time, the lab, the checker's version, and each
+//! check's result and duration. Nothing that identifies the student or the machine is in it,
+//! like paths or names, and nothing is sent anywhere. Runs on a shared machine can append at
+//! the same time, so the file is locked while a line is written. `usage-report` sums it up.
+
+use crate::json::Json;
+use crate::{CheckStatus, clock};
+use camino::Utf8Path;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+/// Appends this run's line to the log at `path`.
+pub fn append(path: &Utf8Path, lab: &str, checks: &[CheckStatus]) -> Result<(), String> {
+    let checks: Vec<Json> = checks
+        .iter()
+        .map(|x| {
+            let result = match (&x.skipped, x.passed) {
+                (Some(_), _) => "skipped",
+                (None, true) => "passed",
+                (None, false) => "failed",
+            };
+            Json::object([
+                ("name", x.name.into()),
+                ("result", result.into()),
+                ("duration_ms", (x.duration.as_millis() as u64).into()),
+            ])
+        })
+        .collect();
+    let line = Json::object([
+        ("time", clock::now().into()),
+        ("lab", lab.into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+        ("checks", checks.into()),
+    ]);
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("can't open the usage log {path}: {e}"))?;
+    // Unlocked when the file is closed.
+    file.lock()
+        .map_err(|e| format!("can't lock the usage log {path}: {e}"))?;
+    file.write_all(format!("{}\n", line.compact()).as_bytes())
+        .map_err(|e| format!("can't write the usage log {path}: {e}"))
+}
+
+/// What the log says about one check.
+#[derive(Default)]
+struct CheckStats {
+    name: String,
+    runs: usize,
+    failed: usize,
+    skipped: usize,
+    durations: Vec<u64>,
+}
+
+/// Prints how often each check failed in the log at `path`, for `lab` or every lab.
+pub fn report(path: &Utf8Path, lab: Option<&str>) -> Result<(), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("can't read {path}: {e}"))?;
+    let mut runs = 0;
+    let mut unreadable = 0;
+    let mut labs: Vec<String> = Vec::new();
+    let mut stats: Vec<CheckStats> = Vec::new();
+    for line in text.lines().filter(|x| !x.trim().is_empty()) {
+        let Ok(run) = Json::parse(line) else {
+            // A run that was killed while writing leaves half a line.
+            unreadable += 1;
+            continue;
+        };
+        let (Some(run_lab), Some(checks)) = (
+            run.get("lab").and_then(Json::as_str),
+            run.get("checks").and_then(Json::as_array),
+        ) else {
+            unreadable += 1;
+            continue;
+        };
+        if lab.is_some_and(|x| x != run_lab) {
+            continue;
+        }
+        runs += 1;
+        if !labs.iter().any(|x| x == run_lab) {
+            labs.push(run_lab.to_string());
+        }
+        for check in checks {
+            let (Some(name), Some(result)) = (
+                check.get("name").and_then(Json::as_str),
+                check.get("result").and_then(Json::as_str),
+            ) else {
+                continue;
+            };
+            let index = match stats.iter().position(|x| x.name == name) {
+                Some(x) => x,
+                None => {
+                    stats.push(CheckStats {
+                        name: name.to_string(),
+                        ..CheckStats::default()
+                    });
+                    stats.len() - 1
+                }
+            };
+            let entry = &mut stats[index];
+            match result {
+                "skipped" => {
+                    entry.skipped += 1;
+                    continue;
+                }
+                "failed" => entry.failed += 1,
+                _ => {}
+            }
+            entry.runs += 1;
+            if let Some(Json::Number(x)) = check.get("duration_ms") {
+                entry.durations.push(*x as u64);
+            }
+        }
+    }
+
+    if unreadable > 0 {
+        say!("{unreadable} lines of {path} can't be read and were left out");
+    }
+    if runs == 0 {
+        return Err(match lab {
+            Some(x) => format!("{path} has no runs of {x}"),
+            None => format!("{path} has no runs"),
+        });
+    }
+    labs.sort();
+    say!("{runs} runs of {}", labs.join(", "));
+    // Most failed first.
+    stats.sort_by(|a, b| {
+        let rate = |x: &CheckStats| x.failed as f64 / x.runs.max(1) as f64;
+        rate(b).total_cmp(&rate(a)).then(a.name.cmp(&b.name))
+    });
+    say!(
+        "{:>6} {:>6} {:>7} {:>7} {:>9}  check",
+        "runs",
+        "failed",
+        "failed%",
+        "skipped",
+        "median"
+    );
+    for mut check in stats {
+        check.durations.sort();
+        let median = match check.durations.get(check.durations.len() / 2) {
+            Some(x) => format!("{:.2}s", *x as f64 / 1000.0),
+            None => "-".into(),
+        };
+        say!(
+            "{:>6} {:>6} {:>6.0}% {:>7} {:>9}  {}",
+            check.runs,
+            check.failed,
+            check.failed as f64 * 100.0 / check.runs.max(1) as f64,
+            check.skipped,
+            median,
+            check.name
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Zip archives of stored, uncompressed files, as described in PKWARE's APPNOTE.TXT. Every
+//! system opens those, and the checker's are small enough that compressing them isn't worth
+//! an implementation of deflate.
+
+use crate::datetime;
+
+/// CRC-32 as zip uses it: the IEEE polynomial, reflected.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0xedb88320,
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+/// The MS-DOS time and date zip stores, which can't be before 1980.
+fn dos_time(at: u64) -> (u16, u16) {
+    let (year, month, day) = datetime::civil_from_days((at / 86400) as i64);
+    if year < 1980 {
+        return (0, 1 << 5 | 1);
+    }
+    let seconds = at % 86400;
+    let time = (seconds / 3600) << 11 | (seconds % 3600 / 60) << 5 | (seconds % 60 / 2);
+    let date = ((year - 1980).min(127) << 9 | month << 5 | day) as u16;
+    (time as u16, date)
+}
+
+/// An archive of `files`, each a path in the archive and its contents, all modified `at`.
+pub fn archive(files: &[(String, Vec<u8>)], at: u64) -> Vec<u8> {
+    let (time, date) = dos_time(at);
+    // Version 2.0, and bit 11 of the flags for UTF-8 names.
+    let common = |out: &mut Vec<u8>, name: &str, data: &[u8]| {
+        for x in [20u16, 1 << 11, 0, time, date] {
+            out.extend(x.to_le_bytes());
+        }
+        out.extend(crc32(data).to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend((data.len() as u32).to_le_bytes());
+        out.extend((name.len() as u16).to_le_bytes());
+        out.extend(0u16.to_le_bytes());
+    };
+
+    let mut out = Vec::new();
+    let mut offsets = Vec::new();
+    for (name, data) in files {
+        offsets.push(out.len() as u32);
+        out.extend(0x04034b50u32.to_le_bytes());
+        common(&mut out, name, data);
+        out.extend(name.as_bytes());
+        out.extend(data);
+    }
+    let directory = out.len() as u32;
+    for ((name, data), offset) in files.iter().zip(offsets) {
+        out.extend(0x02014b50u32.to_le_bytes());
+        // Made by version 2.0.
+        out.extend(20u16.to_le_bytes());
+        common(&mut out, name, data);
+        // The comment's length, the disk, and the attributes.
+        out.extend([0; 10]);
+        out.extend(offset.to_le_bytes());
+        out.extend(name.as_bytes());
+    }
+    let directory_len = out.len() as u32 - directory;
+    out.extend(0x06054b50u32.to_le_bytes());
+    out.extend([0; 4]);
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend((files.len() as u16).to_le_bytes());
+    out.extend(directory_len.to_le_bytes());
+    out.extend(directory.to_le_bytes());
+    out.extend(0u16.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(data[at..at + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], at: usize) -> u32 {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn crc32_known_answers() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414fa339
+        );
+    }
+
+    #[test]
+    fn dos_times() {
+        // 2026-10-20 12:34:56 UTC; seconds are stored halved.
+        let (time, date) = dos_time(1_792_499_696);
+        assert_eq!((time >> 11, time >> 5 & 63, time & 31), (12, 34, 28));
+        assert_eq!((date >> 9, date >> 5 & 15, date & 31), (46, 10, 20));
+        assert_eq!(dos_time(0), (0, 1 << 5 | 1));
+    }
+
+    #[test]
+    fn archive_structure() {
+        let files = [
+            ("report.json".to_string(), b"{}\n".to_vec()),
+            ("logs/übung.txt".to_string(), b"hello".to_vec()),
+        ];
+        let zip = archive(&files, 1_792_499_696);
+
+        // The end of the central directory is last, with no comment.
+        let end = zip.len() - 22;
+        assert_eq!(u32_at(&zip, end), 0x06054b50);
+        assert_eq!((u16_at(&zip, end + 8), u16_at(&zip, end + 10)), (2, 2));
+        let directory_len = u32_at(&zip, end + 12) as usize;
+        let mut entry = u32_at(&zip, end + 16) as usize;
+        assert_eq!(entry + directory_len, end);
+
+        for (name, data) in &files {
+            assert_eq!(u32_at(&zip, entry), 0x02014b50);
+            assert_eq!(u16_at(&zip, entry + 8), 1 << 11);
+            assert_eq!(u32_at(&zip, entry + 16), crc32(data));
+            assert_eq!(u32_at(&zip, entry + 20) as usize, data.len());
+            let name_len = u16_at(&zip, entry + 28) as usize;
+            assert_eq!(&zip[entry + 46..entry + 46 + name_len], name.as_bytes());
+
+            // Each entry points at its file's local header, which says the same and is
+            // followed by the name and the data as they are.
+            let local = u32_at(&zip, entry + 42) as usize;
+            assert_eq!(u32_at(&zip, local), 0x04034b50);
+            assert_eq!(zip[local + 4..local + 30], zip[entry + 6..entry + 32]);
+            let start = local + 30 + name_len;
+            assert_eq!(&zip[local + 30..start], name.as_bytes());
+            assert_eq!(&zip[start..start + data.len()], data.as_slice());
+            entry += 46 + name_len;
+        }
+        assert_eq!(entry, end);
+    }
+}
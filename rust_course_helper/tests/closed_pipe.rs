@@ -0,0 +1,51 @@
+//! Output into a pipe whose reader is gone, like `rust_course_helper ... | head -1` once `head`
+//! exits, is dropped without ending the run, so the exit code is still the verdict.
+
+use std::io::Read;
+use std::process::{Command, Stdio};
+
+/// Runs the checker with `args`, closing its stdout before it writes anything. Its exit code, and
+/// what it printed on stderr.
+fn run_with_closed_stdout(args: &[&str]) -> (Option<i32>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust_course_helper"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdout.take());
+    let mut stderr = String::new();
+    child
+        .stderr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    (child.wait().unwrap().code(), stderr)
+}
+
+#[test]
+fn successful_runs_still_succeed() {
+    let (code, stderr) = run_with_closed_stdout(&["--lab", "lab01", "--show-plan"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert_eq!(stderr, "");
+}
+
+#[test]
+fn failed_runs_still_fail() {
+    let repo = std::env::temp_dir().join("rust_course_helper_closed_pipe_missing_repo");
+    let repo = repo.to_str().unwrap();
+    for format in ["human", "json", "short"] {
+        let args = ["--repo", repo, "--lab", "lab01", "--format", format];
+        let (code, stderr) = run_with_closed_stdout(&args);
+        assert_eq!(code, Some(1), "{format}: {stderr}");
+        // A broken pipe isn't worth a message, and mustn't be a panic.
+        assert!(!stderr.contains("panicked"), "{format}: {stderr}");
+        assert!(
+            !stderr.contains("can't write to stdout"),
+            "{format}: {stderr}"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! `--ci github` groups each check's output in the log, annotates the problems, and appends the
+//! step summary and the verdict to the files Actions names in its variables.
+
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn runs_in_github_actions() {
+    let dir = std::env::temp_dir().join("rust_course_helper_github_ci");
+    let _ = fs::remove_dir_all(&dir);
+    let repo = dir.join("repo");
+    fs::create_dir_all(repo.join("lab01")).unwrap();
+    fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&repo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    // Earlier steps' summaries and outputs are kept.
+    let summary = dir.join("summary.md");
+    let outputs = dir.join("outputs");
+    fs::write(&summary, "## earlier step\n").unwrap();
+    fs::write(&outputs, "earlier=1\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rust_course_helper"))
+        .args(["--lab", "lab01", "--ci", "github", "--repo"])
+        .arg(&repo)
+        .env("NO_COLOR", "1")
+        .env("GITHUB_STEP_SUMMARY", &summary)
+        .env("GITHUB_OUTPUT", &outputs)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    let group = lines.iter().position(|x| *x == "::group::lab_folder");
+    let group = group.expect(&stdout);
+    assert_eq!(lines[group + 1], "::endgroup::", "{stdout}");
+    assert_eq!(
+        lines.iter().filter(|x| x.starts_with("::group::")).count(),
+        lines.iter().filter(|x| **x == "::endgroup::").count(),
+    );
+    // Paths are relative to the checkout, and the help is on its own line.
+    assert!(
+        lines.contains(
+            &"::error title=lab_folder,file=lab01::lab01 is empty%0Ahelp: run `cargo init` inside it to start the lab"
+        ),
+        "{stdout}"
+    );
+
+    let summary = fs::read_to_string(&summary).unwrap();
+    assert!(
+        summary.starts_with("## earlier step\n## lab01: failure\n"),
+        "{summary}"
+    );
+    assert!(summary.contains("| lab_folder | **failed** |"), "{summary}");
+    assert!(
+        summary.contains("- **error** [lab_folder] lab01 is empty (`lab01`)"),
+        "{summary}"
+    );
+    assert_eq!(
+        fs::read_to_string(&outputs).unwrap(),
+        "earlier=1\nverdict=failure\n"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,86 @@
+//! Lab folders that aren't a Cargo project yet get one problem saying what's in them, and the
+//! checks that need the project are skipped instead of failing too.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A repo whose `lab01` folder holds `files`, which are paths and their contents.
+fn repo(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let repo = std::env::temp_dir().join(format!("rust_course_helper_lab_folder_{name}"));
+    let _ = fs::remove_dir_all(&repo);
+    fs::create_dir_all(repo.join("lab01")).unwrap();
+    fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+    for (path, data) in files {
+        let path = repo.join("lab01").join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, data).unwrap();
+    }
+    let status = Command::new("git")
+        .args(["init", "-q"])
+        .current_dir(&repo)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    repo
+}
+
+/// The problems a run on `repo` finds, one `[check] text` line each, and its JSON report.
+fn run(repo: &Path) -> (Vec<String>, String) {
+    let run = |format: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_rust_course_helper"))
+            .args(["--lab", "lab01", "--format", format, "--repo"])
+            .arg(repo)
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let problems = run("short")
+        .lines()
+        .filter_map(|x| Some(x.split_once(": error: ")?.1.to_string()))
+        .collect();
+    (problems, run("json"))
+}
+
+fn assert_only_problem(repo: &Path, expected: &str) {
+    let (problems, json) = run(repo);
+    assert_eq!(problems, [format!("[lab_folder] {expected}")]);
+    // The checks that build the lab wait for it to exist.
+    assert!(
+        json.contains(r#""skipped": "`lab_folder` failed""#),
+        "{json}"
+    );
+    let _ = fs::remove_dir_all(repo);
+}
+
+#[test]
+fn empty_folder() {
+    assert_only_problem(&repo("empty", &[]), "lab01 is empty");
+}
+
+#[test]
+fn sources_without_a_manifest() {
+    let repo = repo(
+        "sources",
+        &[("main.rs", "fn main() {}\n"), ("src/lib.rs", "")],
+    );
+    assert_only_problem(&repo, "lab01 contains Rust files but no Cargo.toml");
+}
+
+#[test]
+fn only_other_files() {
+    let repo = repo(
+        "other_files",
+        &[
+            ("notes.txt", "todo\n"),
+            ("statement.pdf", ""),
+            (".hidden", ""),
+        ],
+    );
+    assert_only_problem(
+        &repo,
+        "lab01 has no Rust project, only: notes.txt, statement.pdf",
+    );
+}
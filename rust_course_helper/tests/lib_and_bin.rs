@@ -0,0 +1,109 @@
+//! A lab with a library and a thin binary on top of it: each check looks at the target it's
+//! about, and what both targets compile is reported once.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const FILES: &[(&str, &str)] = &[
+    (
+        "lab01/Cargo.toml",
+        include_str!("../fixtures/lib_and_bin/lab01/Cargo.toml.in"),
+    ),
+    (
+        "lab01/src/lib.rs",
+        include_str!("../fixtures/lib_and_bin/lab01/src/lib.rs"),
+    ),
+    (
+        "lab01/src/main.rs",
+        include_str!("../fixtures/lib_and_bin/lab01/src/main.rs"),
+    ),
+];
+
+fn git(repo: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(repo)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?}");
+}
+
+/// The fixture as a committed repo, and a config that turns on the checks that pick a target.
+fn setup(dir: &Path) -> (PathBuf, PathBuf) {
+    let repo = dir.join("repo");
+    for (path, text) in FILES {
+        let path = repo.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+    git(&repo, &["init", "-q"]);
+    git(&repo, &["add", "."]);
+    git(&repo, &["commit", "-q", "-m", "lab"]);
+
+    let api = dir.join("api.rs");
+    fs::write(&api, include_str!("../fixtures/lib_and_bin/api.rs")).unwrap();
+    let config = dir.join("course.toml");
+    let api = api.to_str().unwrap().replace('\\', "/");
+    let text = format!(
+        "[defaults]\napi_file = \"{api}\"\nstdin_eof_check = true\nargument_templates = [[]]\n\
+        warning_checks = [\"compiler_warnings\"]\n"
+    );
+    fs::write(&config, text).unwrap();
+    (repo, config)
+}
+
+fn run(repo: &Path, config: &Path, format: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rust_course_helper"))
+        .args(["--lab", "lab01", "--format", format, "--repo"])
+        .arg(repo)
+        .arg("--config")
+        .arg(config)
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(output.status.code(), Some(0), "{stdout}");
+    stdout
+}
+
+#[test]
+fn each_check_picks_its_target() {
+    let dir = std::env::temp_dir().join("rust_course_helper_lib_and_bin");
+    let _ = fs::remove_dir_all(&dir);
+    let (repo, config) = setup(&dir);
+
+    // The warning is in the library's tests, which the binary's tests compile too. It's only a
+    // warning, so the checks after it run.
+    let short = run(&repo, &config, "short");
+    let problems: Vec<&str> = short
+        .lines()
+        .filter_map(|x| Some(x.split_once(":-: ")?.1))
+        .collect();
+    assert_eq!(
+        problems,
+        [
+            "warning: [compiler_warnings] warning in test code: unused variable: `unused` (src/lib.rs:11)"
+        ],
+        "{short}"
+    );
+
+    let json = run(&repo, &config, "json");
+    // The API is the library's, the binary is the one that's named after the lab and reads
+    // stdin, and the tests run.
+    for check in ["api", "binary_names", "stdin_eof", "extra_args", "tests"] {
+        let start = json.find(&format!("\"name\": \"{check}\"")).expect(check);
+        let status = &json[start..start + json[start..].find('}').unwrap()];
+        assert!(status.contains(r#""passed": true"#), "{status}");
+        assert!(status.contains(r#""skipped": null"#), "{status}");
+    }
+    // Cargo was asked for the targets once, for all the checks.
+    assert_eq!(
+        json.matches(r#""command": "cargo metadata""#).count(),
+        1,
+        "{json}"
+    );
+    let _ = fs::remove_dir_all(&dir);
+}